@@ -23,6 +23,7 @@ if (([string]::IsNullOrWhiteSpace($env:INTELLI_SKIP_PROFILE)) -Or ($env:INTELLI_
         Add-Content $Profile "# `$env:INTELLI_SEARCH_HOTKEY = 'Ctrl+Spacebar'"
         Add-Content $Profile "# `$env:INTELLI_BOOKMARK_HOTKEY = 'Ctrl+b'"
         Add-Content $Profile "# `$env:INTELLI_LABEL_HOTKEY = 'Ctrl+l'"
+        Add-Content $Profile "# `$env:INTELLI_SKIP_HISTORY_SETUP = '0'"
         Add-Content $Profile ". `$env:INTELLI_HOME\bin\intelli-shell.ps1"
     }
     Write-Host "Close this terminal and open a new one for the changes to take effect"
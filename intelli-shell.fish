@@ -1,18 +1,31 @@
+set -gx INTELLI_SHELL fish
+
 function intelli-shell --description 'IntelliShell'
   $INTELLI_HOME/bin/intelli-shell $argv;
 end
 
 function _intelli_exec
     set p_lines (fish_prompt | string split0 | wc -l)
+    # The cursor is placed at the marker of the output, if any
+    set -lx INTELLI_CURSOR_MARKER 1
+    set -l intelli_marker '{{%cursor%}}'
     # Swap stderr and stdout
-    if test (math $p_lines + 0) -gt "1"
+    if test "$INTELLI_INLINE" = "0"
+      set INTELLI_OUTPUT (intelli-shell $argv 3>&1 1>&2 2>&3)
+    else if test (math $p_lines + 0) -gt "1"
       set INTELLI_OUTPUT (intelli-shell --inline --inline-extra-line  $argv 3>&1 1>&2 2>&3)
     else 
       set INTELLI_OUTPUT (intelli-shell --inline  $argv 3>&1 1>&2 2>&3)
     end
-    # Replace line
+    # Replace line, sensitive commands are prefixed with a space so fish keeps them out of the history
     commandline -f repaint
-    commandline -r "$INTELLI_OUTPUT"
+    if string match -q -- "*$intelli_marker*" "$INTELLI_OUTPUT"
+      set -l parts (string split -m1 -- $intelli_marker "$INTELLI_OUTPUT")
+      commandline -r -- "$parts[1]$parts[2]"
+      commandline -C (string length -- "$parts[1]")
+    else
+      commandline -r "$INTELLI_OUTPUT"
+    end
 end
 
 function _intelli_search 
@@ -30,6 +43,55 @@ function _intelli_label
     _intelli_exec label "$LINE"
 end
 
+set -g _intelli_alias_prefix ""
+set -g _intelli_alias_ix 0
+set -g _intelli_alias_last ""
+
+function _intelli_cycle_alias
+    set LINE (commandline)
+    # Pressing it again right after an expansion cycles through the next candidates
+    if test -n "$_intelli_alias_last" -a "$LINE" = "$_intelli_alias_last"
+      set -g _intelli_alias_ix (math $_intelli_alias_ix + 1)
+    else
+      set -g _intelli_alias_prefix "$LINE"
+      set -g _intelli_alias_ix 0
+    end
+    # Output is written to stderr
+    set EXPANSION (intelli-shell cycle-alias --index $_intelli_alias_ix "$_intelli_alias_prefix" 2>&1 1>/dev/null)
+    if test -n "$EXPANSION"
+      commandline -r "$EXPANSION"
+      set -g _intelli_alias_last "$EXPANSION"
+    else
+      # Fallback to the regular completion, so it can be bound to tab
+      set -g _intelli_alias_last ""
+      commandline -f complete
+    end
+end
+
+# Fish only renders its own autosuggestions, so the suggestion is filled in with the right arrow at the end of the line
+function _intelli_accept_suggestion
+    set LINE (commandline | string collect)
+    if test -n "$LINE" -a (commandline -C) -ge (string length -- "$LINE")
+      # Output is written to stderr
+      set SUGGESTION (intelli-shell suggest-inline "$LINE" 2>&1 1>/dev/null | string collect)
+      if test -n "$SUGGESTION" -a (string sub -l (string length -- "$LINE") -- "$SUGGESTION") = "$LINE"
+        commandline -r -- "$SUGGESTION"
+        commandline -f end-of-line
+        return
+      end
+    end
+    # Fallback to the regular behavior, accepting fish's own autosuggestion if any
+    commandline -f forward-char
+end
+
+if test "$INTELLI_RECORD_HISTORY" = "1"
+  # Record the executed commands on the background, so they can be bookmarked later
+  function _intelli_record --on-event fish_preexec
+    intelli-shell record -- "$argv[1]" &>/dev/null &
+    disown
+  end
+end
+
 function fish_user_key_bindings
   if [ "$INTELLI_SKIP_ESC_BIND" != "1" ] 
     bind --preset \e 'kill-whole-line'
@@ -49,4 +111,11 @@ function fish_user_key_bindings
   else
     bind \cl '_intelli_label'
   end
+  if test -n "$INTELLI_ALIAS_HOTKEY"
+    bind $INTELLI_ALIAS_HOTKEY '_intelli_cycle_alias'
+  end
+  if test "$INTELLI_INLINE_SUGGESTIONS" = "1"
+    bind \e\[C '_intelli_accept_suggestion'
+    bind \eOC '_intelli_accept_suggestion'
+  end
 end
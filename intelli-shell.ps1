@@ -1,6 +1,26 @@
+$env:INTELLI_SHELL = "powershell"
+
 $IntelliSearchChord = if ($null -eq $env:INTELLI_SEARCH_HOTKEY) { 'Ctrl+Spacebar' } else { $env:INTELLI_SEARCH_HOTKEY }
 $IntelliBookmarkChord = if ($null -eq $env:INTELLI_BOOKMARK_HOTKEY) { 'Ctrl+b' } else { $env:INTELLI_BOOKMARK_HOTKEY }
 $IntelliLabelChord = if ($null -eq $env:INTELLI_LABEL_HOTKEY) { 'Ctrl+l' } else { $env:INTELLI_LABEL_HOTKEY }
+# The interface is rendered inline unless INTELLI_INLINE=0, like on the other shells
+$IntelliInlineArgs = if ($env:INTELLI_INLINE -eq '0') { '' } else { '--inline ' }
+
+# Sensitive commands are prefixed with a space, to keep them out of the history
+if ($env:INTELLI_SKIP_HISTORY_SETUP -ne '1') {
+    $IntelliHistoryHandler = (Get-PSReadLineOption).AddToHistoryHandler
+    $IntelliAddToHistory = {
+        param([string]$line)
+        if ($line.StartsWith(' ')) {
+            return $false
+        }
+        if ($null -ne $IntelliHistoryHandler) {
+            return $IntelliHistoryHandler.Invoke($line)
+        }
+        return $true
+    }.GetNewClosure()
+    Set-PSReadLineOption -AddToHistoryHandler $IntelliAddToHistory
+}
 
 Set-PSReadLineKeyHandler -Chord $IntelliSearchChord -BriefDescription "IntelliShell Search" -Description "Searches for a bookmarked command" -ScriptBlock {
     $line = $null
@@ -9,7 +29,7 @@ Set-PSReadLineKeyHandler -Chord $IntelliSearchChord -BriefDescription "IntelliSh
 
     $TempFile = New-TemporaryFile
     $line = $line -replace '"','""""""""""""'
-    $Command = 'intelli-shell.exe --file-output=""""' + $TempFile.FullName + '"""" search """"' + $line + '""""' 
+    $Command = '$env:INTELLI_CURSOR_MARKER=1; intelli-shell.exe ' + $IntelliInlineArgs + '--file-output=""""' + $TempFile.FullName + '"""" search """"' + $line + '""""' 
     Start-Process powershell.exe -Wait -NoNewWindow -ArgumentList "-command", "$Command"
     $IntelliOutput = Get-Content -Raw $TempFile
     Remove-Item $TempFile
@@ -18,7 +38,15 @@ Set-PSReadLineKeyHandler -Chord $IntelliSearchChord -BriefDescription "IntelliSh
     [Microsoft.PowerShell.PSConsoleReadLine]::BeginningOfLine()
     [Microsoft.PowerShell.PSConsoleReadLine]::RevertLine()
     if (-Not [string]::IsNullOrWhiteSpace($IntelliOutput)) {
+        # The cursor is placed at the marker of the output, if any
+        $IntelliCursor = $IntelliOutput.IndexOf('{{%cursor%}}')
+        if ($IntelliCursor -ge 0) {
+            $IntelliOutput = $IntelliOutput.Remove($IntelliCursor, '{{%cursor%}}'.Length)
+        }
         [Microsoft.PowerShell.PSConsoleReadLine]::Insert($IntelliOutput)
+        if ($IntelliCursor -ge 0) {
+            [Microsoft.PowerShell.PSConsoleReadLine]::SetCursorPosition($IntelliCursor)
+        }
     }
 }
 
@@ -29,9 +57,9 @@ Set-PSReadLineKeyHandler -Chord $IntelliBookmarkChord -BriefDescription "Intelli
 
     $TempFile = New-TemporaryFile
     $line = $line -replace '"','""""""""""""'
-    $Command = 'intelli-shell.exe --file-output=""""' + $TempFile.FullName + '"""" new -c """"' + $line + '""""' 
+    $Command = '$env:INTELLI_CURSOR_MARKER=1; intelli-shell.exe ' + $IntelliInlineArgs + '--file-output=""""' + $TempFile.FullName + '"""" new -c """"' + $line + '""""' 
 	if ([string]::IsNullOrWhiteSpace($line)) {
-        $Command = 'intelli-shell.exe --file-output=""""' + $TempFile.FullName + '"""" new' 
+        $Command = '$env:INTELLI_CURSOR_MARKER=1; intelli-shell.exe ' + $IntelliInlineArgs + '--file-output=""""' + $TempFile.FullName + '"""" new' 
     }
     Start-Process powershell.exe -Wait -NoNewWindow -ArgumentList "-command", "$Command"
     $IntelliOutput = Get-Content -Raw $TempFile
@@ -41,7 +69,15 @@ Set-PSReadLineKeyHandler -Chord $IntelliBookmarkChord -BriefDescription "Intelli
     [Microsoft.PowerShell.PSConsoleReadLine]::BeginningOfLine()
     [Microsoft.PowerShell.PSConsoleReadLine]::RevertLine()
     if (-Not [string]::IsNullOrWhiteSpace($IntelliOutput)) {
+        # The cursor is placed at the marker of the output, if any
+        $IntelliCursor = $IntelliOutput.IndexOf('{{%cursor%}}')
+        if ($IntelliCursor -ge 0) {
+            $IntelliOutput = $IntelliOutput.Remove($IntelliCursor, '{{%cursor%}}'.Length)
+        }
         [Microsoft.PowerShell.PSConsoleReadLine]::Insert($IntelliOutput)
+        if ($IntelliCursor -ge 0) {
+            [Microsoft.PowerShell.PSConsoleReadLine]::SetCursorPosition($IntelliCursor)
+        }
     }
 }
 
@@ -52,7 +88,7 @@ Set-PSReadLineKeyHandler -Chord $IntelliLabelChord -BriefDescription "IntelliShe
 
     $TempFile = New-TemporaryFile
     $line = $line -replace '"','""""""""""""'
-    $Command = 'intelli-shell.exe --file-output=""""' + $TempFile.FullName + '"""" label """"' + $line + '""""' 
+    $Command = '$env:INTELLI_CURSOR_MARKER=1; intelli-shell.exe ' + $IntelliInlineArgs + '--file-output=""""' + $TempFile.FullName + '"""" label """"' + $line + '""""' 
     Start-Process powershell.exe -Wait -NoNewWindow -ArgumentList "-command", "$Command"
     $IntelliOutput = Get-Content -Raw $TempFile
     Remove-Item $TempFile
@@ -61,6 +97,14 @@ Set-PSReadLineKeyHandler -Chord $IntelliLabelChord -BriefDescription "IntelliShe
     [Microsoft.PowerShell.PSConsoleReadLine]::BeginningOfLine()
     [Microsoft.PowerShell.PSConsoleReadLine]::RevertLine()
     if (-Not [string]::IsNullOrWhiteSpace($IntelliOutput)) {
+        # The cursor is placed at the marker of the output, if any
+        $IntelliCursor = $IntelliOutput.IndexOf('{{%cursor%}}')
+        if ($IntelliCursor -ge 0) {
+            $IntelliOutput = $IntelliOutput.Remove($IntelliCursor, '{{%cursor%}}'.Length)
+        }
         [Microsoft.PowerShell.PSConsoleReadLine]::Insert($IntelliOutput)
+        if ($IntelliCursor -ge 0) {
+            [Microsoft.PowerShell.PSConsoleReadLine]::SetCursorPosition($IntelliCursor)
+        }
     }
 }
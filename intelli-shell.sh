@@ -2,6 +2,11 @@ if [[ -n "$ZSH_VERSION" ]]; then
     # zshell
     # https://zsh.sourceforge.io/Guide/zshguide04.html
 
+    export INTELLI_SHELL=zsh
+
+    # Sensitive commands are prefixed with a space, to keep them out of the history
+    if [[ "${INTELLI_SKIP_HISTORY_SETUP:-0}" == "0" ]]; then setopt HIST_IGNORE_SPACE; fi
+
     # Default bindings
     intelli_search_key="${INTELLI_SEARCH_HOTKEY:-^@}"
     intelli_bookmark_key="${INTELLI_BOOKMARK_HOTKEY:-^b}"
@@ -9,9 +14,14 @@ if [[ -n "$ZSH_VERSION" ]]; then
 
     function _intelli_exec {
         p_lines=$(echo "$PS1" | wc -l)
+        # The cursor is placed at the marker of the output, if any
+        local -x INTELLI_CURSOR_MARKER=1
+        local intelli_marker='{{%cursor%}}'
         
         # Swap stderr and stdout 
-        if [ "$p_lines" -gt "1" ]; then
+        if [[ "${INTELLI_INLINE:-1}" == "0" ]]; then
+            INTELLI_OUTPUT=$(intelli-shell "$@" 3>&1 1>&2 2>&3)
+        elif [ "$p_lines" -gt "1" ]; then
             INTELLI_OUTPUT=$(intelli-shell --inline --inline-extra-line "$@" 3>&1 1>&2 2>&3)
         else
             INTELLI_OUTPUT=$(intelli-shell --inline "$@" 3>&1 1>&2 2>&3)
@@ -19,8 +29,14 @@ if [[ -n "$ZSH_VERSION" ]]; then
 
         # Rewrite line
         zle reset-prompt
-        BUFFER=$INTELLI_OUTPUT
-        zle end-of-line
+        if [[ "$INTELLI_OUTPUT" == *"$intelli_marker"* ]]; then
+            local intelli_prefix="${INTELLI_OUTPUT%%"$intelli_marker"*}"
+            BUFFER="${intelli_prefix}${INTELLI_OUTPUT#*"$intelli_marker"}"
+            CURSOR=${#intelli_prefix}
+        else
+            BUFFER=$INTELLI_OUTPUT
+            zle end-of-line
+        fi
     }
 
     function _intelli_search {
@@ -34,6 +50,108 @@ if [[ -n "$ZSH_VERSION" ]]; then
     function _intelli_label {
         _intelli_exec label "$BUFFER"
     }
+
+    if [[ "${INTELLI_INLINE_SUGGESTIONS:-0}" == "1" ]]; then
+        _intelli_ghost_highlight=""
+        # Buffer of the last lookup along with its suggestion, so redraws of the same buffer don't look it up again
+        _intelli_ghost_buffer=""
+        _intelli_suggestion=""
+
+        # The daemon, when running, is asked through its socket without forking
+        if [[ -n "$INTELLI_DAEMON_SOCKET" ]]; then zmodload zsh/net/socket 2>/dev/null; fi
+
+        # Sets the suggestion for the given buffer on _intelli_suggestion
+        function _intelli_suggest_inline {
+            local intelli_fd
+            _intelli_suggestion=""
+            if [[ -S "$INTELLI_DAEMON_SOCKET" ]] && zsocket "$INTELLI_DAEMON_SOCKET" 2>/dev/null; then
+                intelli_fd=$REPLY
+                print -rn -u $intelli_fd -- "suggest-inline"$'\0'"zsh"$'\0'"$1"$'\0'
+                read -r -d '' -t 1 -u $intelli_fd _intelli_suggestion
+                exec {intelli_fd}>&-
+            else
+                # Output is written to stderr
+                _intelli_suggestion=$(intelli-shell suggest-inline "$1" 2>&1 1>/dev/null)
+            fi
+        }
+
+        function _intelli_ghost_text {
+            # Clear any previous suggestion
+            POSTDISPLAY=""
+            if [[ -n "$_intelli_ghost_highlight" ]]; then
+                region_highlight=("${(@)region_highlight:#$_intelli_ghost_highlight}")
+                _intelli_ghost_highlight=""
+            fi
+            # Only suggest when the cursor is at the end of a line long enough
+            if [[ -n "$BUFFER" && ${#BUFFER} -ge ${INTELLI_INLINE_SUGGESTIONS_MIN_LENGTH:-2} && $CURSOR -eq ${#BUFFER} ]]; then
+                if [[ "$BUFFER" != "$_intelli_ghost_buffer" ]]; then
+                    _intelli_ghost_buffer="$BUFFER"
+                    _intelli_suggest_inline "$BUFFER"
+                fi
+                if [[ -n "$_intelli_suggestion" && "$_intelli_suggestion" == "$BUFFER"* ]]; then
+                    POSTDISPLAY="${_intelli_suggestion#"$BUFFER"}"
+                    _intelli_ghost_highlight="${#BUFFER} $(( ${#BUFFER} + ${#POSTDISPLAY} )) fg=8"
+                    region_highlight+=("$_intelli_ghost_highlight")
+                fi
+            fi
+        }
+
+        function _intelli_accept_ghost_text {
+            if [[ -n "$POSTDISPLAY" ]]; then
+                BUFFER="$BUFFER$POSTDISPLAY"
+                POSTDISPLAY=""
+                zle end-of-line
+            else
+                zle forward-char
+            fi
+        }
+
+        autoload -Uz add-zle-hook-widget
+        add-zle-hook-widget line-pre-redraw _intelli_ghost_text
+        zle -N _intelli_accept_ghost_text
+        bindkey "^[[C" _intelli_accept_ghost_text
+        bindkey "^[OC" _intelli_accept_ghost_text
+    fi
+
+    if [[ -n "$INTELLI_ALIAS_HOTKEY" ]]; then
+        _intelli_alias_prefix=""
+        _intelli_alias_ix=0
+        _intelli_alias_last=""
+
+        function _intelli_cycle_alias {
+            # Pressing it again right after an expansion cycles through the next candidates
+            if [[ -n "$_intelli_alias_last" && "$BUFFER" == "$_intelli_alias_last" ]]; then
+                _intelli_alias_ix=$(( _intelli_alias_ix + 1 ))
+            else
+                _intelli_alias_prefix="$BUFFER"
+                _intelli_alias_ix=0
+            fi
+            # Output is written to stderr
+            local expansion=$(intelli-shell cycle-alias --index "$_intelli_alias_ix" "$_intelli_alias_prefix" 2>&1 1>/dev/null)
+            if [[ -n "$expansion" ]]; then
+                BUFFER="$expansion"
+                _intelli_alias_last="$expansion"
+                zle end-of-line
+            else
+                # Fallback to the regular completion, so it can be bound to tab
+                _intelli_alias_last=""
+                zle expand-or-complete
+            fi
+        }
+
+        zle -N _intelli_cycle_alias
+        bindkey "$INTELLI_ALIAS_HOTKEY" _intelli_cycle_alias
+    fi
+
+    if [[ "${INTELLI_RECORD_HISTORY:-0}" == "1" ]]; then
+        # Record the executed commands on the background, so they can be bookmarked later
+        function _intelli_record {
+            ( intelli-shell record -- "$1" &>/dev/null & )
+        }
+
+        autoload -Uz add-zsh-hook
+        add-zsh-hook preexec _intelli_record
+    fi
     
     if [[ "${INTELLI_SKIP_ESC_BIND:-0}" == "0" ]]; then bindkey "\e" kill-whole-line; fi
     zle -N _intelli_search
@@ -47,17 +165,37 @@ elif [[ -n "$BASH" ]]; then
     # bash
     # https://www.gnu.org/software/bash/manual/html_node/Bash-Builtins.html#index-bind
 
+    export INTELLI_SHELL=bash
+
+    # Sensitive commands are prefixed with a space, to keep them out of the history
+    if [[ "${INTELLI_SKIP_HISTORY_SETUP:-0}" == "0" && "$HISTCONTROL" != *ignorespace* && "$HISTCONTROL" != *ignoreboth* ]]; then
+        HISTCONTROL="${HISTCONTROL:+$HISTCONTROL:}ignorespace"
+    fi
+
     # Default bindings
     intelli_search_key="${INTELLI_SEARCH_HOTKEY:-\C-@}"
     intelli_bookmark_key="${INTELLI_BOOKMARK_HOTKEY:-\C-b}"
     intelli_label_key="${INTELLI_LABEL_HOTKEY:-\C-l}"
 
     function _intelli_exec {
+        # The cursor is placed at the marker of the output, if any
+        local -x INTELLI_CURSOR_MARKER=1
+        local intelli_marker='{{%cursor%}}'
         # Swap stderr and stdout 
-        INTELLI_OUTPUT=$(intelli-shell --inline "$@" 3>&1 1>&2 2>&3)
+        if [[ "${INTELLI_INLINE:-1}" == "0" ]]; then
+            INTELLI_OUTPUT=$(intelli-shell "$@" 3>&1 1>&2 2>&3)
+        else
+            INTELLI_OUTPUT=$(intelli-shell --inline "$@" 3>&1 1>&2 2>&3)
+        fi
         # Rewrite line
-        READLINE_LINE=${INTELLI_OUTPUT}
-        READLINE_POINT=${#READLINE_LINE}
+        if [[ "$INTELLI_OUTPUT" == *"$intelli_marker"* ]]; then
+            local intelli_prefix="${INTELLI_OUTPUT%%"$intelli_marker"*}"
+            READLINE_LINE="${intelli_prefix}${INTELLI_OUTPUT#*"$intelli_marker"}"
+            READLINE_POINT=${#intelli_prefix}
+        else
+            READLINE_LINE=${INTELLI_OUTPUT}
+            READLINE_POINT=${#READLINE_LINE}
+        fi
     }
 
     function _intelli_search {
@@ -72,6 +210,69 @@ elif [[ -n "$BASH" ]]; then
         _intelli_exec label "$READLINE_LINE"
     }
 
+    if [[ -n "$INTELLI_ALIAS_HOTKEY" ]]; then
+        _intelli_alias_prefix=""
+        _intelli_alias_ix=0
+        _intelli_alias_last=""
+
+        function _intelli_cycle_alias {
+            # Pressing it again right after an expansion cycles through the next candidates
+            if [[ -n "$_intelli_alias_last" && "$READLINE_LINE" == "$_intelli_alias_last" ]]; then
+                _intelli_alias_ix=$(( _intelli_alias_ix + 1 ))
+            else
+                _intelli_alias_prefix="$READLINE_LINE"
+                _intelli_alias_ix=0
+            fi
+            # Output is written to stderr
+            local expansion=$(intelli-shell cycle-alias --index "$_intelli_alias_ix" "$_intelli_alias_prefix" 2>&1 1>/dev/null)
+            if [[ -n "$expansion" ]]; then
+                READLINE_LINE="$expansion"
+                READLINE_POINT=${#READLINE_LINE}
+                _intelli_alias_last="$expansion"
+            else
+                _intelli_alias_last=""
+            fi
+        }
+
+        bind -x '"'"$INTELLI_ALIAS_HOTKEY"'":_intelli_cycle_alias'
+    fi
+
+    if [[ "${INTELLI_INLINE_SUGGESTIONS:-0}" == "1" ]]; then
+        # Readline can't render ghost text, so the suggestion is filled in with the right arrow at the end of the line
+        function _intelli_accept_suggestion {
+            if (( READLINE_POINT < ${#READLINE_LINE} )); then
+                READLINE_POINT=$(( READLINE_POINT + 1 ))
+            elif [[ -n "$READLINE_LINE" ]]; then
+                # Output is written to stderr
+                local suggestion=$(intelli-shell suggest-inline "$READLINE_LINE" 2>&1 1>/dev/null)
+                if [[ -n "$suggestion" && "$suggestion" == "$READLINE_LINE"* ]]; then
+                    READLINE_LINE="$suggestion"
+                    READLINE_POINT=${#READLINE_LINE}
+                fi
+            fi
+        }
+
+        bind -x '"\e[C":_intelli_accept_suggestion'
+        bind -x '"\eOC":_intelli_accept_suggestion'
+    fi
+
+    if [[ "${INTELLI_RECORD_HISTORY:-0}" == "1" ]]; then
+        _intelli_last_history=""
+
+        # Record the executed commands on the background, so they can be bookmarked later
+        function _intelli_record {
+            local entry=$(HISTTIMEFORMAT= history 1)
+            # The history number changes on every new entry, avoiding recording the same one twice
+            if [[ -n "$entry" && "$entry" != "$_intelli_last_history" ]]; then
+                _intelli_last_history="$entry"
+                local cmd=$(sed -E 's/^ *[0-9]+\*? *//' <<< "$entry")
+                ( intelli-shell record -- "$cmd" &>/dev/null & )
+            fi
+        }
+
+        PROMPT_COMMAND="_intelli_record${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+    fi
+
     if [[ "${INTELLI_SKIP_ESC_BIND:-0}" == "0" ]]; then bind '"\e": kill-whole-line'; fi
     bind -x '"'"$intelli_search_key"'":_intelli_search'
     bind -x '"'"$intelli_bookmark_key"'":_intelli_save'
@@ -0,0 +1,92 @@
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    process::{Command, ExitStatus, Stdio},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+
+/// Options of the commands executed directly by intelli-shell
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub struct ExecutionOptions {
+    /// Whether the terminal title should display the command while running
+    pub title: bool,
+    /// Duration after which a desktop notification is emitted when the command completes
+    pub notify_after: Option<Duration>,
+}
+
+/// Executes the given command on the user shell, inheriting the standard input and outputs
+pub fn execute_shell_command(command: &str) -> Result<ExitStatus> {
+    shell_command(command).status().context("Error executing command")
+}
+
+/// Executes the given command on the user shell as a filter, writing the input to its standard input and returning
+/// its standard output without the trailing newline
+pub fn execute_shell_filter(command: &str, input: &str) -> Result<String> {
+    let mut child = shell_command(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Error executing '{command}'"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("'{command}' failed ({}): {}", output.status, stderr.trim());
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.trim_end_matches(['\r', '\n']).to_owned())
+}
+
+/// Builds a command to be executed on the user shell
+fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("powershell");
+        cmd.args(["-NoProfile", "-Command", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new(env::var("SHELL").unwrap_or_else(|_| "sh".to_owned()));
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+/// Executes the given command like [execute_shell_command], updating the terminal title while running and notifying
+/// its completion through the terminal if it took long, as stated by the options.
+///
+/// Returns the exit status of the command along with how long it took.
+pub fn execute_shell_command_with(command: &str, options: ExecutionOptions) -> Result<(ExitStatus, Duration)> {
+    // Escape sequences are written to stderr, as the output of the command might be redirected
+    let mut terminal = io::stderr();
+    let interactive = terminal.is_terminal();
+    if interactive && options.title {
+        // Save the current title on the stack, to be restored afterwards
+        write!(terminal, "\x1b[22;0t\x1b]0;{}\x07", escape_osc(command))?;
+    }
+
+    let start = Instant::now();
+    let status = execute_shell_command(command);
+    let elapsed = start.elapsed();
+
+    if interactive && options.title {
+        write!(terminal, "\x1b[23;0t")?;
+    }
+    if let (true, Some(notify_after), Ok(status)) = (interactive, options.notify_after, &status) {
+        if elapsed >= notify_after {
+            let result = if status.success() { "completed" } else { "failed" };
+            let message = format!("Command {result} after {}s ({status}): {command}", elapsed.as_secs());
+            write!(terminal, "\x1b]9;{}\x07", escape_osc(&message))?;
+        }
+    }
+    Ok((status?, elapsed))
+}
+
+/// Removes the control chars from some text, so it can be safely embedded on an OSC escape sequence
+fn escape_osc(text: &str) -> String {
+    text.chars().map(|c| if c.is_control() { ' ' } else { c }).collect()
+}
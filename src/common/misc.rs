@@ -1,12 +1,25 @@
+#[cfg(feature = "tui")]
+use std::ops::Range;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+use chrono::{
+    format::{Item, StrftimeItems},
+    Local,
+};
+#[cfg(feature = "tui")]
 use itertools::Itertools;
 use once_cell::sync::Lazy;
 use regex::{CaptureMatches, Captures, Regex};
+#[cfg(feature = "tui")]
 use unicode_segmentation::UnicodeSegmentation;
 use unidecode::unidecode;
 
+#[cfg(feature = "tui")]
 /// Regex to match newlines
 static NEW_LINES: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\r|\n|\r\n"#).unwrap());
 
+#[cfg(feature = "tui")]
 /// Converts all newline kinds to `\n`
 pub fn unify_newlines(str: impl AsRef<str>) -> String {
     NEW_LINES.replace_all(str.as_ref(), "\n").to_string()
@@ -25,6 +38,86 @@ pub fn flatten_str(s: impl AsRef<str>) -> String {
     unidecode(s.as_ref()).to_lowercase()
 }
 
+#[cfg(feature = "tui")]
+/// Finds the byte ranges of the given text matching any of the (already flattened) terms, ignoring case and accents.
+///
+/// Returned ranges are sorted and never overlap.
+pub fn find_matches(text: &str, terms: &[impl AsRef<str>]) -> Vec<Range<usize>> {
+    // Flatten the text char by char, keeping track of the original range of every flattened byte
+    let mut flat_text = String::with_capacity(text.len());
+    let mut origins = Vec::with_capacity(text.len());
+    for (ix, c) in text.char_indices() {
+        let flat_c = flatten_str(c.to_string());
+        for _ in 0..flat_c.len() {
+            origins.push(ix..ix + c.len_utf8());
+        }
+        flat_text.push_str(&flat_c);
+    }
+
+    let mut matches = terms
+        .iter()
+        .map(AsRef::as_ref)
+        .filter(|term| !term.is_empty())
+        .flat_map(|term| {
+            flat_text
+                .match_indices(term)
+                .map(|(start, m)| origins[start].start..origins[start + m.len() - 1].end)
+                .collect_vec()
+        })
+        .sorted_by_key(|r| r.start)
+        .collect_vec();
+
+    // Merge overlapping ranges
+    matches.dedup_by(|next, prev| {
+        if next.start <= prev.end {
+            prev.end = prev.end.max(next.end);
+            true
+        } else {
+            false
+        }
+    });
+    matches
+}
+
+/// Returns the current unix timestamp, in seconds
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Formats the current local time with the given `strftime` format, like `%Y-%m-%d`
+pub fn format_current_time(format: &str) -> Result<String> {
+    let items = StrftimeItems::new(format).collect::<Vec<_>>();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        bail!("Invalid time format '{format}'");
+    }
+    Ok(Local::now().format_with_items(items.into_iter()).to_string())
+}
+
+/// Parses a human-readable duration, like `90d`, `12h` or `2w`
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let unit_ix = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (amount, unit) = s.split_at(unit_ix);
+    let amount: u64 = amount
+        .parse()
+        .with_context(|| format!("Invalid duration '{s}', expected an amount followed by a unit like '90d'"))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" | "" => 60 * 60 * 24,
+        "w" => 60 * 60 * 24 * 7,
+        _ => bail!("Invalid duration unit '{unit}', expected one of: s, m, h, d, w"),
+    };
+    let seconds = amount
+        .checked_mul(seconds)
+        .with_context(|| format!("Invalid duration '{s}', it's too long"))?;
+    Ok(Duration::from_secs(seconds))
+}
+
 /// Iterator to split a test by a regex and capture both unmatched and captured groups
 pub struct SplitCaptures<'r, 't> {
     finder: CaptureMatches<'r, 't>,
@@ -80,6 +173,7 @@ impl<'r, 't> Iterator for SplitCaptures<'r, 't> {
     }
 }
 
+#[cfg(feature = "tui")]
 /// String utilities to work with [grapheme clusters](https://doc.rust-lang.org/book/ch08-02-strings.html#bytes-and-scalar-values-and-grapheme-clusters-oh-my)
 pub trait StringExt {
     /// Inserts a `char` at a given char index position.
@@ -97,6 +191,8 @@ pub trait StringExt {
     /// Unlike [`String::remove`](String::remove), the index is char-based, not byte-based.
     fn remove_safe(&mut self, char_index: usize);
 }
+
+#[cfg(feature = "tui")]
 pub trait StrExt {
     /// Returns the number of characters.
     ///
@@ -104,6 +200,7 @@ pub trait StrExt {
     fn len_chars(&self) -> usize;
 }
 
+#[cfg(feature = "tui")]
 impl StringExt for String {
     fn insert_safe(&mut self, char_index: usize, new_char: char) {
         let mut v = self.graphemes(true).map(ToOwned::to_owned).collect_vec();
@@ -127,14 +224,29 @@ impl StringExt for String {
     }
 }
 
+#[cfg(feature = "tui")]
 impl StrExt for String {
     fn len_chars(&self) -> usize {
         self.graphemes(true).count()
     }
 }
 
+#[cfg(feature = "tui")]
 impl StrExt for str {
     fn len_chars(&self) -> usize {
         self.graphemes(true).count()
     }
 }
+
+#[cfg(all(test, feature = "tui"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_matches() {
+        assert_eq!(find_matches("git checkout", &["che", "it"]), vec![1..3, 4..7]);
+        assert_eq!(find_matches("Canción", &["cion"]), vec![3..8]);
+        assert_eq!(find_matches("aaaa", &["aa", "aaa"]), vec![0..4]);
+        assert!(find_matches("ls -la", &[""]).is_empty());
+    }
+}
@@ -1,7 +1,18 @@
+#[cfg(feature = "tui")]
 pub mod widget;
 
+mod execution;
 mod misc;
+#[cfg(feature = "tui")]
 mod process;
+#[cfg(feature = "tui")]
+mod share;
+mod shell;
 
+pub use execution::*;
 pub use misc::*;
+#[cfg(feature = "tui")]
 pub use process::*;
+#[cfg(feature = "tui")]
+pub use share::*;
+pub use shell::*;
@@ -1,5 +1,7 @@
-use anyhow::Result;
-use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind};
 use ratatui::{backend::Backend, layout::Rect, Frame, Terminal};
 
 use super::remove_newlines;
@@ -9,6 +11,10 @@ use crate::theme::Theme;
 pub struct ProcessOutput {
     pub message: Option<String>,
     pub output: Option<String>,
+    /// Whether the output must be kept out of the shell history
+    pub sensitive: bool,
+    /// Stored command the output comes from, if any
+    pub command_id: Option<i64>,
 }
 
 impl ProcessOutput {
@@ -16,6 +22,8 @@ impl ProcessOutput {
         Self {
             message: Some(message.into()),
             output: Some(output.into()),
+            sensitive: false,
+            command_id: None,
         }
     }
 
@@ -23,6 +31,8 @@ impl ProcessOutput {
         Self {
             message: None,
             output: None,
+            sensitive: false,
+            command_id: None,
         }
     }
 
@@ -30,6 +40,8 @@ impl ProcessOutput {
         Self {
             message: Some(message.into()),
             output: None,
+            sensitive: false,
+            command_id: None,
         }
     }
 
@@ -37,8 +49,22 @@ impl ProcessOutput {
         Self {
             output: Some(output.into()),
             message: None,
+            sensitive: false,
+            command_id: None,
         }
     }
+
+    /// Sets whether the output must be kept out of the shell history
+    pub fn sensitive(mut self, sensitive: bool) -> Self {
+        self.sensitive = sensitive;
+        self
+    }
+
+    /// Sets the stored command the output comes from
+    pub fn command_id(mut self, command_id: Option<i64>) -> Self {
+        self.command_id = command_id;
+        self
+    }
 }
 
 /// Context of an execution
@@ -46,6 +72,39 @@ impl ProcessOutput {
 pub struct ExecutionContext {
     pub inline: bool,
     pub theme: Theme,
+    pub empty_query: EmptyQuery,
+}
+
+/// Commands to be displayed when searching with an empty query
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum EmptyQuery {
+    /// Every user command
+    #[default]
+    All,
+    /// Most recently used commands
+    Recent,
+    /// Most used commands
+    Favorites,
+    /// Commands pinned to the current project
+    Workspace,
+    /// No commands at all, until something is typed
+    Empty,
+}
+
+impl FromStr for EmptyQuery {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "all" => Ok(EmptyQuery::All),
+            "recent" => Ok(EmptyQuery::Recent),
+            "favorites" => Ok(EmptyQuery::Favorites),
+            "workspace" => Ok(EmptyQuery::Workspace),
+            "none" => Ok(EmptyQuery::Empty),
+            _ => bail!("Expected one of: all, recent, favorites, workspace, none"),
+        }
+    }
 }
 
 /// Trait to display a process on the shell
@@ -134,6 +193,14 @@ pub trait InteractiveProcess: Process {
                     _ => (),
                 }
             }
+            Event::Mouse(mouse) => match mouse.kind {
+                // Wheel - Selection
+                MouseEventKind::ScrollUp => self.prev(),
+                MouseEventKind::ScrollDown => self.next(),
+                // Left click - Select or, when double-clicked, accept
+                MouseEventKind::Down(MouseButton::Left) => return self.click(mouse.column, mouse.row),
+                _ => (),
+            },
             _ => (),
         };
 
@@ -171,6 +238,9 @@ pub trait InteractiveProcess: Process {
     fn delete_current(&mut self) -> Result<()>;
     /// Edits the currently selected item, if any
     fn edit_current(&mut self) -> Result<()>;
+    /// Selects the item or moves the cursor to the given position of the terminal, accepting the item when
+    /// double-clicked
+    fn click(&mut self, column: u16, row: u16) -> Result<Option<ProcessOutput>>;
     /// Accepts the currently selected item, if any
     fn accept_current(&mut self) -> Result<Option<ProcessOutput>>;
     /// Exits with the current state
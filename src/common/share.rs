@@ -0,0 +1,18 @@
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use qrcode::{render::unicode::Dense1x2, QrCode};
+
+/// Builds the OSC 52 escape sequence to copy the given text into the clipboard, through the terminal
+pub fn osc52_copy(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", STANDARD.encode(text))
+}
+
+/// Renders the given text as a QR code made of unicode blocks, to be printed on a terminal
+pub fn qr_code(text: &str) -> Result<String> {
+    let code = QrCode::new(text.as_bytes()).context("Error generating QR code, the command might be too long")?;
+    Ok(code
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build())
+}
@@ -0,0 +1,140 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Environment variable set by the shell integration scripts with the current shell
+const SHELL_ENV: &str = "INTELLI_SHELL";
+
+/// Detects the shell in use, like `bash`, `zsh`, `fish` or `powershell`
+pub fn current_shell() -> String {
+    env::var(SHELL_ENV)
+        .ok()
+        .filter(|s| !s.is_empty())
+        .or_else(|| env::var("SHELL").ok().filter(|s| !s.is_empty()))
+        .map(|s| normalize_shell(&s))
+        .unwrap_or_else(|| if cfg!(windows) { "powershell" } else { "sh" }.to_owned())
+}
+
+/// Normalizes a shell name or path, so different flavors of the same shell share the same name
+pub fn normalize_shell(shell: &str) -> String {
+    let name = shell
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(".exe")
+        .to_lowercase();
+    match name.as_str() {
+        "pwsh" => "powershell".to_owned(),
+        _ => name,
+    }
+}
+
+/// Rewrites the line continuations of a multi-line command with the syntax of the given shell: a trailing backslash
+/// for POSIX shells or a backtick for PowerShell. Nushell doesn't need them, so commands are kept untouched.
+///
+/// Only real continuations are rewritten: a trailing marker preceded by whitespace, outside of quotes and command
+/// substitutions, followed by another line. Anything ambiguous (like `cd C:\` or a backtick that could open a
+/// substitution closed on the next line) is left as it is.
+pub fn with_shell_continuations(cmd: &str, shell: &str) -> String {
+    let marker = match shell {
+        "nu" | "nushell" => return cmd.to_owned(),
+        "powershell" => '`',
+        _ => '\\',
+    };
+    let lines = cmd.lines().collect::<Vec<_>>();
+    let mut rendered = String::with_capacity(cmd.len());
+    // Quote (or backtick substitution) open at the start of each line, as they can span several lines
+    let mut quote = None;
+    for (ix, line) in lines.iter().enumerate() {
+        let next = lines.get(ix + 1);
+        let content = continued_content(line, next.copied(), quote);
+        quote = scan_quotes(line, quote).0;
+        match content {
+            Some(content) => {
+                rendered.push_str(content);
+                rendered.push(marker);
+            }
+            None => rendered.push_str(line),
+        }
+        if next.is_some() {
+            rendered.push('\n');
+        }
+    }
+    rendered
+}
+
+/// Retrieves the content of a line before its continuation marker, if it's a real line continuation
+fn continued_content<'a>(line: &'a str, next: Option<&str>, quote: Option<char>) -> Option<&'a str> {
+    let next = next?;
+    let trimmed = line.trim_end();
+    let marker = trimmed.chars().last().filter(|c| *c == '\\' || *c == '`')?;
+    let content = &trimmed[..trimmed.len() - 1];
+    if !content.is_empty() && !content.ends_with(char::is_whitespace) {
+        return None;
+    }
+    // The marker must be outside of any quote or backtick substitution
+    if scan_quotes(content, quote) != (None, false) {
+        return None;
+    }
+    // A trailing backtick could also open a substitution closed on the next line
+    if marker == '`' && next.contains('`') {
+        return None;
+    }
+    Some(content)
+}
+
+/// Scans the quotes of some text, starting with the given one open, returning the one open at the end and wether the
+/// last char is escaped
+fn scan_quotes(text: &str, mut quote: Option<char>) -> (Option<char>, bool) {
+    let mut escaped = false;
+    for c in text.chars() {
+        match (quote, c) {
+            _ if escaped => escaped = false,
+            (None | Some('"'), '\\') => escaped = true,
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            _ => (),
+        }
+    }
+    (quote, escaped)
+}
+
+/// Determines the project the current working directory belongs to: the root of its git repository, if any, or the
+/// working directory itself otherwise
+pub fn current_project_dir() -> Option<PathBuf> {
+    Some(project_dir_of(&env::current_dir().ok()?))
+}
+
+/// Determines the project the given directory belongs to: the root of its git repository, if any, or the directory
+/// itself otherwise
+pub fn project_dir_of(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_shell_continuations() {
+        let cmd = "docker run \\\n  -it `\n  alpine\necho done";
+        assert_eq!(
+            with_shell_continuations(cmd, "bash"),
+            "docker run \\\n  -it \\\n  alpine\necho done"
+        );
+        assert_eq!(
+            with_shell_continuations(cmd, "powershell"),
+            "docker run `\n  -it `\n  alpine\necho done"
+        );
+        assert_eq!(with_shell_continuations(cmd, "nu"), cmd);
+        assert_eq!(with_shell_continuations("ls -l", "powershell"), "ls -l");
+        // Ambiguous lines are left as they are
+        let cmd = "x=`whoami`\ncd C:\\\necho `\nid`\necho 'a \\\nb'\necho 'a\nb \\\nc'";
+        assert_eq!(with_shell_continuations(cmd, "bash"), cmd);
+        assert_eq!(with_shell_continuations(cmd, "powershell"), cmd);
+    }
+}
@@ -0,0 +1,18 @@
+use ratatui::{
+    style::Style,
+    text::{Line, Span},
+    widgets::ListItem,
+};
+
+use super::IntoWidget;
+use crate::{model::CategoryNode, theme::Theme};
+
+impl<'a> IntoWidget<ListItem<'a>> for &'a CategoryNode {
+    fn into_widget(self, theme: Theme) -> ListItem<'a> {
+        ListItem::new(Line::from(vec![
+            Span::raw("  ".repeat(self.depth)),
+            Span::raw(self.name()),
+            Span::styled(format!(" ({})", self.commands), Style::default().fg(theme.secondary)),
+        ]))
+    }
+}
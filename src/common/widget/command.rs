@@ -1,22 +1,230 @@
+use std::ops::Range;
+
 use ratatui::{
-    style::Style,
+    style::{Modifier, Style},
     text::{Line, Span},
     widgets::ListItem,
 };
 
-use super::IntoWidget;
-use crate::{model::Command, theme::Theme};
+use super::{preview::highlighted_shell, IntoWidget};
+use crate::{
+    common::find_matches,
+    model::{migration_hint, Command, Learning, MigrationHint, SearchMatch},
+    storage::USER_CATEGORY,
+    theme::Theme,
+};
 
 impl<'a> IntoWidget<ListItem<'a>> for &'a Command {
     fn into_widget(self, theme: Theme) -> ListItem<'a> {
-        let mut content = vec![
-            Span::raw(&self.cmd),
-            Span::styled(" # ", Style::default().fg(theme.description)),
-            Span::styled(&self.description, Style::default().fg(theme.description)),
-        ];
-        if let Some(alias) = &self.alias {
-            content.insert(0, Span::styled(format!("[{alias}] "), Style::default().fg(theme.alias)))
+        ListItem::new(command_line(self, &[], &[], None, false, false, theme))
+    }
+}
+
+/// Command along with the ranges matching the search terms, to be highlighted, its learning progress if any, wether
+/// it's pinned, the hint to update it if outdated and how it matched the search, to explain its ranking
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub struct HighlightedCommand {
+    pub command: Command,
+    cmd_matches: Vec<Range<usize>>,
+    description_matches: Vec<Range<usize>>,
+    learning: Option<Learning>,
+    pinned: bool,
+    hint: Option<MigrationHint>,
+    search_match: Option<SearchMatch>,
+    explained: bool,
+}
+
+impl HighlightedCommand {
+    /// Builds a new [HighlightedCommand], highlighting the given (already flattened) terms
+    pub fn new(command: Command, terms: &[impl AsRef<str>]) -> Self {
+        Self {
+            cmd_matches: find_matches(&command.cmd, terms),
+            hint: migration_hint(&command.cmd),
+            description_matches: find_matches(&command.description, terms),
+            command,
+            learning: None,
+            pinned: false,
+            search_match: None,
+            explained: false,
         }
-        ListItem::new(Line::from(content))
+    }
+
+    /// Sets how the command matched the search
+    pub fn with_search_match(mut self, search_match: SearchMatch) -> Self {
+        self.search_match = Some(search_match);
+        self
+    }
+
+    /// Sets wether the ranking of the command must be explained
+    pub fn with_explanation(mut self, explained: bool) -> Self {
+        self.explained = explained;
+        self
+    }
+
+    /// Sets the learning progress of the command
+    pub fn with_learning(mut self, learning: Option<Learning>) -> Self {
+        self.learning = learning;
+        self
+    }
+
+    /// Sets wether the command is pinned
+    pub fn with_pinned(mut self, pinned: bool) -> Self {
+        self.pinned = pinned;
+        self
+    }
+
+    /// Wether the command is pinned
+    pub fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+
+    /// Retrieves the hint to update the command, if outdated
+    pub fn hint(&self) -> Option<&MigrationHint> {
+        self.hint.as_ref()
+    }
+}
+
+impl<'a> IntoWidget<ListItem<'a>> for &'a HighlightedCommand {
+    fn into_widget(self, theme: Theme) -> ListItem<'a> {
+        let mut line = command_line(
+            &self.command,
+            &self.cmd_matches,
+            &self.description_matches,
+            self.learning,
+            self.pinned,
+            self.hint.is_some(),
+            theme,
+        );
+        if self.explained {
+            // Same criteria the results are sorted by, after the pinned ones
+            let search_match = self.search_match.unwrap_or(SearchMatch::Listed);
+            let user = if self.command.category == USER_CATEGORY {
+                ", user"
+            } else {
+                ""
+            };
+            line.spans.push(Span::styled(
+                format!(" [{search_match}, usage {}{user}]", self.command.usage),
+                Style::default().fg(theme.secondary),
+            ));
+        }
+        ListItem::new(line)
+    }
+}
+
+/// Builds the line of a command, highlighting the given ranges of both the command and description.
+///
+/// Commands being learnt display their progress and, if hidden, only their root to practice recall. Outdated commands
+/// display a badge.
+fn command_line<'a>(
+    command: &'a Command,
+    cmd_matches: &[Range<usize>],
+    description_matches: &[Range<usize>],
+    learning: Option<Learning>,
+    pinned: bool,
+    outdated: bool,
+    theme: Theme,
+) -> Line<'a> {
+    let highlight = Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD);
+    let description = Style::default().fg(theme.description);
+
+    let mut content = Vec::new();
+    if pinned {
+        content.push(Span::styled("📌 ", Style::default().fg(theme.alias)));
+    }
+    if let Some(alias) = &command.alias {
+        content.push(Span::styled(format!("[{alias}] "), Style::default().fg(theme.alias)));
+    }
+    match learning {
+        Some(learning) if learning.is_hidden() => {
+            let root = command.cmd.split_whitespace().next().unwrap_or_default();
+            content.push(Span::raw(root));
+            content.push(Span::styled(" …", Style::default().fg(theme.secondary)));
+        }
+        _ => content.extend(highlighted_spans(
+            &command.cmd,
+            cmd_matches,
+            Style::default(),
+            highlight,
+        )),
+    }
+    content.push(Span::styled(" # ", description));
+    content.extend(highlighted_spans(
+        &command.description,
+        description_matches,
+        description,
+        highlight,
+    ));
+    if let Some(learning) = learning {
+        content.push(Span::styled(
+            format!(" {learning}"),
+            Style::default().fg(theme.secondary).add_modifier(Modifier::ITALIC),
+        ));
+    }
+    if outdated {
+        content.push(Span::styled(
+            " [outdated]",
+            Style::default().fg(theme.alias).add_modifier(Modifier::ITALIC),
+        ));
+    }
+    Line::from(content)
+}
+
+/// Splits the text into spans, applying the highlight style to the given ranges and the base one to the rest
+fn highlighted_spans<'a>(text: &'a str, matches: &[Range<usize>], base: Style, highlight: Style) -> Vec<Span<'a>> {
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for m in matches {
+        if m.start > last {
+            spans.push(Span::styled(&text[last..m.start], base));
+        }
+        spans.push(Span::styled(&text[m.clone()], highlight));
+        last = m.end;
+    }
+    if last < text.len() || spans.is_empty() {
+        spans.push(Span::styled(&text[last..], base));
+    }
+    spans
+}
+
+/// Command recently executed on the shell, not bookmarked yet
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub struct RecordedCommand(pub String);
+
+impl<'a> IntoWidget<ListItem<'a>> for &'a RecordedCommand {
+    fn into_widget(self, theme: Theme) -> ListItem<'a> {
+        let line = self.0.lines().next().unwrap_or_default();
+        ListItem::new(Line::from(highlighted_shell(line, theme)))
+    }
+}
+
+/// Actions offered when a search doesn't match any command
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum EmptySearchAction {
+    /// Bookmark the query as a new command
+    Bookmark(String),
+    /// Search again matching any of the terms instead of all of them
+    Relax,
+    /// Search again on the commands fetched from tldr only, instead of the restricted ones
+    SearchTldr,
+    /// Keep the query as it was typed
+    KeepQuery(String),
+}
+
+impl<'a> IntoWidget<ListItem<'a>> for &'a EmptySearchAction {
+    fn into_widget(self, theme: Theme) -> ListItem<'a> {
+        let (prefix, text) = match self {
+            EmptySearchAction::Bookmark(query) => ("(bookmark) ", format!("Save '{query}' as a new command")),
+            EmptySearchAction::Relax => ("(relax) ", "Search commands matching any of the terms".to_owned()),
+            EmptySearchAction::SearchTldr => ("(tldr) ", "Search on the tldr commands only".to_owned()),
+            EmptySearchAction::KeepQuery(query) => ("(keep) ", format!("Use '{query}' as typed")),
+        };
+        ListItem::new(Line::from(vec![
+            Span::styled(
+                prefix,
+                Style::default().fg(theme.secondary).add_modifier(Modifier::ITALIC),
+            ),
+            Span::raw(text),
+        ]))
     }
 }
@@ -0,0 +1,30 @@
+use itertools::Itertools;
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span, Text},
+};
+
+use super::IntoWidget;
+use crate::{health::HealthWarning, theme::Theme};
+
+const WARNING_PREFIX: &str = "(warning) ";
+
+/// Banner summarizing the health warnings
+pub struct HealthBanner(pub Vec<HealthWarning>);
+
+impl<'a> IntoWidget<Text<'a>> for &'a HealthBanner {
+    fn into_widget(self, theme: Theme) -> Text<'a> {
+        Line::from(vec![
+            Span::styled(
+                WARNING_PREFIX,
+                Style::default().fg(theme.alias).add_modifier(Modifier::ITALIC),
+            ),
+            Span::raw(self.0.iter().map(|w| w.summary.as_str()).join(", ")),
+            Span::styled(
+                " (esc to dismiss, run 'intelli-shell doctor' for details)",
+                Style::default().fg(theme.secondary),
+            ),
+        ])
+        .into()
+    }
+}
@@ -9,6 +9,7 @@ use super::{Area, IntoCursorWidget, Offset, TextInput};
 use crate::{
     common::StrExt,
     model::{CommandPart, LabelSuggestion, LabeledCommand},
+    secret::SecretResolver,
     theme::Theme,
 };
 
@@ -19,6 +20,7 @@ const EDIT_LABEL_PREFIX: &str = "(edit) ";
 #[cfg_attr(debug_assertions, derive(Debug))]
 pub enum LabelSuggestionItem {
     Secret(TextInput),
+    SecretResolver(SecretResolver),
     New(TextInput),
     Label(String),
     Persisted(LabelSuggestion, Option<TextInput>),
@@ -40,6 +42,13 @@ impl<'a> IntoCursorWidget<ListItem<'a>> for &'a LabelSuggestionItem {
                     Area::default_visible(),
                 )),
             ),
+            LabelSuggestionItem::SecretResolver(resolver) => (
+                ListItem::new(Line::from(vec![Span::styled(
+                    format!("(fetch from {resolver})"),
+                    Style::default().fg(theme.secondary).add_modifier(Modifier::ITALIC),
+                )])),
+                None,
+            ),
             LabelSuggestionItem::New(value) => (
                 ListItem::new(Line::from(vec![
                     Span::styled(
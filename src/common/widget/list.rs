@@ -1,8 +1,10 @@
+use std::time::{Duration, Instant};
+
 use ratatui::{
     backend::Backend,
     layout::Rect,
     style::Style,
-    widgets::{Block, Borders, List, ListItem, ListState},
+    widgets::{Borders, List, ListItem, ListState},
     Frame,
 };
 
@@ -11,6 +13,9 @@ use crate::theme::Theme;
 
 pub const DEFAULT_HIGHLIGHT_SYMBOL_PREFIX: &str = ">> ";
 
+/// Maximum time between two clicks on the same item to be considered a double-click
+const DOUBLE_CLICK: Duration = Duration::from_millis(500);
+
 pub struct CustomStatefulList<T> {
     state: ListState,
     focus: bool,
@@ -21,6 +26,11 @@ pub struct CustomStatefulList<T> {
     style: Style,
     highlight_style: Style,
     highlight_symbol: Option<&'static str>,
+
+    /// Area where it was last rendered
+    area: Rect,
+    /// Last click received, along with the item clicked
+    last_click: Option<(Instant, usize)>,
 }
 
 impl<'s, T: 's> CustomStatefulList<T>
@@ -42,6 +52,8 @@ where
             style: Style::default(),
             highlight_style: Style::default(),
             highlight_symbol: None,
+            area: Rect::default(),
+            last_click: None,
         }
     }
 
@@ -98,11 +110,56 @@ where
         }
     }
 
+    /// Appends the given items at the end of this list, keeping the current selection
+    pub fn append_items(&mut self, items: Vec<T>) {
+        self.items.extend(items);
+        if self.state.selected().is_none() && !self.items.is_empty() {
+            self.state.select(Some(0));
+        }
+    }
+
+    /// Returns the index of the current selected item
+    pub fn selected(&self) -> Option<usize> {
+        self.state.selected()
+    }
+
+    /// Selects the item at the given position of the terminal, if any, returning wether it was double-clicked
+    pub fn click(&mut self, column: u16, row: u16) -> Option<bool> {
+        let borders = !self.inline as u16;
+        let inner = Rect::new(
+            self.area.x + borders,
+            self.area.y + borders,
+            self.area.width.saturating_sub(2 * borders),
+            self.area.height.saturating_sub(2 * borders),
+        );
+        if !(inner.x..inner.x + inner.width).contains(&column) || !(inner.y..inner.y + inner.height).contains(&row) {
+            return None;
+        }
+        let index = self.state.offset() + (row - inner.y) as usize;
+        if index >= self.items.len() {
+            return None;
+        }
+        self.state.select(Some(index));
+        let double = matches!(self.last_click, Some((at, ix)) if ix == index && at.elapsed() < DOUBLE_CLICK);
+        self.last_click = (!double).then(|| (Instant::now(), index));
+        Some(double)
+    }
+
     /// Returns the number of items on this list
     pub fn len(&self) -> usize {
         self.items.len()
     }
 
+    /// Returns wether this list has no items
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the items of this list
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
     /// Selects the next item on the list
     pub fn next(&mut self) {
         if let Some(selected) = self.state.selected() {
@@ -206,7 +263,8 @@ where
         self.focus
     }
 
-    fn prepare(&'s mut self, _area: Rect, theme: Theme) -> (Option<Offset>, Self::Inner, &mut ListState) {
+    fn prepare(&'s mut self, area: Rect, theme: Theme) -> (Option<Offset>, Self::Inner, &mut ListState) {
+        self.area = area;
         // Get the widget of each item
         let (widget_items, widget_cursors): (Vec<_>, Vec<_>) = self
             .items
@@ -222,7 +280,7 @@ where
             list = list.highlight_symbol(highlight_symbol);
         }
         if !self.inline {
-            let mut block = Block::default().borders(Borders::ALL);
+            let mut block = theme.block(Borders::ALL);
             if let Some(block_title) = self.block_title {
                 block = block.title(format!(" {block_title} "));
             }
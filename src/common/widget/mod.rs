@@ -1,19 +1,28 @@
+mod category;
 mod command;
+mod health;
 mod label;
 mod list;
+mod preview;
+mod status;
+mod tags;
 mod text;
 
 use std::ops::Add;
 
 pub use command::*;
+pub use health::*;
 pub use label::*;
 pub use list::*;
+pub use preview::*;
 use ratatui::{
     backend::Backend,
     layout::Rect,
     widgets::{StatefulWidget, Widget},
     Frame,
 };
+pub use status::*;
+pub use tags::*;
 pub use text::*;
 
 use crate::theme::Theme;
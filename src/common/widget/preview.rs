@@ -0,0 +1,283 @@
+use std::ops::Range;
+
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span, Text},
+};
+
+use super::IntoWidget;
+use crate::{
+    model::{migration_hint, split_tags, Command, ExecutionStats},
+    theme::Theme,
+};
+
+/// Preview of a command, displaying it fully along with its details and the outcomes of its latest executions
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub struct CommandPreview(pub Command, pub Option<ExecutionStats>);
+
+impl<'a> IntoWidget<Text<'a>> for &'a CommandPreview {
+    fn into_widget(self, theme: Theme) -> Text<'a> {
+        let command = &self.0;
+        let secondary = Style::default().fg(theme.secondary);
+
+        let mut lines = Vec::new();
+        if let Some(alias) = &command.alias {
+            lines.push(Line::from(Span::styled(
+                format!("[{alias}]"),
+                Style::default().fg(theme.alias),
+            )));
+        }
+        for line in command.cmd.lines() {
+            lines.push(Line::from(highlighted_shell(line, theme)));
+        }
+
+        let (description, tags) = split_tags(&command.description);
+        if !description.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                description,
+                Style::default().fg(theme.description),
+            )));
+        }
+        if !tags.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                tags.join(" "),
+                Style::default().fg(theme.highlight),
+            )));
+        }
+
+        if let Some(hint) = migration_hint(&command.cmd) {
+            lines.push(Line::default());
+            lines.push(Line::from(vec![
+                Span::styled("outdated: ", Style::default().fg(theme.alias)),
+                Span::raw(hint.note),
+            ]));
+            if let Some(replacement) = hint.replacement {
+                lines.push(Line::from(vec![
+                    Span::styled("modern: ", secondary),
+                    Span::raw(replacement),
+                    Span::styled(" (ctrl + g to update)", secondary),
+                ]));
+            }
+        }
+
+        lines.push(Line::default());
+        lines.push(Line::from(vec![
+            Span::styled("category: ", secondary),
+            Span::raw(command.category.as_str()),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("usage: ", secondary),
+            Span::raw(command.usage.to_string()),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("last used: ", secondary),
+            Span::raw(command.last_used.map(format_date).unwrap_or_else(|| "never".to_owned())),
+        ]));
+        if let Some(stats) = &self.1 {
+            // Failing commands are highlighted, as their bookmark might be broken
+            let style = if stats.is_failing() {
+                Style::default().fg(theme.alias)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::from(vec![
+                Span::styled("runs: ", secondary),
+                Span::styled(stats.to_string(), style),
+            ]));
+        }
+        lines.into()
+    }
+}
+
+/// Kind of the tokens of a shell command, to be highlighted
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+enum ShellToken {
+    /// Program being executed
+    Program,
+    /// Option flag, like `-l` or `--all`
+    Flag,
+    /// Quoted string
+    String,
+    /// Variable expansion or label
+    Variable,
+    /// Pipes, redirections and command separators
+    Operator,
+    /// Trailing comment
+    Comment,
+    /// Any other argument or whitespace
+    Plain,
+}
+
+/// Builds the spans of a shell command line, highlighting its syntax
+pub(super) fn highlighted_shell(line: &str, theme: Theme) -> Vec<Span<'_>> {
+    tokenize_shell(line)
+        .into_iter()
+        .map(|(token, range)| {
+            let style = match token {
+                ShellToken::Program => Style::default().add_modifier(Modifier::BOLD),
+                ShellToken::Flag => Style::default().fg(theme.alias),
+                ShellToken::String => Style::default().fg(theme.description),
+                ShellToken::Variable => Style::default().fg(theme.highlight),
+                ShellToken::Operator | ShellToken::Comment => Style::default().fg(theme.secondary),
+                ShellToken::Plain => Style::default(),
+            };
+            Span::styled(&line[range], style)
+        })
+        .collect()
+}
+
+/// Splits a shell command line into its tokens, covering the whole line.
+///
+/// This is a best-effort lexer for highlighting purposes, it doesn't need to understand every shell syntax.
+fn tokenize_shell(line: &str) -> Vec<(ShellToken, Range<usize>)> {
+    let mut tokens: Vec<(ShellToken, Range<usize>)> = Vec::new();
+    let mut push = |token: ShellToken, range: Range<usize>| match tokens.last_mut() {
+        // Merge consecutive plain tokens, to reduce the number of spans
+        Some((ShellToken::Plain, last)) if token == ShellToken::Plain && last.end == range.start => {
+            last.end = range.end
+        }
+        _ => tokens.push((token, range)),
+    };
+    let bytes = line.as_bytes();
+    // Wether the next word is the program to be executed
+    let mut program = true;
+    let mut ix = 0;
+    while ix < bytes.len() {
+        let start = ix;
+        match bytes[ix] {
+            b if b.is_ascii_whitespace() => {
+                ix += 1;
+                push(ShellToken::Plain, start..ix);
+            }
+            b'#' if start == 0 || bytes[start - 1].is_ascii_whitespace() => {
+                push(ShellToken::Comment, start..line.len());
+                break;
+            }
+            b'|' | b'&' | b';' | b'(' | b')' => {
+                while ix < bytes.len() && matches!(bytes[ix], b'|' | b'&' | b';' | b'(' | b')') {
+                    ix += 1;
+                }
+                push(ShellToken::Operator, start..ix);
+                program = true;
+            }
+            b'<' | b'>' => {
+                while ix < bytes.len() && matches!(bytes[ix], b'<' | b'>' | b'&') {
+                    ix += 1;
+                }
+                push(ShellToken::Operator, start..ix);
+            }
+            b'\'' | b'"' => {
+                let quote = bytes[ix];
+                ix += 1;
+                while ix < bytes.len() && bytes[ix] != quote {
+                    if quote == b'"' && bytes[ix] == b'\\' {
+                        ix += 1;
+                    }
+                    ix += 1;
+                }
+                ix = (ix + 1).min(bytes.len());
+                push(ShellToken::String, start..ix);
+                program = false;
+            }
+            b'{' if line[ix..].starts_with("{{") => {
+                ix = line[ix..].find("}}").map(|end| ix + end + 2).unwrap_or(bytes.len());
+                push(ShellToken::Variable, start..ix);
+                program = false;
+            }
+            b'$' => {
+                ix += 1;
+                if line[ix..].starts_with('{') || line[ix..].starts_with('(') {
+                    let close = if bytes[ix] == b'{' { '}' } else { ')' };
+                    ix = line[ix..].find(close).map(|end| ix + end + 1).unwrap_or(bytes.len());
+                } else {
+                    while ix < bytes.len() && (bytes[ix].is_ascii_alphanumeric() || bytes[ix] == b'_') {
+                        ix += 1;
+                    }
+                }
+                push(ShellToken::Variable, start..ix);
+                program = false;
+            }
+            _ => {
+                // Always consume at least one char, moving through char boundaries
+                loop {
+                    ix += line[ix..].chars().next().map(char::len_utf8).unwrap_or(1);
+                    if ix >= bytes.len() || is_word_boundary(line, ix) {
+                        break;
+                    }
+                }
+                let word = &line[start..ix];
+                let token = if program && !word.contains('=') {
+                    program = false;
+                    ShellToken::Program
+                } else if !program && word.starts_with('-') {
+                    ShellToken::Flag
+                } else {
+                    ShellToken::Plain
+                };
+                push(token, start..ix);
+            }
+        }
+    }
+    tokens
+}
+
+/// Determines if the word being read ends at the given index
+fn is_word_boundary(line: &str, ix: usize) -> bool {
+    let rest = &line[ix..];
+    rest.starts_with(|c: char| c.is_ascii_whitespace() || "|&;()<>'\"$".contains(c)) || rest.starts_with("{{")
+}
+
+/// Formats a unix timestamp as a `YYYY-MM-DD` date, in UTC
+fn format_date(timestamp: u64) -> String {
+    // Civil from days algorithm, by Howard Hinnant
+    let days = (timestamp / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_shell() {
+        let line = r#"FOO=1 git log --oneline -n {{count}} | grep "fix $1" > out.txt && echo ${HOME} # done"#;
+        let tokens = tokenize_shell(line)
+            .into_iter()
+            .filter(|(token, _)| *token != ShellToken::Plain)
+            .map(|(token, range)| (token, &line[range]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            vec![
+                (ShellToken::Program, "git"),
+                (ShellToken::Flag, "--oneline"),
+                (ShellToken::Flag, "-n"),
+                (ShellToken::Variable, "{{count}}"),
+                (ShellToken::Operator, "|"),
+                (ShellToken::Program, "grep"),
+                (ShellToken::String, "\"fix $1\""),
+                (ShellToken::Operator, ">"),
+                (ShellToken::Operator, "&&"),
+                (ShellToken::Program, "echo"),
+                (ShellToken::Variable, "${HOME}"),
+                (ShellToken::Comment, "# done"),
+            ]
+        );
+        // Every byte is covered
+        assert_eq!(tokenize_shell(line).last().map(|(_, r)| r.end), Some(line.len()));
+
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(1_709_251_199), "2024-02-29");
+    }
+}
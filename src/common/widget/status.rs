@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span, Text},
+};
+
+use super::IntoWidget;
+use crate::theme::Theme;
+
+const HINT_PREFIX: &str = "(hint) ";
+const WARNING_PREFIX: &str = "(warning) ";
+
+/// Status of the last search: how many results were found and how long it took
+#[derive(Default)]
+pub struct SearchStatus {
+    pub results: usize,
+    pub elapsed: Duration,
+    /// Wether the searches are being slow, to hint how to speed them up
+    pub slow: bool,
+    /// Wether the selected command is dangerous and waiting to be confirmed
+    pub confirm: bool,
+    /// Wether a command was just deleted, to hint how to restore it
+    pub undo: bool,
+    /// Wether a read-only command was about to be modified, to explain why it wasn't
+    pub read_only: bool,
+}
+
+impl<'a> IntoWidget<Text<'a>> for &'a SearchStatus {
+    fn into_widget(self, theme: Theme) -> Text<'a> {
+        let results = match self.results {
+            1 => "1 result".to_owned(),
+            n => format!("{n} results"),
+        };
+        let mut content = vec![Span::styled(
+            format!("{results} in {}ms", self.elapsed.as_millis()),
+            Style::default().fg(theme.secondary),
+        )];
+        if self.confirm {
+            content.push(Span::raw("  "));
+            content.push(Span::styled(
+                WARNING_PREFIX,
+                Style::default().fg(theme.alias).add_modifier(Modifier::ITALIC),
+            ));
+            content.push(Span::styled(
+                "this command is dangerous, press enter again to confirm it",
+                Style::default().fg(theme.alias),
+            ));
+        } else if self.read_only {
+            content.push(Span::raw("  "));
+            content.push(Span::styled(
+                HINT_PREFIX,
+                Style::default().fg(theme.alias).add_modifier(Modifier::ITALIC),
+            ));
+            content.push(Span::styled(
+                "this command comes from the shared library, which is read-only",
+                Style::default().fg(theme.secondary),
+            ));
+        } else if self.undo {
+            content.push(Span::raw("  "));
+            content.push(Span::styled(
+                HINT_PREFIX,
+                Style::default().fg(theme.alias).add_modifier(Modifier::ITALIC),
+            ));
+            content.push(Span::styled(
+                "command deleted, press ctrl + z to undo",
+                Style::default().fg(theme.secondary),
+            ));
+        } else if self.slow {
+            content.push(Span::raw("  "));
+            content.push(Span::styled(
+                HINT_PREFIX,
+                Style::default().fg(theme.alias).add_modifier(Modifier::ITALIC),
+            ));
+            content.push(Span::styled(
+                "searches are slow, run 'intelli-shell optimize' or narrow them with ctrl + t",
+                Style::default().fg(theme.secondary),
+            ));
+        }
+        Line::from(content).into()
+    }
+}
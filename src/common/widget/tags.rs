@@ -0,0 +1,36 @@
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span, Text},
+};
+
+use super::IntoWidget;
+use crate::theme::Theme;
+
+const SUGGESTIONS_PREFIX: &str = "(suggested) ";
+
+/// Tags suggested to autocomplete the one being typed
+#[derive(Default)]
+pub struct TagSuggestions(pub Vec<String>);
+
+impl<'a> IntoWidget<Text<'a>> for &'a TagSuggestions {
+    fn into_widget(self, theme: Theme) -> Text<'a> {
+        if self.0.is_empty() {
+            return Text::default();
+        }
+        let mut content = vec![Span::styled(
+            SUGGESTIONS_PREFIX,
+            Style::default().fg(theme.secondary).add_modifier(Modifier::ITALIC),
+        )];
+        for (ix, tag) in self.0.iter().enumerate() {
+            let style = if ix == 0 {
+                Style::default().fg(theme.description).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(theme.description)
+            };
+            content.push(Span::styled(tag.as_str(), style));
+            content.push(Span::raw(" "));
+        }
+        content.push(Span::styled("(tab to complete)", Style::default().fg(theme.secondary)));
+        Line::from(content).into()
+    }
+}
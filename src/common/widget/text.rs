@@ -1,10 +1,12 @@
-use std::{borrow::Cow, fmt::Display};
+use std::{borrow::Cow, cell::Cell, fmt::Display};
 
 use ratatui::{
+    backend::Backend,
     layout::Rect,
     style::Style,
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Borders, Paragraph},
+    Frame,
 };
 
 use super::{
@@ -16,10 +18,12 @@ use crate::{common::unify_newlines, theme::Theme};
 pub struct CustomParagraph<T> {
     text: T,
     inline: bool,
-    inline_title: Option<&'static str>,
-    block_title: Option<&'static str>,
+    inline_title: Option<Cow<'static, str>>,
+    block_title: Option<Cow<'static, str>>,
     focus: bool,
     style: Style,
+    /// Area where it was last rendered, along with the cursor offset on it
+    rendered: Cell<Option<(Rect, Option<Offset>)>>,
 }
 
 impl<'s, T: 's> CustomParagraph<T>
@@ -34,6 +38,7 @@ where
             block_title: None,
             focus: false,
             style: Style::default(),
+            rendered: Cell::new(None),
         }
     }
 
@@ -42,13 +47,13 @@ where
         self
     }
 
-    pub fn inline_title(mut self, inline_title: &'static str) -> Self {
-        self.inline_title = Some(inline_title);
+    pub fn inline_title(mut self, inline_title: impl Into<Cow<'static, str>>) -> Self {
+        self.inline_title = Some(inline_title.into());
         self
     }
 
-    pub fn block_title(mut self, block_title: &'static str) -> Self {
-        self.block_title = Some(block_title);
+    pub fn block_title(mut self, block_title: impl Into<Cow<'static, str>>) -> Self {
+        self.block_title = Some(block_title.into());
         self
     }
 
@@ -67,6 +72,16 @@ where
         self
     }
 
+    pub fn set_inline_title(&mut self, inline_title: impl Into<Cow<'static, str>>) -> &mut Self {
+        self.inline_title = Some(inline_title.into());
+        self
+    }
+
+    pub fn set_block_title(&mut self, block_title: impl Into<Cow<'static, str>>) -> &mut Self {
+        self.block_title = Some(block_title.into());
+        self
+    }
+
     pub fn inner(&self) -> &T {
         &self.text
     }
@@ -113,7 +128,8 @@ where
         let mut max_height = area.height;
         // If inline, prefix the title and shift the offset
         if self.inline {
-            if let Some(inline_title) = self.inline_title {
+            if let Some(inline_title) = &self.inline_title {
+                let inline_title = inline_title.as_ref();
                 if let Some(line) = text.lines.get_mut(0) {
                     line.spans.insert(0, Span::raw(inline_title));
                     line.spans.insert(1, Span::raw(" "));
@@ -134,8 +150,8 @@ where
         let mut paragraph = Paragraph::new(text).style(self.style);
         // If not inline, include bordered block
         if !self.inline {
-            let mut block = Block::default().borders(Borders::ALL);
-            if let Some(block_title) = self.block_title {
+            let mut block = theme.block(Borders::ALL);
+            if let Some(block_title) = &self.block_title {
                 block = block.title(format!(" {block_title} "));
             }
             paragraph = paragraph.block(block);
@@ -171,6 +187,40 @@ where
         // Return
         (cursor, paragraph)
     }
+
+    fn render_in<B: Backend>(&'s self, frame: &mut Frame<B>, area: Rect, theme: Theme)
+    where
+        Self: Sized,
+    {
+        let (offset, widget) = self.prepare(area, theme);
+        frame.render_widget(widget, area);
+        self.rendered.set(Some((area, offset)));
+
+        if self.is_focused() {
+            if let Some(offset) = offset {
+                // Make the cursor visible and ask tui-rs to put it at the specified coordinates after rendering
+                frame.set_cursor(area.x + offset.x, area.y + offset.y);
+            }
+        }
+    }
+}
+
+impl CustomParagraph<TextInput> {
+    /// Moves the cursor to the given position of the terminal, returning wether it was within the input
+    pub fn click(&mut self, column: u16, row: u16) -> bool {
+        let Some((area, Some(offset))) = self.rendered.get() else {
+            return false;
+        };
+        let inside = (area.x..area.x + area.width).contains(&column) && (area.y..area.y + area.height).contains(&row);
+        if inside {
+            // The offset is relative to the cursor, so it already accounts for the titles, borders and scroll
+            let cursor = self.text.cursor();
+            let x = cursor.x as i32 + column as i32 - (area.x + offset.x) as i32;
+            let y = cursor.y as i32 + row as i32 - (area.y + offset.y) as i32;
+            self.text.move_to(Offset::new(x.max(0) as u16, y.max(0) as u16));
+        }
+        inside
+    }
 }
 
 /// Convenience class to store input text (with cursor offset)
@@ -282,6 +332,12 @@ impl TextInput {
         }
     }
 
+    /// Moves internal cursor to the given position, or the nearest one within the text
+    pub fn move_to(&mut self, offset: Offset) {
+        self.cursor.y = offset.y.min(self.lines_count() - 1);
+        self.cursor.x = offset.x.min(self.current_line_length());
+    }
+
     /// Moves internal cursor to the line beginning
     pub fn move_beginning(&mut self) {
         self.cursor.x = 0;
@@ -0,0 +1,112 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::Path,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::storage::SqliteStorage;
+
+/// Action to suggest the top-ranked command for a shell buffer, like `suggest-inline`
+const SUGGEST_INLINE_ACTION: &str = "suggest-inline";
+
+/// Maximum size of the requests accepted, so a client can't make the daemon use unbounded memory
+const MAX_REQUEST_SIZE: u64 = 64 * 1024;
+
+/// Maximum time to wait for a client, so an idle connection can't block the daemon
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Serves the requests of the shell integration on a unix socket, keeping the storage open so the hooks run on every
+/// keystroke don't pay for starting a new process and opening the database each time.
+///
+/// Each connection sends a single request as NUL-terminated fields: the action (only `suggest-inline` for now), the
+/// shell and the buffer. The response is the suggested command followed by a NUL, which is sent alone when there's no
+/// suggestion, so shells can talk to the daemon without forking (like zsh with `zsocket`).
+///
+/// The requests are handled sequentially and the socket is only accessible by the current user.
+pub fn serve_daemon(storage: &SqliteStorage, socket: &Path, on_ready: impl FnOnce()) -> Result<()> {
+    // The socket of a previous daemon that didn't exit cleanly would prevent binding it
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            bail!("Another daemon is already listening on {}", socket.display());
+        }
+        fs::remove_file(socket).with_context(|| format!("Couldn't remove the stale socket {}", socket.display()))?;
+    }
+    let listener = UnixListener::bind(socket).with_context(|| format!("Couldn't bind socket {}", socket.display()))?;
+    fs::set_permissions(socket, fs::Permissions::from_mode(0o600))?;
+    on_ready();
+    // A broken connection shouldn't stop the daemon
+    for stream in listener.incoming().flatten() {
+        let _ = handle_connection(storage, stream);
+    }
+    Ok(())
+}
+
+/// Reads a request from the connection and writes back its response
+fn handle_connection(storage: &SqliteStorage, stream: UnixStream) -> Result<()> {
+    stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+    let mut reader = BufReader::new((&stream).take(MAX_REQUEST_SIZE));
+    let response = match read_fields(&mut reader, 3)?.as_slice() {
+        [action, shell, buffer] if action == SUGGEST_INLINE_ACTION && !buffer.trim().is_empty() => storage
+            .find_command_by_prefix(buffer, shell)?
+            .map(|command| command.cmd),
+        _ => None,
+    };
+    let mut stream = &stream;
+    stream.write_all(response.unwrap_or_default().as_bytes())?;
+    stream.write_all(b"\0")?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Reads the given number of NUL-terminated fields
+fn read_fields(reader: &mut impl BufRead, count: usize) -> Result<Vec<String>> {
+    let mut fields = Vec::with_capacity(count);
+    let mut field = Vec::new();
+    while fields.len() < count {
+        field.clear();
+        reader.read_until(b'\0', &mut field)?;
+        if field.pop() != Some(b'\0') {
+            bail!("Incomplete or too large request");
+        }
+        fields.push(String::from_utf8_lossy(&field).into_owned());
+    }
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::Command, storage::USER_CATEGORY};
+
+    #[test]
+    fn test_handle_connection() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        let mut command = Command::new(USER_CATEGORY, "git log --oneline", "");
+        storage.insert_command(&mut command).unwrap();
+
+        let request = |request: &[u8]| {
+            let (mut client, server) = UnixStream::pair().unwrap();
+            client.write_all(request).unwrap();
+            handle_connection(&storage, server).unwrap();
+            let mut response = Vec::new();
+            client.read_to_end(&mut response).unwrap();
+            String::from_utf8(response).unwrap()
+        };
+        assert_eq!(request(b"suggest-inline\0zsh\0git l\0"), "git log --oneline\0");
+        assert_eq!(request(b"suggest-inline\0zsh\0docker\0"), "\0");
+        assert_eq!(request(b"unknown\0zsh\0git l\0"), "\0");
+
+        let (mut client, server) = UnixStream::pair().unwrap();
+        client.write_all(b"suggest-inline\0zsh\0git l").unwrap();
+        drop(client);
+        assert!(handle_connection(&storage, server).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use std::{fmt, io};
+
+/// Kind of failure, each one mapped into a documented exit code so scripts can branch on them
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum ErrorKind {
+    /// Any other failure
+    Other,
+    /// The command, category or file wasn't found
+    NotFound,
+    /// A remote couldn't be reached
+    Network,
+    /// A remote rejected the credentials
+    Auth,
+    /// The input couldn't be parsed
+    Parse,
+}
+
+impl ErrorKind {
+    /// Retrieves the exit code of the process for this kind of failure
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::NotFound => 2,
+            ErrorKind::Network => 3,
+            ErrorKind::Auth => 4,
+            ErrorKind::Parse => 5,
+        }
+    }
+
+    /// Retrieves the machine-readable name of this kind of failure
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorKind::Other => "other",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::Network => "network",
+            ErrorKind::Auth => "auth",
+            ErrorKind::Parse => "parse",
+        }
+    }
+
+    /// Classifies an error, looking for a known cause on its chain
+    pub fn of(err: &anyhow::Error) -> Self {
+        for cause in err.chain() {
+            if let Some(err) = cause.downcast_ref::<UserFacingError>() {
+                return err.kind;
+            } else if cause.is::<serde_json::Error>() {
+                return ErrorKind::Parse;
+            } else if let Some(err) = cause.downcast_ref::<io::Error>() {
+                if err.kind() == io::ErrorKind::NotFound {
+                    return ErrorKind::NotFound;
+                }
+            }
+            #[cfg(feature = "tldr")]
+            if let Some(err) = cause.downcast_ref::<git2::Error>() {
+                match (err.code(), err.class()) {
+                    (git2::ErrorCode::Auth, _) | (_, git2::ErrorClass::Ssh) => return ErrorKind::Auth,
+                    (_, git2::ErrorClass::Net | git2::ErrorClass::Http | git2::ErrorClass::Ssl) => {
+                        return ErrorKind::Network
+                    }
+                    _ => (),
+                }
+            }
+        }
+        ErrorKind::Other
+    }
+}
+
+/// Error with a message meant to be displayed to the user, along with its kind
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub struct UserFacingError {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl UserFacingError {
+    /// Builds a new [UserFacingError]
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// Builds a new [ErrorKind::NotFound] error
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::NotFound, message)
+    }
+
+    /// Builds a new [ErrorKind::Parse] error
+    pub fn parse(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Parse, message)
+    }
+
+    /// Retrieves the kind of this error
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for UserFacingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for UserFacingError {}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{anyhow, Context};
+
+    use super::*;
+
+    #[test]
+    fn test_error_kind() {
+        assert_eq!(ErrorKind::of(&anyhow!("Unexpected")), ErrorKind::Other);
+
+        let err = Err::<(), _>(UserFacingError::not_found("Command 'x' not found"))
+            .context("Error editing")
+            .unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::NotFound);
+        assert_eq!(ErrorKind::of(&err).exit_code(), 2);
+
+        let err = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        assert_eq!(ErrorKind::of(&err.into()), ErrorKind::Parse);
+    }
+}
@@ -0,0 +1,163 @@
+use std::{env, fs};
+
+use anyhow::Result;
+
+use crate::{
+    init::{Shell, ShellSetup},
+    model::{dangerous_pattern_error, invalid_exec_prefixes},
+    provider::{providers_ttl_error, unknown_providers},
+    secret::SecretResolver,
+    storage::SqliteStorage,
+};
+
+/// Issue found when checking the health of the environment
+#[derive(Clone)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub struct HealthWarning {
+    /// Short summary of the issue
+    pub summary: String,
+    /// Detailed explanation of the issue and how to fix it
+    pub detail: String,
+}
+
+/// Checks the health of the configuration, returning the issues found. It's cheap enough to be run on every search.
+pub fn check_health() -> Vec<HealthWarning> {
+    let mut warnings = Vec::new();
+
+    let unknown = unknown_providers();
+    if !unknown.is_empty() {
+        warnings.push(HealthWarning {
+            summary: "unknown label providers".to_owned(),
+            detail: format!(
+                "INTELLI_LABEL_PROVIDERS contains unknown providers that will be ignored: {}",
+                unknown.join(", ")
+            ),
+        });
+    }
+
+    if let Some(err) = providers_ttl_error() {
+        warnings.push(HealthWarning {
+            summary: "invalid label providers ttl".to_owned(),
+            detail: format!(
+                "INTELLI_LABEL_PROVIDERS_TTL is not a valid duration, so provided suggestions won't be cached: {err}"
+            ),
+        });
+    }
+
+    let unknown = SecretResolver::unknown();
+    if !unknown.is_empty() {
+        warnings.push(HealthWarning {
+            summary: "unknown secret resolvers".to_owned(),
+            detail: format!(
+                "INTELLI_SECRET_RESOLVERS contains unknown resolvers that will be ignored: {}",
+                unknown.join(", ")
+            ),
+        });
+    }
+
+    let invalid = invalid_exec_prefixes();
+    if !invalid.is_empty() {
+        warnings.push(HealthWarning {
+            summary: "invalid exec prefixes".to_owned(),
+            detail: format!(
+                "INTELLI_EXEC_PREFIXES contains rules not formatted as 'selector=prefix' that will be ignored: {}",
+                invalid.join(", ")
+            ),
+        });
+    }
+
+    if let Some(err) = dangerous_pattern_error() {
+        warnings.push(HealthWarning {
+            summary: "invalid dangerous pattern".to_owned(),
+            detail: format!(
+                "INTELLI_DANGEROUS_PATTERN is not a valid regex, so the default one is used instead: {err}"
+            ),
+        });
+    }
+
+    warnings
+}
+
+/// Runs a deeper check than [check_health], including the database integrity and the shell integration, returning the
+/// issues found along with how to fix them
+pub fn diagnose(storage: &SqliteStorage) -> Result<Vec<HealthWarning>> {
+    let mut warnings = check_health();
+
+    // Probing the lock takes the write lock for a moment, so it's not checked on every search
+    if storage.is_locked()? {
+        warnings.push(HealthWarning {
+            summary: "database locked".to_owned(),
+            detail: "The database is locked by another process, changes might fail or take a while to be saved. Check \
+                     for other running intelli-shell processes"
+                .to_owned(),
+        });
+    }
+
+    let problems = storage.integrity_check()?;
+    if !problems.is_empty() {
+        warnings.push(HealthWarning {
+            summary: "database corrupted".to_owned(),
+            detail: format!(
+                "The database integrity check failed, export your commands and import them into a new database: {}",
+                problems.join("; ")
+            ),
+        });
+    }
+
+    match Shell::detect() {
+        None => warnings.push(HealthWarning {
+            summary: "unknown shell".to_owned(),
+            detail: "Couldn't detect a supported shell, set INTELLI_SHELL to bash, zsh, fish or powershell".to_owned(),
+        }),
+        Some(shell) => {
+            let rc_file = shell.rc_file()?;
+            let installed = fs::read_to_string(&rc_file).is_ok_and(|rc| rc.contains("intelli-shell"));
+            if !installed {
+                warnings.push(HealthWarning {
+                    summary: "shell integration not installed".to_owned(),
+                    detail: format!(
+                        "{} doesn't load intelli-shell, run `intelli-shell init --wizard` to set it up",
+                        rc_file.display()
+                    ),
+                });
+            } else if env::var_os("INTELLI_SHELL").is_none() {
+                warnings.push(HealthWarning {
+                    summary: "shell integration not loaded".to_owned(),
+                    detail: format!(
+                        "The integration is installed on {} but it's not loaded on this session, open a new terminal",
+                        rc_file.display()
+                    ),
+                });
+            }
+            if let Ok(setup) = ShellSetup::new(shell, true) {
+                let script = setup.script();
+                if !script.exists() {
+                    warnings.push(HealthWarning {
+                        summary: "shell script not found".to_owned(),
+                        detail: format!(
+                            "{} doesn't exist, check INTELLI_HOME or install intelli-shell again",
+                            script.display()
+                        ),
+                    });
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "tldr")]
+    {
+        use crate::storage::{USER_CATEGORY, VAULT_CATEGORY};
+        let fetched = storage
+            .get_categories()?
+            .iter()
+            .any(|(category, _)| category != USER_CATEGORY && category != VAULT_CATEGORY);
+        if !fetched {
+            warnings.push(HealthWarning {
+                summary: "tldr commands not fetched".to_owned(),
+                detail: "No tldr commands are stored, run `intelli-shell fetch` to have them available".to_owned(),
+            });
+        }
+    }
+
+    Ok(warnings)
+}
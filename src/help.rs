@@ -0,0 +1,159 @@
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Regex to match the long options documented on a help text, like `  -f, --file <FILE>   Description`
+static OPTION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s+(?:-\w(?:,\s*|\s+))?(--[a-zA-Z0-9][\w-]*)(?:(?:[ =]|\[=)<?([a-zA-Z][\w-]*)>?\]?)?\s{2,}(\S.*)$")
+        .unwrap()
+});
+
+/// Options that are never worth bookmarking
+const SKIPPED_OPTIONS: [&str; 2] = ["--help", "--version"];
+
+/// Captures the help of the given tool, from its `--help` output or its man page otherwise, returning the commands
+/// found on it (the examples and the long options) along with their description, tagged with the tool name
+pub fn read_help_commands(tool: &str) -> Result<Vec<(String, String)>> {
+    let words = tool.split_whitespace().collect_vec();
+    let Some((program, args)) = words.split_first() else {
+        bail!("The command to import can't be empty");
+    };
+    let tool = words.join(" ");
+
+    let help = Command::new(program)
+        .args(args)
+        .arg("--help")
+        .output()
+        .ok()
+        .map(|output| {
+            if output.stdout.is_empty() {
+                String::from_utf8_lossy(&output.stderr).into_owned()
+            } else {
+                String::from_utf8_lossy(&output.stdout).into_owned()
+            }
+        })
+        .unwrap_or_default();
+    let mut commands = parse_help(&tool, &help);
+
+    if commands.is_empty() {
+        let man = Command::new("man")
+            .args(&words)
+            .env("MANPAGER", "cat")
+            .env("MANWIDTH", "120")
+            .output()
+            .with_context(|| format!("Couldn't get the help of '{tool}'"))?;
+        commands = parse_help(&tool, &strip_overstrike(&String::from_utf8_lossy(&man.stdout)));
+    }
+
+    Ok(commands)
+}
+
+/// Parses a help text, returning the examples of the tool and a command for every long option
+fn parse_help(tool: &str, help: &str) -> Vec<(String, String)> {
+    let tag = format!("#{}", tool.split_whitespace().join("-"));
+    let mut commands = Vec::new();
+    let mut previous = "";
+    for line in help.lines() {
+        let trimmed = line.trim();
+        let example = trimmed.strip_prefix("$ ").unwrap_or(trimmed);
+        if example.starts_with(&format!("{tool} ")) {
+            // Examples are described by their inline comment or the comment or caption right before them
+            let (cmd, comment) = match example.split_once(" # ") {
+                Some((cmd, comment)) => (cmd.trim(), Some(comment.trim())),
+                None => (example, None),
+            };
+            let description = comment
+                .or_else(|| previous.strip_prefix('#').map(str::trim))
+                .or_else(|| {
+                    previous
+                        .strip_suffix(':')
+                        .filter(|_| line.starts_with(char::is_whitespace))
+                })
+                .filter(|d| !d.is_empty() && !d.eq_ignore_ascii_case("examples"))
+                .map(ToOwned::to_owned)
+                .unwrap_or_else(|| format!("Example of {tool}"));
+            commands.push((cmd.to_owned(), format!("{description} {tag}")));
+        } else if let Some(caps) = OPTION_REGEX.captures(line) {
+            let option = &caps[1];
+            if !SKIPPED_OPTIONS.contains(&option) {
+                let cmd = match caps.get(2) {
+                    Some(value) => format!(
+                        "{tool} {option} {{{{{}}}}}",
+                        value.as_str().to_lowercase().replace('_', "-")
+                    ),
+                    None => format!("{tool} {option}"),
+                };
+                let description = caps[3].trim().trim_end_matches('.');
+                commands.push((cmd, format!("{description} {tag}")));
+            }
+        }
+        if !trimmed.is_empty() {
+            previous = trimmed;
+        }
+    }
+    commands.into_iter().unique_by(|(cmd, _)| cmd.clone()).collect()
+}
+
+/// Removes the overstrike sequences used by man pages to render bold and underlined text
+fn strip_overstrike(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\u{8}' {
+            stripped.pop();
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_help() {
+        let help = r#"Usage: tar [OPTION...] [FILE]...
+
+Examples:
+  tar -cf archive.tar foo bar  # Create archive.tar from files foo and bar.
+  # List all files in archive.tar verbosely.
+  tar -tvf archive.tar
+
+ Main operation mode:
+  -c, --create               create a new archive
+  -f, --file=ARCHIVE         use archive file or device ARCHIVE
+      --exclude <PATTERN>    exclude files matching PATTERN
+      --color[=WHEN]         colorize the output.
+  -?, --help                 give this help list
+"#;
+        assert_eq!(
+            parse_help("tar", help),
+            vec![
+                (
+                    "tar -cf archive.tar foo bar".to_owned(),
+                    "Create archive.tar from files foo and bar. #tar".to_owned()
+                ),
+                (
+                    "tar -tvf archive.tar".to_owned(),
+                    "List all files in archive.tar verbosely. #tar".to_owned()
+                ),
+                ("tar --create".to_owned(), "create a new archive #tar".to_owned()),
+                (
+                    "tar --file {{archive}}".to_owned(),
+                    "use archive file or device ARCHIVE #tar".to_owned()
+                ),
+                (
+                    "tar --exclude {{pattern}}".to_owned(),
+                    "exclude files matching PATTERN #tar".to_owned()
+                ),
+                ("tar --color {{when}}".to_owned(), "colorize the output #tar".to_owned()),
+            ]
+        );
+
+        assert_eq!(strip_overstrike("t\u{8}ta\u{8}ar\u{8}r"), "tar");
+    }
+}
@@ -0,0 +1,97 @@
+use std::{env, path::PathBuf};
+
+use directories::BaseDirs;
+use itertools::Itertools;
+
+use crate::common::{current_shell, remove_newlines};
+
+/// Determines the history file of the current shell, if any
+pub fn default_history_file() -> Option<PathBuf> {
+    if let Some(file) = env::var_os("HISTFILE").filter(|f| !f.is_empty()) {
+        return Some(PathBuf::from(file));
+    }
+    let dirs = BaseDirs::new()?;
+    if cfg!(windows) {
+        return Some(
+            dirs.data_dir()
+                .join("Microsoft")
+                .join("Windows")
+                .join("PowerShell")
+                .join("PSReadLine")
+                .join("ConsoleHost_history.txt"),
+        );
+    }
+    match current_shell().as_str() {
+        "zsh" => Some(dirs.home_dir().join(".zsh_history")),
+        "fish" => Some(dirs.data_local_dir().join("fish").join("fish_history")),
+        _ => Some(dirs.home_dir().join(".bash_history")),
+    }
+}
+
+/// Parses the content of a history file (plain, zsh extended or fish formats), returning every distinct command
+/// along with the number of times it was found, most frequent first
+pub fn parse_history(content: &str) -> Vec<(String, u64)> {
+    let mut commands = Vec::new();
+    let mut pending: Option<String> = None;
+    for line in content.lines() {
+        // Multiline commands are stored with a trailing backslash on every line but the last one
+        let line = match pending.take() {
+            Some(prev) => format!("{prev}\n{line}"),
+            None => line.to_owned(),
+        };
+        if line.ends_with('\\') {
+            pending = Some(line);
+            continue;
+        }
+
+        let cmd = if let Some(cmd) = line.strip_prefix("- cmd: ") {
+            // Fish format
+            cmd.replace("\\n", "\n").replace("\\\\", "\\")
+        } else if let Some((_, cmd)) = line.strip_prefix(": ").and_then(|l| l.split_once(';')) {
+            // Zsh extended format
+            cmd.to_owned()
+        } else if line.starts_with("  when: ") || line.starts_with("  paths:") || line.starts_with("    - ") {
+            // Fish metadata
+            continue;
+        } else {
+            line
+        };
+
+        let cmd = remove_newlines(cmd).trim().to_owned();
+        if !cmd.is_empty() && !cmd.starts_with('#') {
+            commands.push(cmd);
+        }
+    }
+
+    commands
+        .into_iter()
+        .counts()
+        .into_iter()
+        .map(|(cmd, count)| (cmd, count as u64))
+        .sorted_by(|(a_cmd, a_count), (b_cmd, b_count)| b_count.cmp(a_count).then_with(|| a_cmd.cmp(b_cmd)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_history() {
+        let zsh = ": 1690000000:0;ls -la\n: 1690000001:0;git status\n: 1690000002:0;ls -la\n";
+        assert_eq!(
+            parse_history(zsh),
+            vec![("ls -la".to_owned(), 2), ("git status".to_owned(), 1)]
+        );
+
+        let fish =
+            "- cmd: cargo build\n  when: 1690000000\n- cmd: cargo build\n  when: 1690000001\n  paths:\n    - src\n";
+        assert_eq!(parse_history(fish), vec![("cargo build".to_owned(), 2)]);
+
+        let bash = "echo one \\\n  two\ncd ~\n";
+        assert_eq!(
+            parse_history(bash),
+            vec![("cd ~".to_owned(), 1), ("echo one two".to_owned(), 1)]
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use std::{
+    env,
+    fmt::Write as _,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use directories::{BaseDirs, UserDirs};
+
+/// Shells supported by the shell integration
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+}
+
+impl Shell {
+    /// Detects the shell of the current user, from `INTELLI_SHELL` if set or `SHELL` otherwise
+    pub fn detect() -> Option<Self> {
+        env::var("INTELLI_SHELL")
+            .ok()
+            .and_then(|s| Self::from_name(&s))
+            .or_else(|| env::var("SHELL").ok().and_then(|s| Self::from_name(&s)))
+    }
+
+    /// Parses a shell from its name or the path of its binary
+    fn from_name(name: &str) -> Option<Self> {
+        match Path::new(name).file_name()?.to_str()? {
+            "bash" => Some(Self::Bash),
+            "zsh" => Some(Self::Zsh),
+            "fish" => Some(Self::Fish),
+            "powershell" | "powershell.exe" | "pwsh" | "pwsh.exe" => Some(Self::PowerShell),
+            _ => None,
+        }
+    }
+
+    /// Retrieves the profile file sourced by the shell when starting
+    pub fn rc_file(self) -> Result<PathBuf> {
+        let home = || {
+            BaseDirs::new()
+                .map(|dirs| dirs.home_dir().to_path_buf())
+                .ok_or_else(|| anyhow!("Couldn't determine the home dir"))
+        };
+        Ok(match self {
+            Self::Bash => home()?.join(".bashrc"),
+            Self::Zsh => home()?.join(".zshrc"),
+            Self::Fish => home()?.join(".config").join("fish").join("config.fish"),
+            Self::PowerShell => {
+                // PowerShell 7 has its own profile, Windows PowerShell's one is used unless it's already there
+                let documents = match UserDirs::new().and_then(|dirs| dirs.document_dir().map(Path::to_path_buf)) {
+                    Some(documents) => documents,
+                    None => home()?.join("Documents"),
+                };
+                let profile = documents.join("PowerShell").join("Microsoft.PowerShell_profile.ps1");
+                if profile.exists() {
+                    profile
+                } else {
+                    documents
+                        .join("WindowsPowerShell")
+                        .join("Microsoft.PowerShell_profile.ps1")
+                }
+            }
+        })
+    }
+}
+
+/// Setup of the shell integration
+pub struct ShellSetup {
+    /// Shell to be integrated
+    pub shell: Shell,
+    /// Directory where IntelliShell is installed, containing the `bin` folder
+    pub home: PathBuf,
+    /// Whether the interface is rendered inline instead of full-screen
+    pub inline: bool,
+}
+
+impl ShellSetup {
+    /// Builds a new setup for the given shell, for the current installation
+    pub fn new(shell: Shell, inline: bool) -> Result<Self> {
+        Ok(Self {
+            shell,
+            home: installation_dir()?,
+            inline,
+        })
+    }
+
+    /// Retrieves the shell integration script to be sourced
+    pub fn script(&self) -> PathBuf {
+        let script = match self.shell {
+            Shell::Bash | Shell::Zsh => "intelli-shell.sh",
+            Shell::Fish => "intelli-shell.fish",
+            Shell::PowerShell => "intelli-shell.ps1",
+        };
+        self.home.join("bin").join(script)
+    }
+
+    /// Builds the snippet to be appended to the profile file, like the install script does
+    pub fn snippet(&self) -> String {
+        let home = self.home.to_string_lossy();
+        let mut snippet = String::from("\n# IntelliShell\n");
+        match self.shell {
+            Shell::Bash | Shell::Zsh => {
+                let _ = writeln!(snippet, "export INTELLI_HOME='{home}'");
+                if !self.inline {
+                    snippet.push_str("export INTELLI_INLINE=0\n");
+                }
+                snippet.push_str("# export INTELLI_SEARCH_HOTKEY=\\\\C-@\n");
+                snippet.push_str("# export INTELLI_LABEL_HOTKEY=\\\\C-l\n");
+                snippet.push_str("# export INTELLI_BOOKMARK_HOTKEY=\\\\C-b\n");
+                snippet.push_str("# export INTELLI_SKIP_ESC_BIND=0\n");
+                snippet.push_str("# export INTELLI_SKIP_HISTORY_SETUP=0\n");
+                snippet.push_str("alias intelli-shell=\"'$INTELLI_HOME/bin/intelli-shell'\"\n");
+                snippet.push_str("source \"$INTELLI_HOME/bin/intelli-shell.sh\"\n");
+            }
+            Shell::Fish => {
+                let _ = writeln!(snippet, "set -gx INTELLI_HOME '{home}'");
+                if !self.inline {
+                    snippet.push_str("set -gx INTELLI_INLINE 0\n");
+                }
+                snippet.push_str("# set -gx INTELLI_SEARCH_HOTKEY \\cr\n");
+                snippet.push_str("# set -gx INTELLI_LABEL_HOTKEY \\cl\n");
+                snippet.push_str("# set -gx INTELLI_BOOKMARK_HOTKEY \\cb\n");
+                snippet.push_str("# set -gx INTELLI_SKIP_ESC_BIND 0\n");
+                snippet.push_str("source \"$INTELLI_HOME/bin/intelli-shell.fish\"\n");
+            }
+            Shell::PowerShell => {
+                let _ = writeln!(snippet, "$env:INTELLI_HOME = '{home}'");
+                if !self.inline {
+                    snippet.push_str("$env:INTELLI_INLINE = '0'\n");
+                }
+                snippet.push_str("# $env:INTELLI_SEARCH_HOTKEY = 'Ctrl+Spacebar'\n");
+                snippet.push_str("# $env:INTELLI_BOOKMARK_HOTKEY = 'Ctrl+b'\n");
+                snippet.push_str("# $env:INTELLI_LABEL_HOTKEY = 'Ctrl+l'\n");
+                snippet.push_str("# $env:INTELLI_SKIP_HISTORY_SETUP = '0'\n");
+                snippet.push_str(". $env:INTELLI_HOME\\bin\\intelli-shell.ps1\n");
+            }
+        }
+        snippet
+    }
+}
+
+/// Appends the shell integration snippet to the given profile file, unless it already references IntelliShell.
+///
+/// Returns whether the file was updated or not.
+pub fn install_shell_integration(rc_file: impl AsRef<Path>, setup: &ShellSetup) -> Result<bool> {
+    let rc_file = rc_file.as_ref();
+    let current = fs::read_to_string(rc_file).unwrap_or_default();
+    if current.contains("intelli-shell") {
+        return Ok(false);
+    }
+    if let Some(dir) = rc_file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(rc_file)
+        .with_context(|| format!("Error opening {}", rc_file.display()))?;
+    file.write_all(setup.snippet().as_bytes())?;
+    Ok(true)
+}
+
+/// Determines the directory where IntelliShell is installed, from `INTELLI_HOME` if set or from the location of the
+/// current binary otherwise
+fn installation_dir() -> Result<PathBuf> {
+    if let Some(home) = env::var_os("INTELLI_HOME") {
+        return Ok(PathBuf::from(home));
+    }
+    let exe = env::current_exe().context("Couldn't determine the current binary")?;
+    exe.parent()
+        .filter(|dir| dir.ends_with("bin"))
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .ok_or_else(|| anyhow!("Couldn't determine the installation dir, set INTELLI_HOME to it"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_setup() {
+        assert_eq!(Shell::from_name("/usr/bin/zsh"), Some(Shell::Zsh));
+        assert_eq!(Shell::from_name("fish"), Some(Shell::Fish));
+        assert_eq!(Shell::from_name("pwsh"), Some(Shell::PowerShell));
+        assert_eq!(Shell::from_name("/bin/sh"), None);
+
+        let setup = ShellSetup {
+            shell: Shell::Fish,
+            home: PathBuf::from("/opt/intelli-shell"),
+            inline: false,
+        };
+        let snippet = setup.snippet();
+        assert!(snippet.contains("set -gx INTELLI_HOME '/opt/intelli-shell'\n"));
+        assert!(snippet.contains("set -gx INTELLI_INLINE 0\n"));
+        assert!(snippet.ends_with("source \"$INTELLI_HOME/bin/intelli-shell.fish\"\n"));
+        assert_eq!(
+            setup.script(),
+            PathBuf::from("/opt/intelli-shell/bin/intelli-shell.fish")
+        );
+
+        let setup = ShellSetup {
+            shell: Shell::Bash,
+            inline: true,
+            ..setup
+        };
+        assert!(!setup.snippet().contains("INTELLI_INLINE"));
+
+        let setup = ShellSetup {
+            shell: Shell::PowerShell,
+            inline: false,
+            ..setup
+        };
+        let snippet = setup.snippet();
+        assert!(snippet.contains("$env:INTELLI_INLINE = '0'\n"));
+        assert!(snippet.ends_with(". $env:INTELLI_HOME\\bin\\intelli-shell.ps1\n"));
+    }
+}
@@ -0,0 +1,348 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    env, fs,
+    hash::{Hash, Hasher},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use anyhow::{Context, Result};
+
+use crate::common::unix_timestamp;
+
+/// Usage increment pending to be persisted
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum UsageIncrement {
+    Command {
+        id: i64,
+        timestamp: u64,
+        /// Project directory where the command was used, if known
+        path: Option<String>,
+    },
+    LabelSuggestion {
+        /// Profile the suggestion belongs to
+        profile: String,
+        flat_root_cmd: String,
+        flat_label: String,
+        suggestion: String,
+    },
+}
+
+impl UsageIncrement {
+    /// Builds a new command usage increment, within the given project
+    pub fn command(id: i64, project: Option<&Path>) -> Self {
+        UsageIncrement::Command {
+            id,
+            timestamp: unix_timestamp(),
+            path: project.map(|p| p.to_string_lossy().into_owned()),
+        }
+    }
+
+    /// Serializes the increment into a single line, escaping the fields so they can't break it
+    fn to_line(&self) -> String {
+        match self {
+            UsageIncrement::Command { id, timestamp, path } => {
+                format!("C\t{id}\t{timestamp}\t{}", escape(path.as_deref().unwrap_or_default()))
+            }
+            UsageIncrement::LabelSuggestion {
+                profile,
+                flat_root_cmd,
+                flat_label,
+                suggestion,
+            } => format!(
+                "L\t{}\t{}\t{}\t{}",
+                escape(profile),
+                escape(flat_root_cmd),
+                escape(flat_label),
+                escape(suggestion)
+            ),
+        }
+    }
+
+    /// Deserializes an increment from a line, if valid
+    fn from_line(line: &str) -> Option<Self> {
+        let mut parts = line.splitn(5, '\t');
+        match parts.next()? {
+            "C" => Some(UsageIncrement::Command {
+                id: parts.next()?.parse().ok()?,
+                timestamp: parts.next()?.parse().ok()?,
+                path: parts.next().filter(|p| !p.is_empty()).map(unescape),
+            }),
+            "L" => Some(UsageIncrement::LabelSuggestion {
+                profile: unescape(parts.next()?),
+                flat_root_cmd: unescape(parts.next()?),
+                flat_label: unescape(parts.next()?),
+                suggestion: unescape(parts.next()?),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Escapes the backslashes, tabs and line breaks of a journal field
+fn escape(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverts the [escape] of a journal field
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Usage increments buffered in memory, backed by a journal file to survive crashes
+pub struct UsageJournal {
+    /// Directory where the journals of the same database are stored
+    dir: PathBuf,
+    /// Journal file of the current process, if any
+    path: Option<PathBuf>,
+    /// Handle of the journal file once created, locked while the process is alive so it's not taken as an orphan
+    file: Option<fs::File>,
+    /// Increments pending to be persisted
+    pending: Vec<UsageIncrement>,
+}
+
+impl UsageJournal {
+    /// Builds a new journal for the given database, backed by a temporary file of the current process
+    pub fn new(db_path: impl AsRef<Path>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        db_path.as_ref().hash(&mut hasher);
+        let dir = env::temp_dir()
+            .join("intelli-shell")
+            .join(format!("{:x}", hasher.finish()));
+        Self {
+            path: Some(dir.join(format!("usage-{}.journal", process::id()))),
+            dir,
+            file: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Builds a new journal without any backing file
+    pub fn in_memory() -> Self {
+        Self {
+            dir: PathBuf::new(),
+            path: None,
+            file: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Appends a new increment to the journal
+    pub fn push(&mut self, increment: UsageIncrement) -> Result<()> {
+        if let Some(path) = &self.path {
+            if self.file.is_none() {
+                fs::create_dir_all(&self.dir).context("Error creating journal dir")?;
+                let file = fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .context("Error opening journal file")?;
+                file.lock().context("Error locking journal file")?;
+                self.file = Some(file);
+            }
+            if let Some(file) = &mut self.file {
+                writeln!(file, "{}", increment.to_line()).context("Error writing journal file")?;
+            }
+        }
+        self.pending.push(increment);
+        Ok(())
+    }
+
+    /// Determines if there are increments pending to be persisted
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Retrieves the pending increments
+    pub fn pending(&self) -> &[UsageIncrement] {
+        &self.pending
+    }
+
+    /// Clears the pending increments, once persisted
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        if let (Some(path), Some(_)) = (&self.path, self.file.take()) {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    /// Reads the journals left behind by crashed processes, the ones not locked by a live process
+    pub fn orphans(&self) -> Vec<OrphanJournal> {
+        if self.path.is_none() {
+            return Vec::new();
+        }
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| Some(path) != self.path.as_ref())
+            .filter(|path| path.extension().map(|e| e == "journal").unwrap_or(false))
+            .filter_map(|path| {
+                let mut file = fs::File::open(&path).ok()?;
+                file.try_lock().ok()?;
+                // Another process could have recovered and removed it while waiting for the lock
+                fs::metadata(&path).ok()?;
+                let mut content = String::new();
+                file.read_to_string(&mut content).ok()?;
+                let increments = content.lines().filter_map(UsageIncrement::from_line).collect();
+                Some(OrphanJournal {
+                    path,
+                    increments,
+                    _lock: file,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Journal left behind by a crashed process, locked until removed so no other process recovers it as well
+pub struct OrphanJournal {
+    /// Path of the journal file
+    path: PathBuf,
+    /// Increments pending to be persisted
+    pub increments: Vec<UsageIncrement>,
+    /// Lock of the journal file
+    _lock: fs::File,
+}
+
+impl OrphanJournal {
+    /// Removes the journal file, once its increments have been persisted
+    pub fn remove(self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a journal for a database that doesn't exist, so it gets its own directory
+    fn test_journal() -> UsageJournal {
+        UsageJournal::new(format!("test-{}.db3", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_line_round_trip() {
+        let increments = [
+            UsageIncrement::Command {
+                id: 42,
+                timestamp: 1_700_000_000,
+                path: Some("/home/user/my\tproject".into()),
+            },
+            UsageIncrement::Command {
+                id: 7,
+                timestamp: 0,
+                path: None,
+            },
+            UsageIncrement::LabelSuggestion {
+                profile: "work".into(),
+                flat_root_cmd: "git checkout".into(),
+                flat_label: "branch".into(),
+                suggestion: "multi\nline \\n value\r\twith tabs\\".into(),
+            },
+        ];
+        for increment in increments {
+            let line = increment.to_line();
+            assert!(!line.contains('\n'));
+            let fields = match increment {
+                UsageIncrement::Command { .. } => 4,
+                UsageIncrement::LabelSuggestion { .. } => 5,
+            };
+            assert_eq!(line.split('\t').count(), fields);
+            assert_eq!(UsageIncrement::from_line(&line), Some(increment));
+        }
+
+        assert_eq!(UsageIncrement::from_line("X\t1\t2\t"), None);
+        assert_eq!(UsageIncrement::from_line("C\tnot-an-id\t2\t"), None);
+        assert_eq!(UsageIncrement::from_line("L\tgit"), None);
+    }
+
+    #[test]
+    fn test_push_and_clear() {
+        let mut journal = test_journal();
+        let path = journal.path.clone().unwrap();
+        assert!(journal.is_empty());
+
+        let increment = UsageIncrement::LabelSuggestion {
+            profile: "default".into(),
+            flat_root_cmd: "ssh".into(),
+            flat_label: "host".into(),
+            suggestion: "server\n1".into(),
+        };
+        journal.push(UsageIncrement::command(1, None)).unwrap();
+        journal.push(increment.clone()).unwrap();
+        assert_eq!(journal.pending().len(), 2);
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+        assert_eq!(
+            content.lines().nth(1).and_then(UsageIncrement::from_line),
+            Some(increment)
+        );
+
+        journal.clear();
+        assert!(journal.is_empty());
+        assert!(!path.exists());
+
+        let _ = fs::remove_dir_all(&journal.dir);
+    }
+
+    #[test]
+    fn test_orphans() {
+        let mut journal = test_journal();
+        journal.push(UsageIncrement::command(1, None)).unwrap();
+
+        // A journal locked by a live process is never taken as an orphan
+        let mut live = test_journal();
+        live.dir = journal.dir.clone();
+        live.path = Some(journal.dir.join("usage-live.journal"));
+        live.push(UsageIncrement::command(2, None)).unwrap();
+
+        let orphan = UsageIncrement::command(3, Some(Path::new("/tmp/project")));
+        let orphan_path = journal.dir.join("usage-0.journal");
+        fs::write(&orphan_path, format!("{}\ngarbage\n", orphan.to_line())).unwrap();
+        fs::write(journal.dir.join("other.txt"), "C\t4\t0\t").unwrap();
+
+        let orphans = journal.orphans();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].increments, vec![orphan]);
+
+        // The orphan stays locked until removed
+        assert!(journal.orphans().is_empty());
+        orphans.into_iter().for_each(OrphanJournal::remove);
+        assert!(!orphan_path.exists());
+        assert!(journal.orphans().is_empty());
+
+        // Journals without a backing file have no orphans
+        assert!(UsageJournal::in_memory().orphans().is_empty());
+
+        let _ = fs::remove_dir_all(&journal.dir);
+    }
+}
@@ -15,18 +15,82 @@
 //! - Non-intrusive (inline) and full-screen interfaces
 //! - Fetch command to parse and store [tldr](https://github.com/tldr-pages/tldr) pages (Thanks to them!)
 //! - Portability. You can use bookmarked commands in any supported shell, as well as exporting and importing elsewhere.
+//!
+//! # Library usage
+//!
+//! The storage can be used programmatically to search and insert commands, without the CLI and TUI layers, by
+//! disabling the default `tui` feature:
+//!
+//! ```no_run
+//! use intelli_shell::{
+//!     model::Command,
+//!     storage::{SqliteStorage, USER_CATEGORY},
+//! };
+//!
+//! let storage = SqliteStorage::new()?.with_profile("work");
+//! storage.insert_command(&mut Command::new(
+//!     USER_CATEGORY,
+//!     "ls -la",
+//!     "list files #cool",
+//! ))?;
+//! for command in storage.find_commands("#cool")? {
+//!     println!("{command}");
+//! }
+//! # anyhow::Ok(())
+//! ```
 
 #![forbid(unsafe_code)]
 
 pub mod debug;
 pub mod model;
+#[cfg(feature = "tui")]
 pub mod process;
 pub mod storage;
+#[cfg(feature = "tui")]
 pub mod theme;
 
+#[cfg(feature = "tldr")]
 mod cfg;
 mod common;
+#[cfg(unix)]
+mod daemon;
+mod error;
+#[cfg(feature = "tui")]
+mod health;
+mod help;
+mod history;
+mod init;
+mod journal;
+mod markdown;
+mod paste;
+mod plugin;
+#[cfg(feature = "tui")]
+mod provider;
+mod report;
+#[cfg(feature = "tui")]
+mod secret;
+mod server;
+mod snippets;
+mod ssh;
 #[cfg(feature = "tldr")]
 mod tldr;
 
-pub use common::{remove_newlines, ExecutionContext, Process, ProcessOutput};
+pub use common::{
+    current_project_dir, current_shell, execute_shell_command, execute_shell_command_with, parse_duration,
+    remove_newlines, with_shell_continuations, ExecutionOptions,
+};
+#[cfg(feature = "tui")]
+pub use common::{osc52_copy, qr_code, EmptyQuery, ExecutionContext, Process, ProcessOutput};
+#[cfg(unix)]
+pub use daemon::serve_daemon;
+pub use error::{ErrorKind, UserFacingError};
+#[cfg(feature = "tui")]
+pub use health::{check_health, diagnose, HealthWarning};
+pub use history::default_history_file;
+pub use init::{install_shell_integration, Shell, ShellSetup};
+pub use paste::{is_url, paste_endpoint, upload_paste};
+pub use report::{record_panic, write_report};
+pub use server::{serve, DEFAULT_SERVER_PORT};
+pub use ssh::default_ssh_config_file;
+#[cfg(feature = "tldr")]
+pub use tldr::scrape_tldr_github;
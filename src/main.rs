@@ -1,11 +1,16 @@
 use std::{
+    collections::HashMap,
     fs,
-    io::{self, Write},
+    io::{self, IsTerminal, Write},
     panic,
+    path::PathBuf,
+    process,
+    str::FromStr,
+    time::Duration,
 };
 
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand};
 use crossterm::{
     cursor,
     event::{DisableMouseCapture, EnableMouseCapture},
@@ -15,14 +20,28 @@ use crossterm::{
     QueueableCommand,
 };
 use intelli_shell::{
-    model::{AsLabeledCommand, Command},
-    process::{EditCommandProcess, LabelProcess, SearchProcess},
-    remove_newlines,
-    storage::{SqliteStorage, USER_CATEGORY},
-    theme, ExecutionContext, Process, ProcessOutput,
+    check_health, current_project_dir, current_shell, default_history_file, default_ssh_config_file, diagnose,
+    execute_shell_command_with, install_shell_integration, is_url,
+    model::{
+        category_tree, is_dangerous, may_contain_secrets, strip_cursor_marker, AsLabeledCommand, Command, ExportFormat,
+        ImportChange,
+    },
+    osc52_copy, paste_endpoint,
+    process::{
+        CycleAliasProcess, EditCommandProcess, LabelProcess, NuCompleterProcess, SearchProcess, SuggestInlineProcess,
+    },
+    qr_code, record_panic, remove_newlines, serve,
+    storage::{default_library_file, PruneCriteria, SqliteStorage, DEFAULT_PROFILE, USER_CATEGORY},
+    theme::{ThemeBorder, ThemeVariant},
+    upload_paste, with_shell_continuations, write_report, EmptyQuery, ErrorKind, ExecutionContext, ExecutionOptions,
+    Process, ProcessOutput, Shell, ShellSetup, UserFacingError, DEFAULT_SERVER_PORT,
 };
+#[cfg(unix)]
+use intelli_shell::serve_daemon;
+use itertools::Itertools;
 use once_cell::sync::OnceCell;
 use ratatui::{backend::CrosstermBackend, layout::Rect, Terminal};
+use regex::Regex;
 
 /// Command line arguments
 #[derive(Parser)]
@@ -37,10 +56,61 @@ struct Args {
     #[arg(long)]
     inline_extra_line: bool,
 
+    /// Whether to capture the mouse, to select items by clicking them and scroll with the wheel
+    #[arg(long, env = "INTELLI_MOUSE", value_parser = clap::builder::BoolishValueParser::new())]
+    mouse: bool,
+
     /// Path of an existing file to write the output to (defaults to stdout)
     #[arg(short, long)]
     file_output: Option<String>,
 
+    /// Whether the cursor marker of the output is kept, set by the shell integration which places the cursor there
+    #[arg(long, hide = true, env = "INTELLI_CURSOR_MARKER", value_parser = clap::builder::BoolishValueParser::new())]
+    cursor_marker: bool,
+
+    /// Whether to skip informative messages and report errors as a single JSON line, for scripting
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Profile to scope the commands to, like "work" or "personal"
+    #[arg(short, long, env = "INTELLI_PROFILE", default_value = DEFAULT_PROFILE)]
+    profile: String,
+
+    /// Theme variant of the interface (auto, light, dark), auto picks one based on the terminal background
+    #[arg(long, env = "INTELLI_THEME", default_value = "auto")]
+    theme: ThemeVariant,
+
+    /// Type of the borders of the interface when it's not inline (plain, rounded, double, thick), defaults to the one
+    /// of the theme
+    #[arg(long, env = "INTELLI_BORDER")]
+    border: Option<ThemeBorder>,
+
+    /// Directory of markdown files whose shell code blocks are kept in sync on the vault category
+    #[arg(long, env = "INTELLI_VAULT_DIR")]
+    vault: Option<PathBuf>,
+
+    /// Shared file of commands (on any of the import formats) kept in sync on the read-only library category, defaults
+    /// to /etc/.intellishell if it exists
+    #[arg(long, env = "INTELLI_LIBRARY")]
+    library: Option<PathBuf>,
+
+    /// Whether the terminal title should display the commands executed directly, by `run` or `repl`
+    #[arg(long, env = "INTELLI_EXEC_TITLE", value_parser = clap::builder::BoolishValueParser::new())]
+    exec_title: bool,
+
+    /// Duration after which the completion of the commands executed directly is notified, like "30s"
+    #[arg(long, env = "INTELLI_NOTIFY_AFTER", value_parser = intelli_shell::parse_duration)]
+    notify_after: Option<Duration>,
+
+    /// Half-life of the usage of the commands when ranking them, like "30d", so old favorites fade away over time
+    #[arg(long, env = "INTELLI_USAGE_HALF_LIFE", value_parser = intelli_shell::parse_duration)]
+    usage_half_life: Option<Duration>,
+
+    /// Whether the database is encrypted, its passphrase is read from INTELLI_DB_PASSPHRASE or prompted otherwise
+    #[cfg(feature = "encryption")]
+    #[arg(long, env = "INTELLI_DB_ENCRYPTED", value_parser = clap::builder::BoolishValueParser::new())]
+    encrypted: bool,
+
     /// Action to be executed
     #[command(subcommand)]
     action: Actions,
@@ -49,6 +119,27 @@ struct Args {
 #[derive(Subcommand)]
 #[cfg_attr(debug_assertions, derive(Debug))]
 enum Actions {
+    /// Prints the shell integration to be added to the profile file, or guides through the setup with --wizard
+    Init {
+        /// Guides through the setup interactively, installing the shell integration into the profile file
+        #[arg(long)]
+        wizard: bool,
+    },
+    /// Prints the completion script of this CLI for the given shell
+    ///
+    /// Supported shells are bash, zsh, fish, nu and powershell, the aliases of `run`, `edit` and `share` are also
+    /// completed on bash, zsh and fish
+    Completions {
+        /// Shell to generate the completions for
+        shell: CompletionShell,
+    },
+    /// Prints the aliases starting with the given prefix, one per line, to complete them on the shell
+    #[command(hide = true)]
+    CompleteAlias {
+        /// Alias typed so far
+        #[arg(default_value = "")]
+        prefix: String,
+    },
     /// Stores a new user command
     New {
         /// Command to be stored
@@ -59,26 +150,260 @@ enum Actions {
         /// Description of the command
         description: Option<String>,
     },
+    /// Opens the edit interface for an existing command
+    Edit {
+        /// Alias, id or exact command to be edited
+        command: String,
+    },
     /// Opens a new search interface
     Search {
         /// Filter to be applied
         filter: Option<String>,
+
+        /// Commands to show when the filter is empty (all, recent, favorites, workspace, none)
+        #[arg(long, env = "INTELLI_EMPTY_QUERY", default_value = "all")]
+        empty_query: EmptyQuery,
+
+        /// Period of recent activity on the current project considered when focusing, like `7d` or `12h`
+        #[arg(long, env = "INTELLI_FOCUS_PERIOD", default_value = "7d", value_parser = intelli_shell::parse_duration)]
+        focus_period: Duration,
+
+        /// Maximum number of results loaded at once, the next ones are loaded when scrolling past the last one
+        #[arg(long, env = "INTELLI_PAGE_SIZE", default_value_t = 100, value_parser = clap::value_parser!(u16).range(1..))]
+        page_size: u16,
+
+        /// Directory to search from instead of the working one, for the project-aware features (pinned commands, focus
+        /// filter and usage recorded), useful when launched from wrappers or popups
+        #[arg(long, env = "INTELLI_SEARCH_AT", value_name = "DIR")]
+        at: Option<PathBuf>,
+    },
+    /// Prints the top-ranked command starting with the given buffer, to be rendered as ghost text
+    SuggestInline {
+        /// Current shell buffer
+        buffer: String,
+    },
+    /// Prints the command of the nth alias starting with the given buffer, to cycle through them from the shell
+    CycleAlias {
+        /// Current shell buffer, with the alias prefix
+        buffer: String,
+
+        /// Index of the candidate to print, most used first, wrapping around after the last one
+        #[arg(long, default_value_t = 0)]
+        index: usize,
+    },
+    /// Records a command executed on the shell, so it can be bookmarked later from the search interface
+    Record {
+        /// Command executed
+        command: String,
+    },
+    /// Prints the commands completing the given words as JSON, to be used as nushell's `external_completer`
+    NuComplete {
+        /// Words typed so far, the last one being completed
+        #[arg(allow_hyphen_values = true, trailing_var_arg = true)]
+        spans: Vec<String>,
     },
-    /// Opens a new label interface
+    /// Shares some stored commands, copying them into the clipboard or rendering them as a QR code
+    Share {
+        /// Alias, id or exact command to be shared, or the search filter when `--filter` is set
+        command: String,
+
+        /// Shares every command matching the given search filter, instead of a single one
+        #[arg(long)]
+        filter: bool,
+
+        /// Renders a QR code instead of copying into the clipboard
+        #[arg(long)]
+        qr: bool,
+
+        /// Uploads the commands to the paste endpoint (`INTELLI_PASTE_URL`, defaults to paste.rs) and shares the
+        /// command to import them instead, commands kept out of the history or with secret labels are never uploaded
+        #[arg(long)]
+        upload: bool,
+
+        /// Uploads the commands without confirmation
+        #[arg(short, long, requires = "upload")]
+        yes: bool,
+    },
+    /// Flags a stored command to be learnt, tracking how many times it's run, or shows the progress of every command
+    /// being learnt
+    Learn {
+        /// Alias, id or exact command to be learnt
+        command: Option<String>,
+
+        /// Number of runs to consider the command learnt
+        #[arg(long, default_value_t = 10)]
+        goal: u64,
+
+        /// Hides the command on the search results until learnt, showing only its root to practice recall
+        #[arg(long)]
+        hide: bool,
+
+        /// Stops learning the command
+        #[arg(long, requires = "command", conflicts_with_all = ["goal", "hide"])]
+        stop: bool,
+    },
+    /// Runs a stored command right away, replacing its labels with the given values
+    Run {
+        /// Alias, id or exact command to be run
+        command: String,
+
+        /// Value of a label as `name=value`, can be repeated
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_label_value)]
+        vars: Vec<(String, String)>,
+
+        /// Runs the command without confirmation, even if it's dangerous
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Checks the environment, the database integrity and the shell integration, showing how to fix any issue found
+    Doctor,
+    /// Optimizes the search index and compacts the database, to speed up slow searches
+    Optimize,
+    /// Removes the stored commands meeting every given criteria, at least one is required
+    #[command(group = ArgGroup::new("criteria").required(true).multiple(true))]
+    Prune {
+        /// Commands never used or not used for this long (like 90d, 12h or 2w)
+        #[arg(long, group = "criteria", value_parser = intelli_shell::parse_duration)]
+        unused_for: Option<Duration>,
+
+        /// Commands of the given category
+        #[arg(long, group = "criteria")]
+        category: Option<String>,
+
+        /// Commands matching the given regex
+        #[arg(long, group = "criteria", value_name = "REGEX")]
+        matching: Option<Regex>,
+
+        /// Duplicated commands, only differing on their whitespace, keeping the most used one
+        #[arg(long, group = "criteria")]
+        duplicates: bool,
+
+        /// Lists the commands that would be removed, without removing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Encrypts the database with a passphrase, read from INTELLI_DB_PASSPHRASE or prompted otherwise
+    #[cfg(feature = "encryption")]
+    Encrypt,
+    /// Opens a persistent session to search and execute commands repeatedly
+    Repl {
+        /// Commands to show when the filter is empty (all, recent, favorites, workspace, none)
+        #[arg(long, env = "INTELLI_EMPTY_QUERY", default_value = "all")]
+        empty_query: EmptyQuery,
+
+        /// Period of recent activity on the current project considered when focusing, like `7d` or `12h`
+        #[arg(long, env = "INTELLI_FOCUS_PERIOD", default_value = "7d", value_parser = intelli_shell::parse_duration)]
+        focus_period: Duration,
+
+        /// Maximum number of results loaded at once, the next ones are loaded when scrolling past the last one
+        #[arg(long, env = "INTELLI_PAGE_SIZE", default_value_t = 100, value_parser = clap::value_parser!(u16).range(1..))]
+        page_size: u16,
+    },
+    /// Writes a report to debug issues, with the platform info, redacted configuration and recent panics, to be
+    /// attached to a GitHub issue. Nothing is sent anywhere
+    Report {
+        /// File to write the report to
+        #[arg(default_value = "intelli-shell-report.tar")]
+        file: PathBuf,
+    },
+    /// Serves a local REST API to search and store commands, so editors and launchers can integrate with it
+    Serve {
+        /// Port to listen to, on the loopback interface
+        #[arg(short, long, default_value_t = DEFAULT_SERVER_PORT)]
+        port: u16,
+        /// Token the requests must be authenticated with, a random one is generated when not provided
+        #[arg(long, env = "INTELLI_SERVER_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+        /// Selectors of the commands exposed, like `#team` or `cloud/aws`, or prefixed with `!` to exclude them
+        #[arg(long, env = "INTELLI_SERVER_SCOPE", value_delimiter = ',')]
+        scope: Vec<String>,
+    },
+    /// Runs a background daemon keeping the database open, so the shell hooks run on every keystroke (like the inline
+    /// suggestions) are answered without starting a new process
+    #[cfg(unix)]
+    Daemon {
+        /// Unix socket to listen to, the shell integration talks to the daemon when it's set
+        #[arg(long, env = "INTELLI_DAEMON_SOCKET")]
+        socket: PathBuf,
+    },
+    /// Opens a new label interface, or replaces the labels right away when their values are provided
     Label {
         /// Command to replace labels
         command: String,
+
+        /// Value of a label as `key=value`, can be repeated to replace the labels without any interaction
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse_label_value)]
+        values: Vec<(String, String)>,
+
+        /// Reads the label values from a JSON object on stdin, to replace the labels without any interaction
+        #[arg(long)]
+        stdin: bool,
     },
     /// Exports stored user commands
     Export {
-        /// File path to be exported
+        /// File path to be exported, or the location given to the plugin
         #[arg(short, long)]
         file: Option<String>,
+
+        /// Format of the exported commands (text, json, yaml, md), only text can be imported back
+        #[arg(long, default_value = "text")]
+        format: ExportFormat,
+
+        /// Includes the values learned for the labels, to be restored when importing the file
+        #[arg(long)]
+        include_values: bool,
+
+        /// Exports through the given plugin from the plugins dir, which receives the commands as a JSON array
+        #[arg(long, value_name = "NAME", conflicts_with_all = ["format", "include_values"])]
+        plugin: Option<String>,
     },
     /// Imports user commands
     Import {
-        /// File path to be imported
-        file: String,
+        /// File path (or url) to be imported, the history file when importing the shell history, the directory
+        /// when importing markdown files or the location given to the plugin
+        #[arg(required_unless_present_any = ["history", "ssh_config", "command", "plugin"])]
+        file: Option<String>,
+
+        /// Imports the shell history, preserving how many times each command was run
+        #[arg(long)]
+        history: bool,
+
+        /// Imports the shell code blocks of every markdown file within a directory, recursively
+        #[arg(long, conflicts_with = "history")]
+        dir: bool,
+
+        /// Imports the connection commands of the hosts found on the ssh config file (defaults to ~/.ssh/config) and
+        /// the `known_hosts` file next to it
+        #[arg(long, conflicts_with_all = ["history", "dir"])]
+        ssh_config: bool,
+
+        /// Imports the examples and options found on the help of the given tool (its `--help` output or, if none is
+        /// found, its man page), tagged with the tool name
+        #[arg(long, value_name = "TOOL", conflicts_with_all = ["file", "history", "dir", "ssh_config"])]
+        command: Option<String>,
+
+        /// Imports the commands printed as a JSON array by the given plugin from the plugins dir
+        #[arg(long, value_name = "NAME", conflicts_with_all = ["history", "dir", "ssh_config", "command"])]
+        plugin: Option<String>,
+
+        /// Shows the changes the import would produce, without applying them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Merges the commands that could be produced by filling the labels of a stored command into it, like
+        /// `ssh root@db` into `ssh {{user}}@{{host}}`
+        #[arg(long)]
+        match_templates: bool,
+    },
+    /// Manages the categories of the stored commands
+    Category {
+        #[command(subcommand)]
+        action: CategoryActions,
+    },
+    /// Manages the theme of the interface
+    Theme {
+        #[command(subcommand)]
+        action: ThemeActions,
     },
     #[cfg(feature = "tldr")]
     /// Fetches new commands from tldr
@@ -86,40 +411,164 @@ enum Actions {
         /// Category to fetch, skip to fetch for current platform (common, android, osx, linux, windows)
         category: Option<String>,
     },
+    #[cfg(feature = "tldr")]
+    /// Manages commands fetched from tldr
+    Tldr {
+        #[command(subcommand)]
+        action: TldrActions,
+    },
+}
+
+#[derive(Subcommand)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+enum CategoryActions {
+    /// Lists the categories along with their number of commands
+    List {
+        /// Shows the nested categories (like `cloud/aws/ec2`) as a tree, counting the commands of every branch
+        #[arg(long)]
+        tree: bool,
+    },
+    /// Moves a command into a category, which can be nested within others like `cloud/aws/ec2`
+    Set {
+        /// Alias, id or exact command to be moved
+        command: String,
+        /// Category to move it into
+        category: String,
+    },
+    /// Renames a category
+    Rename {
+        /// Current name of the category
+        from: String,
+        /// New name of the category
+        to: String,
+    },
+    /// Merges some categories into another one
+    Merge {
+        /// Categories to be merged
+        #[arg(required = true)]
+        from: Vec<String>,
+        /// Category to merge them into
+        #[arg(long)]
+        into: String,
+    },
+}
+
+#[derive(Subcommand)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+enum ThemeActions {
+    /// Checks the configured theme against common color vision deficiencies, warning about hard to read colors
+    Check,
+}
+
+#[cfg(feature = "tldr")]
+#[derive(Subcommand)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+enum TldrActions {
+    /// Removes fetched commands that have never been used, user commands are kept
+    Prune {
+        /// Also remove commands not used for this long (like 90d, 12h or 2w)
+        #[arg(long, value_parser = intelli_shell::parse_duration)]
+        unused_for: Option<Duration>,
+    },
 }
 
 static PANIC_INFO: OnceCell<String> = OnceCell::new();
 
 fn main() {
-    // Parse arguments
-    let cli = Args::parse();
+    // Parse arguments, reporting invalid ones as a parse failure
+    let cli = Args::try_parse().unwrap_or_else(|err| {
+        if !err.use_stderr() {
+            err.exit()
+        }
+        err.print().expect("couldn't print the error");
+        process::exit(ErrorKind::Parse.exit_code());
+    });
+    let quiet = cli.quiet;
 
     // Set panic hook to avoid printing while on raw mode
     panic::set_hook(Box::new(|info| {
-        PANIC_INFO.get_or_init(|| info.to_string());
+        let info = PANIC_INFO.get_or_init(|| info.to_string());
+        // Keep track of the panic, to be included on the reports
+        let _ = record_panic(info);
     }));
 
     // Run program
     match panic::catch_unwind(|| run(cli)) {
         Ok(Ok(_)) => (),
-        Ok(Err(err)) => eprintln!(" -> Error: {err}"),
+        Ok(Err(err)) => {
+            let kind = ErrorKind::of(&err);
+            if quiet {
+                let error = serde_json::json!({
+                    "code": kind.exit_code(),
+                    "kind": kind.name(),
+                    "message": err.to_string(),
+                });
+                eprintln!("{error}");
+            } else {
+                eprintln!(" -> Error: {err}");
+            }
+            process::exit(kind.exit_code());
+        }
         Err(_) => {
             disable_raw_mode().unwrap();
             if let Some(panic_info) = PANIC_INFO.get() {
                 eprintln!("{panic_info}");
             }
+            process::exit(ErrorKind::Other.exit_code());
         }
     }
 }
 
 fn run(cli: Args) -> Result<()> {
     // Prepare storage
+    #[cfg(feature = "encryption")]
+    let storage = if cli.encrypted && !matches!(cli.action, Actions::Encrypt) {
+        SqliteStorage::new_encrypted(&read_passphrase(false, !runs_from_hooks(&cli.action))?)?
+    } else {
+        SqliteStorage::new()?
+    };
+    #[cfg(not(feature = "encryption"))]
     let storage = SqliteStorage::new()?;
+    let storage = storage
+        .with_profile(cli.profile)
+        .with_usage_half_life(cli.usage_half_life)
+        .with_import_template_match(matches!(
+            cli.action,
+            Actions::Import {
+                match_templates: true,
+                ..
+            }
+        ));
+    // The synced sources are only refreshed for the actions reading the commands, failing to sync isn't fatal
+    if reads_commands(&cli.action) {
+        if let Some(vault) = &cli.vault {
+            if let Err(err) = storage.sync_vault(vault) {
+                eprintln!(" -> Warning: Error syncing the vault at {}: {err:#}", vault.display());
+            }
+        }
+        if let Some(library) = cli.library.clone().or_else(default_library_file) {
+            if let Err(err) = storage.sync_library(&library) {
+                eprintln!(
+                    " -> Warning: Error syncing the library at {}: {err:#}",
+                    library.display()
+                );
+            }
+        }
+    }
+    // Label values provided upfront replace the labels without any interface, so the output can be captured, as well
+    // as the candidates of the nushell completer
+    let non_interactive = matches!(&cli.action, Actions::Label { values, stdin, .. } if *stdin || !values.is_empty())
+        || matches!(&cli.action, Actions::NuComplete { .. });
 
     // Execution context
     let context = ExecutionContext {
         inline: cli.inline,
-        theme: theme::DARK,
+        theme: cli.theme.theme().with_border(cli.border),
+        empty_query: EmptyQuery::default(),
+    };
+    let options = ExecutionOptions {
+        title: cli.exec_title,
+        notify_after: cli.notify_after,
     };
 
     // Execute command
@@ -131,54 +580,528 @@ fn run(cli: Args) -> Result<()> {
             exec(
                 cli.inline,
                 cli.inline_extra_line,
+                cli.mouse,
                 EditCommandProcess::new(&storage, command, context)?,
             )
         }
-        Actions::Search { filter } => exec(
+        Actions::Edit { command } => match storage.find_command(remove_newlines(&command))? {
+            Some(found) if found.is_read_only() => Err(anyhow!(
+                "Command '{}' comes from the shared library, which is read-only",
+                found.cmd
+            )),
+            Some(command) => exec(
+                cli.inline,
+                cli.inline_extra_line,
+                cli.mouse,
+                EditCommandProcess::new(&storage, command, context)?,
+            ),
+            None => Err(UserFacingError::not_found(format!("Command '{command}' not found")).into()),
+        },
+        Actions::Search {
+            filter,
+            empty_query,
+            focus_period,
+            page_size,
+            at,
+        } => {
+            let mut process = SearchProcess::new(
+                &storage,
+                remove_newlines(filter.unwrap_or_default()),
+                page_size as usize,
+                ExecutionContext { empty_query, ..context },
+            )?
+            .with_warnings(check_health())
+            .with_focus_period(focus_period);
+            if let Some(at) = at {
+                let dir = fs::canonicalize(&at).with_context(|| format!("Couldn't find directory {}", at.display()))?;
+                process = process.with_project_dir(dir)?;
+            }
+            exec(cli.inline, cli.inline_extra_line, cli.mouse, process)
+        }
+        Actions::SuggestInline { buffer } => exec(
+            cli.inline,
+            cli.inline_extra_line,
+            cli.mouse,
+            SuggestInlineProcess::new(&storage, buffer),
+        ),
+        Actions::CycleAlias { buffer, index } => exec(
+            cli.inline,
+            cli.inline_extra_line,
+            cli.mouse,
+            CycleAliasProcess::new(&storage, buffer, index),
+        ),
+        Actions::NuComplete { spans } => exec(
             cli.inline,
             cli.inline_extra_line,
-            SearchProcess::new(&storage, remove_newlines(filter.unwrap_or_default()), context)?,
+            cli.mouse,
+            NuCompleterProcess::new(&storage, spans),
         ),
-        Actions::Label { command } => match remove_newlines(&command).as_labeled_command() {
+        Actions::Record { command } => {
+            // Sensitive commands, prefixed with a space or looking like they contain secrets, are never recorded as
+            // well as the dangerous ones (so they're not one keystroke away) and our own ones
+            let own = command.split_whitespace().next() == Some("intelli-shell");
+            let sensitive = command.starts_with(' ') || may_contain_secrets(&command) || is_dangerous(&command, "");
+            if !sensitive && !own {
+                if let Some(project) = current_project_dir() {
+                    storage.record_shell_command(&command, project)?;
+                }
+            }
+            Ok(ProcessOutput::empty())
+        }
+        Actions::Share {
+            command,
+            filter,
+            qr,
+            upload,
+            yes,
+        } => {
+            let mut commands = if filter {
+                storage.find_commands(remove_newlines(&command))?
+            } else {
+                storage.find_command(remove_newlines(&command))?.into_iter().collect()
+            };
+            if commands.is_empty() {
+                return Err(UserFacingError::not_found(format!("Command '{command}' not found")).into());
+            }
+            let text = if upload {
+                commands.retain(|c| !c.is_sensitive() && !c.cmd.as_labeled_command().is_some_and(|l| l.has_secrets()));
+                if commands.is_empty() {
+                    bail!("Commands kept out of the history or with secret labels can't be uploaded");
+                }
+                let question = format!("Upload {} commands to {}?", commands.len(), paste_endpoint());
+                if !yes && !confirm(&question)? {
+                    bail!("The upload wasn't confirmed");
+                }
+                let text = commands.iter().map(Command::to_text).join("\n");
+                format!("intelli-shell import {}", upload_paste(&format!("{text}\n"))?)
+            } else {
+                commands.iter().map(Command::to_text).join("\n")
+            };
+            if qr {
+                Ok(ProcessOutput::message(format!("{}\n{text}", qr_code(&text)?)))
+            } else {
+                print!("{}", osc52_copy(&text));
+                Ok(ProcessOutput::message(format!(" -> Copied to the clipboard:\n{text}")))
+            }
+        }
+        Actions::Learn { command: None, .. } => {
+            let learning = storage.get_learning_commands()?;
+            if learning.is_empty() {
+                Ok(ProcessOutput::message(" -> There are no commands being learnt"))
+            } else {
+                Ok(ProcessOutput::message(
+                    learning
+                        .into_iter()
+                        .map(|(command, learning)| format!(" -> {learning} {}", command.cmd))
+                        .join("\n"),
+                ))
+            }
+        }
+        Actions::Learn {
+            command: Some(command),
+            goal,
+            hide,
+            stop,
+        } => match storage.find_command(remove_newlines(&command))? {
+            Some(command) if stop => match storage.stop_learning_command(command.id)? {
+                true => Ok(ProcessOutput::message(format!(
+                    " -> Stopped learning '{}'",
+                    command.cmd
+                ))),
+                false => Ok(ProcessOutput::message(format!(
+                    " -> '{}' wasn't being learnt",
+                    command.cmd
+                ))),
+            },
+            Some(command) => {
+                storage.learn_command(&command, goal, hide)?;
+                Ok(ProcessOutput::message(format!(
+                    " -> Learning '{}', run it {goal} times to master it",
+                    command.cmd
+                )))
+            }
+            None => Err(UserFacingError::not_found(format!("Command '{command}' not found")).into()),
+        },
+        Actions::Run { command, vars, yes } => match storage.find_command(remove_newlines(&command))? {
+            Some(command) => {
+                let cmd = command.prefixed_cmd();
+                let cmd = match cmd.as_labeled_command() {
+                    Some(labeled_command) => labeled_command.fill(&vars.into_iter().collect())?,
+                    None => cmd,
+                };
+                let cmd = strip_cursor_marker(&cmd, false);
+                if !yes
+                    && is_dangerous(&cmd, &command.description)
+                    && !confirm(&format!("Run the dangerous command '{cmd}'?"))?
+                {
+                    // Fail so that scripts chaining the command don't go on
+                    return Err(anyhow!("The command wasn't confirmed"));
+                }
+                storage.increment_command_usage(command.id)?;
+                storage.flush_usage()?;
+                let (status, duration) = execute_shell_command_with(&cmd, options)?;
+                // The command already ran, so failing to record it must not hide its own exit status
+                if let Err(err) = storage.record_execution(command.id, status.code(), duration) {
+                    eprintln!(" -> Warning: Error recording the execution: {err:#}");
+                }
+                if !status.success() {
+                    // Behave like the command itself, so it can be used as an alias
+                    process::exit(status.code().unwrap_or(ErrorKind::Other.exit_code()));
+                }
+                Ok(ProcessOutput::empty())
+            }
+            None => Err(UserFacingError::not_found(format!("Command '{command}' not found")).into()),
+        },
+        #[cfg(feature = "encryption")]
+        Actions::Encrypt => {
+            storage.encrypt(&read_passphrase(true, true)?)?;
+            Ok(ProcessOutput::message(
+                " -> Database encrypted, set INTELLI_DB_ENCRYPTED=1 to open it from now on",
+            ))
+        }
+        Actions::Optimize => {
+            storage.optimize()?;
+            Ok(ProcessOutput::message(" -> Database optimized successfully"))
+        }
+        Actions::Init { wizard: false } => {
+            let shell = Shell::detect().ok_or_else(|| {
+                anyhow!("Couldn't detect the shell, set INTELLI_SHELL to bash, zsh, fish or powershell")
+            })?;
+            Ok(ProcessOutput::message(ShellSetup::new(shell, true)?.snippet()))
+        }
+        Actions::Init { wizard: true } => Ok(ProcessOutput::message(init_wizard(&storage)?)),
+        Actions::Completions { shell } => Ok(ProcessOutput::message(completions_script(shell))),
+        Actions::CompleteAlias { prefix } => Ok(ProcessOutput::message(
+            storage
+                .find_commands_by_alias_prefix(prefix)?
+                .into_iter()
+                .filter_map(|c| c.alias)
+                .join("\n"),
+        )),
+        Actions::Prune {
+            unused_for,
+            category,
+            matching,
+            duplicates,
+            dry_run,
+        } => {
+            let criteria = PruneCriteria {
+                unused_for,
+                category,
+                pattern: matching,
+                duplicates,
+            };
+            if dry_run {
+                let commands = storage.find_prunable_commands(&criteria)?;
+                Ok(ProcessOutput::message(commands.iter().fold(
+                    format!(" -> Prune would remove {} commands", commands.len()),
+                    |acc, c| format!("{acc}\n - {} ({})", c.cmd, c.category),
+                )))
+            } else {
+                let deleted = storage.prune_commands(&criteria)?;
+                Ok(ProcessOutput::message(format!(" -> Removed {deleted} commands")))
+            }
+        }
+        Actions::Doctor => {
+            let warnings = diagnose(&storage)?;
+            if warnings.is_empty() {
+                Ok(ProcessOutput::message(" -> No issues found"))
+            } else {
+                Ok(ProcessOutput::message(
+                    warnings
+                        .into_iter()
+                        .map(|w| format!(" -> {}: {}", w.summary, w.detail))
+                        .join("\n"),
+                ))
+            }
+        }
+        Actions::Repl {
+            empty_query,
+            focus_period,
+            page_size,
+        } => {
+            let context = ExecutionContext { empty_query, ..context };
+            let search = SearchOptions {
+                focus_period,
+                page_size: page_size as usize,
+            };
+            repl(
+                &storage,
+                context,
+                options,
+                search,
+                cli.inline,
+                cli.inline_extra_line,
+                cli.mouse,
+            )?;
+            Ok(ProcessOutput::empty())
+        }
+        Actions::Report { file } => {
+            write_report(&storage, &file)?;
+            Ok(ProcessOutput::message(format!(
+                " -> Report written to '{}', review it before attaching it to an issue",
+                file.display()
+            )))
+        }
+        Actions::Serve { port, token, scope } => {
+            let generated = token.is_none();
+            let token = token.unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+            serve(&storage, port, &token, &scope, |port| {
+                if !cli.quiet {
+                    println!(" -> Listening on http://127.0.0.1:{port}");
+                }
+                // The generated token is always printed, as the server couldn't be used otherwise
+                if generated {
+                    println!(" -> Token: {token}");
+                }
+            })?;
+            Ok(ProcessOutput::empty())
+        }
+        #[cfg(unix)]
+        Actions::Daemon { socket } => {
+            serve_daemon(&storage, &socket, || {
+                if !cli.quiet {
+                    println!(" -> Listening on {}", socket.display());
+                }
+            })?;
+            Ok(ProcessOutput::empty())
+        }
+        Actions::Label { command, values, stdin } if stdin || !values.is_empty() => {
+            let mut label_values = if stdin { read_label_values()? } else { HashMap::new() };
+            label_values.extend(values);
+            let command = remove_newlines(&command);
+            match command.as_labeled_command() {
+                Some(labeled_command) => {
+                    let sensitive = labeled_command.has_secrets();
+                    Ok(ProcessOutput::output(labeled_command.fill(&label_values)?).sensitive(sensitive))
+                }
+                None => Ok(ProcessOutput::output(command)),
+            }
+        }
+        Actions::Label { command, .. } => match remove_newlines(&command).as_labeled_command() {
             Some(labeled_command) => exec(
                 cli.inline,
                 cli.inline_extra_line,
-                LabelProcess::new(&storage, labeled_command, context)?,
+                cli.mouse,
+                // The command was typed by the user, so it's not confirmed even if dangerous once filled
+                LabelProcess::new(&storage, labeled_command, context)?.with_confirmed(true),
             ),
             None => Ok(ProcessOutput::new(" -> The command contains no labels!", command)),
         },
-        Actions::Export { file } => {
-            let file_path = file.as_deref().unwrap_or("user_commands.txt");
-            let exported = storage.export(USER_CATEGORY, file_path)?;
-            Ok(ProcessOutput::message(format!(
-                " -> Successfully exported {exported} commands to '{file_path}'"
-            )))
+        Actions::Export {
+            file,
+            plugin: Some(plugin),
+            ..
+        } => {
+            let (exported, output) = storage.export_plugin(USER_CATEGORY, &plugin, file.as_deref())?;
+            let message = format!(" -> Successfully exported {exported} commands through the '{plugin}' plugin");
+            match output.trim_end() {
+                "" => Ok(ProcessOutput::message(message)),
+                output => Ok(ProcessOutput::message(format!("{output}\n{message}"))),
+            }
+        }
+        Actions::Export {
+            file,
+            format,
+            include_values,
+            plugin: None,
+        } => {
+            let file_path = file.unwrap_or_else(|| format!("user_commands.{}", format.extension()));
+            let (exported, values) = storage.export(USER_CATEGORY, &file_path, format, include_values)?;
+            if include_values {
+                Ok(ProcessOutput::message(format!(
+                    " -> Successfully exported {exported} commands and {values} label values to '{file_path}'"
+                )))
+            } else {
+                Ok(ProcessOutput::message(format!(
+                    " -> Successfully exported {exported} commands to '{file_path}'"
+                )))
+            }
+        }
+        Actions::Import {
+            file,
+            plugin: Some(plugin),
+            dry_run,
+            ..
+        } => {
+            if dry_run {
+                let changes = storage.import_plugin_dry_run(USER_CATEGORY, &plugin, file.as_deref())?;
+                Ok(ProcessOutput::message(import_summary(&changes)))
+            } else {
+                let stats = storage.import_plugin(USER_CATEGORY, &plugin, file.as_deref())?;
+                Ok(ProcessOutput::message(format!(" -> Imported {stats}")))
+            }
+        }
+        Actions::Import {
+            command: Some(tool),
+            dry_run,
+            ..
+        } => {
+            if dry_run {
+                let changes = storage.import_help_dry_run(USER_CATEGORY, &tool)?;
+                Ok(ProcessOutput::message(import_summary(&changes)))
+            } else {
+                let stats = storage.import_help(USER_CATEGORY, &tool)?;
+                Ok(ProcessOutput::message(format!(" -> Imported {stats}")))
+            }
         }
-        Actions::Import { file } => {
-            let new = storage.import(USER_CATEGORY, file)?;
-            Ok(ProcessOutput::message(format!(" -> Imported {new} new commands")))
+        Actions::Import {
+            file,
+            ssh_config: true,
+            dry_run,
+            ..
+        } => {
+            let config_file = file
+                .map(PathBuf::from)
+                .or_else(default_ssh_config_file)
+                .ok_or_else(|| anyhow!("Couldn't find the ssh config file"))?;
+            if dry_run {
+                let changes = storage.import_ssh_dry_run(USER_CATEGORY, config_file)?;
+                Ok(ProcessOutput::message(import_summary(&changes)))
+            } else {
+                let stats = storage.import_ssh(USER_CATEGORY, config_file)?;
+                Ok(ProcessOutput::message(format!(" -> Imported {stats}")))
+            }
         }
+        Actions::Import {
+            file,
+            history,
+            dir,
+            dry_run,
+            ..
+        } => {
+            let history_file = match (&file, history) {
+                (Some(_), false) => None,
+                (file, _) => Some(
+                    file.as_ref()
+                        .map(PathBuf::from)
+                        .or_else(default_history_file)
+                        .ok_or_else(|| anyhow!("Couldn't find the shell history file"))?,
+                ),
+            };
+            if dry_run {
+                let changes = match (history_file, dir) {
+                    (Some(history_file), _) => storage.import_history_dry_run(USER_CATEGORY, history_file)?,
+                    (None, true) => storage.import_dir_dry_run(USER_CATEGORY, file.unwrap_or_default())?,
+                    (None, false) => {
+                        let file = file.unwrap_or_default();
+                        if is_url(&file) {
+                            storage.import_url_dry_run(USER_CATEGORY, file)?
+                        } else {
+                            storage.import_dry_run(USER_CATEGORY, file)?
+                        }
+                    }
+                };
+                Ok(ProcessOutput::message(import_summary(&changes)))
+            } else {
+                let (stats, values) = match (history_file, dir) {
+                    (Some(history_file), _) => (storage.import_history(USER_CATEGORY, history_file)?, 0),
+                    (None, true) => (storage.import_dir(USER_CATEGORY, file.unwrap_or_default())?, 0),
+                    (None, false) => {
+                        let file = file.unwrap_or_default();
+                        if is_url(&file) {
+                            storage.import_url(USER_CATEGORY, file)?
+                        } else {
+                            let stats = storage.import(USER_CATEGORY, file.clone())?;
+                            (stats, storage.import_values(file)?)
+                        }
+                    }
+                };
+                if values > 0 {
+                    Ok(ProcessOutput::message(format!(
+                        " -> Imported {stats} and {values} new label values"
+                    )))
+                } else {
+                    Ok(ProcessOutput::message(format!(" -> Imported {stats}")))
+                }
+            }
+        }
+        Actions::Theme {
+            action: ThemeActions::Check,
+        } => {
+            let warnings = cli.theme.check();
+            if warnings.is_empty() {
+                Ok(ProcessOutput::message(format!(
+                    " -> No issues found with the {} theme",
+                    cli.theme
+                )))
+            } else {
+                Ok(ProcessOutput::message(
+                    warnings.into_iter().map(|w| format!(" -> {w}")).join("\n"),
+                ))
+            }
+        }
+        Actions::Category { action } => match action {
+            CategoryActions::List { tree: false } => Ok(ProcessOutput::message(
+                storage
+                    .get_categories()?
+                    .into_iter()
+                    .map(|(category, count)| format!(" -> {category} ({count})"))
+                    .join("\n"),
+            )),
+            CategoryActions::List { tree: true } => Ok(ProcessOutput::message(
+                category_tree(&storage.get_categories()?)
+                    .into_iter()
+                    .map(|node| format!(" {}-> {} ({})", "  ".repeat(node.depth), node.name(), node.commands))
+                    .join("\n"),
+            )),
+            CategoryActions::Set { command, category } => match storage.find_command(remove_newlines(&command))? {
+                Some(found) if storage.set_command_category(found.id, &category)? => Ok(ProcessOutput::message(
+                    format!(" -> Moved '{}' into '{}'", found.cmd, category.trim()),
+                )),
+                _ => Err(UserFacingError::not_found(format!("Command '{command}' not found")).into()),
+            },
+            CategoryActions::Rename { from, to } => {
+                let updated = storage.rename_category(&from, &to)?;
+                Ok(ProcessOutput::message(format!(
+                    " -> Renamed '{from}' to '{to}' ({updated} commands)"
+                )))
+            }
+            CategoryActions::Merge { from, into } => {
+                let updated = storage.merge_categories(&from, &into)?;
+                Ok(ProcessOutput::message(format!(
+                    " -> Merged {updated} commands into '{into}'"
+                )))
+            }
+        },
         #[cfg(feature = "tldr")]
         Actions::Fetch { category } => exec(
             cli.inline,
             cli.inline_extra_line,
+            cli.mouse,
             intelli_shell::process::FetchProcess::new(category, &storage),
         ),
+        #[cfg(feature = "tldr")]
+        Actions::Tldr {
+            action: TldrActions::Prune { unused_for },
+        } => {
+            let deleted = storage.prune_unused_commands(unused_for)?;
+            Ok(ProcessOutput::message(format!(" -> Removed {deleted} unused commands")))
+        }
     }?;
 
     // Print any message received
-    if let Some(msg) = res.message {
+    if let Some(msg) = res.message.filter(|_| !cli.quiet) {
         println!("{msg}");
         if cli.inline_extra_line {
             println!();
         }
     }
 
-    // Write out the result
-    match res.output {
+    // Write out the result, prefixing sensitive commands with a space so the shell keeps them out of the history
+    match res
+        .output
+        .map(|output| strip_cursor_marker(&output, cli.cursor_marker))
+        .map(|output| if res.sensitive { format!(" {output}") } else { output })
+    {
         None => (),
         Some(output) => match cli.file_output {
+            None if non_interactive => println!("{output}"),
             None => eprintln!("{output}"),
+            // The buffer is replaced with the file content, so continuations of stored commands must match the shell
+            Some(path) if res.command_id.is_some() => {
+                fs::write(path, with_shell_continuations(&output, &current_shell()))?
+            }
             Some(path) => fs::write(path, output)?,
         },
     }
@@ -187,18 +1110,368 @@ fn run(cli: Args) -> Result<()> {
     Ok(())
 }
 
-fn exec<P>(inline: bool, inline_extra_line: bool, process: P) -> Result<ProcessOutput>
+/// Determines if the given action reads the stored commands, so the synced sources must be refreshed before
+fn reads_commands(action: &Actions) -> bool {
+    matches!(
+        action,
+        Actions::Edit { .. }
+            | Actions::Search { .. }
+            | Actions::Share { .. }
+            | Actions::Learn { .. }
+            | Actions::Run { .. }
+            | Actions::Repl { .. }
+            | Actions::Serve { .. }
+            | Actions::Export { .. }
+            | Actions::Prune { .. }
+            | Actions::Category { .. }
+    )
+}
+
+/// Determines if the given action is run by the shell integration hooks, so it must never prompt the user
+#[cfg(feature = "encryption")]
+fn runs_from_hooks(action: &Actions) -> bool {
+    matches!(
+        action,
+        Actions::CompleteAlias { .. }
+            | Actions::SuggestInline { .. }
+            | Actions::CycleAlias { .. }
+            | Actions::Record { .. }
+            | Actions::NuComplete { .. }
+    )
+}
+
+/// Asks the user to confirm something on the terminal, failing when it's not interactive
+fn confirm(question: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Err(anyhow!("Confirmation required, run it with --yes to skip it"));
+    }
+    ask(question, false)
+}
+
+/// Asks a yes or no question, returning the default answer when none is given
+fn ask(question: &str, default: bool) -> Result<bool> {
+    eprint!("{question} {} ", if default { "[Y/n]" } else { "[y/N]" });
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(match answer.trim().to_lowercase().as_str() {
+        "" => default,
+        answer => matches!(answer, "y" | "yes"),
+    })
+}
+
+/// Guides new users through the setup, returning the summary of the changes
+fn init_wizard(storage: &SqliteStorage) -> Result<String> {
+    if !io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "The wizard must be run on a terminal, use `init` to print the shell integration"
+        ));
+    }
+    let shell = Shell::detect()
+        .ok_or_else(|| anyhow!("Couldn't detect the shell, set INTELLI_SHELL to bash, zsh, fish or powershell"))?;
+    let mut summary = Vec::new();
+
+    let inline = ask(
+        "Render the interface inline, below the prompt, instead of full-screen?",
+        true,
+    )?;
+    let setup = ShellSetup::new(shell, inline)?;
+    let rc_file = shell.rc_file()?;
+    if !setup.script().exists() {
+        summary.push(format!(
+            " -> Shell integration script not found at {}, install it with the install script first",
+            setup.script().display()
+        ));
+    } else if ask(
+        &format!("Install the shell integration into {}?", rc_file.display()),
+        true,
+    )? {
+        if install_shell_integration(&rc_file, &setup)? {
+            summary.push(format!(
+                " -> Shell integration installed into {}, restart the terminal to load it",
+                rc_file.display()
+            ));
+        } else {
+            summary.push(format!(
+                " -> {} already references IntelliShell, it was left untouched",
+                rc_file.display()
+            ));
+        }
+    } else {
+        summary.push(format!(
+            " -> Add this to {} to enable the shell integration:\n{}",
+            rc_file.display(),
+            setup.snippet().trim()
+        ));
+    }
+
+    #[cfg(feature = "tldr")]
+    if ask("Fetch the tldr pages for this platform?", false)? {
+        let mut commands = intelli_shell::scrape_tldr_github(None)?;
+        let new = storage.insert_commands(&mut commands)?;
+        summary.push(format!(" -> Retrieved {new} new commands from tldr"));
+    }
+    #[cfg(not(feature = "tldr"))]
+    let _ = storage;
+
+    Ok(summary.join("\n"))
+}
+
+/// Shells the completion script can be generated for
+#[derive(Clone, Copy)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    Nu,
+    PowerShell,
+}
+
+impl FromStr for CompletionShell {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "bash" => Ok(CompletionShell::Bash),
+            "zsh" => Ok(CompletionShell::Zsh),
+            "fish" => Ok(CompletionShell::Fish),
+            "nu" | "nushell" => Ok(CompletionShell::Nu),
+            "powershell" | "pwsh" => Ok(CompletionShell::PowerShell),
+            _ => bail!("Expected one of: bash, zsh, fish, nu, powershell"),
+        }
+    }
+}
+
+/// Completes the aliases of the commands taking one, on bash
+const BASH_ALIAS_COMPLETION: &str = r#"
+_intelli_shell_aliases() {
+    if [[ $COMP_CWORD -eq 2 && " run edit share " == *" ${COMP_WORDS[1]} "* ]]; then
+        local IFS=$'\n'
+        COMPREPLY=($(intelli-shell complete-alias "${COMP_WORDS[COMP_CWORD]}" 2>/dev/null))
+    else
+        _intelli-shell "$@"
+    fi
+}
+complete -F _intelli_shell_aliases -o bashdefault -o default intelli-shell
+"#;
+
+/// Completes the aliases of the commands taking one, on zsh
+const ZSH_ALIAS_COMPLETION: &str = r#"
+_intelli_shell_aliases() {
+    if (( CURRENT == 3 )) && [[ " run edit share " == *" ${words[2]} "* ]]; then
+        local -a aliases
+        aliases=(${(f)"$(intelli-shell complete-alias "${words[CURRENT]}" 2>/dev/null)"})
+        compadd -a aliases
+    else
+        _intelli-shell "$@"
+    fi
+}
+compdef _intelli_shell_aliases intelli-shell
+"#;
+
+/// Completes the aliases of the commands taking one, on fish
+const FISH_ALIAS_COMPLETION: &str = r#"
+complete -c intelli-shell -n "__fish_seen_subcommand_from run edit share" -f -a "(intelli-shell complete-alias (commandline -ct) 2>/dev/null)"
+"#;
+
+/// Generates the completion script of this CLI for the given shell, along with the completion of the aliases if
+/// supported
+fn completions_script(shell: CompletionShell) -> String {
+    let mut command = Args::command();
+    let name = command.get_name().to_owned();
+    let mut script = Vec::new();
+    match shell {
+        CompletionShell::Bash => clap_complete::generate(clap_complete::Shell::Bash, &mut command, name, &mut script),
+        CompletionShell::Zsh => clap_complete::generate(clap_complete::Shell::Zsh, &mut command, name, &mut script),
+        CompletionShell::Fish => clap_complete::generate(clap_complete::Shell::Fish, &mut command, name, &mut script),
+        CompletionShell::PowerShell => {
+            clap_complete::generate(clap_complete::Shell::PowerShell, &mut command, name, &mut script)
+        }
+        CompletionShell::Nu => clap_complete::generate(clap_complete_nushell::Nushell, &mut command, name, &mut script),
+    }
+    let mut script = String::from_utf8_lossy(&script).into_owned();
+    script.push_str(match shell {
+        CompletionShell::Bash => BASH_ALIAS_COMPLETION,
+        CompletionShell::Zsh => ZSH_ALIAS_COMPLETION,
+        CompletionShell::Fish => FISH_ALIAS_COMPLETION,
+        CompletionShell::Nu | CompletionShell::PowerShell => "",
+    });
+    script
+}
+
+/// Reads the passphrase of the database from the `INTELLI_DB_PASSPHRASE` variable or prompts for it, without echoing
+/// the typed chars, asking to confirm it if required. It fails instead of prompting when not `interactive` or not run
+/// on a terminal.
+#[cfg(feature = "encryption")]
+fn read_passphrase(confirm: bool, interactive: bool) -> Result<String> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+
+    if let Some(passphrase) = std::env::var("INTELLI_DB_PASSPHRASE").ok().filter(|p| !p.is_empty()) {
+        return Ok(passphrase);
+    }
+    if !interactive || !io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "The database is encrypted and the passphrase can't be prompted, set INTELLI_DB_PASSPHRASE"
+        ));
+    }
+
+    let prompt = |message: &str| -> Result<String> {
+        print!("{message}");
+        io::stdout().flush()?;
+        enable_raw_mode()?;
+        let mut passphrase = String::new();
+        let res = loop {
+            match event::read() {
+                Ok(Event::Key(k)) if k.kind == KeyEventKind::Press => match k.code {
+                    KeyCode::Enter => break Ok(()),
+                    KeyCode::Esc => break Err(anyhow!("Passphrase not provided")),
+                    KeyCode::Char('c') if k.modifiers.contains(KeyModifiers::CONTROL) => {
+                        break Err(anyhow!("Passphrase not provided"))
+                    }
+                    KeyCode::Char(c) => passphrase.push(c),
+                    KeyCode::Backspace => {
+                        passphrase.pop();
+                    }
+                    _ => (),
+                },
+                Ok(_) => (),
+                Err(err) => break Err(err.into()),
+            }
+        };
+        disable_raw_mode()?;
+        println!();
+        res.map(|_| passphrase)
+    };
+
+    let passphrase = prompt("Database passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err(anyhow!("The passphrase can't be empty"));
+    }
+    if confirm && prompt("Confirm the passphrase: ")? != passphrase {
+        return Err(anyhow!("The passphrases don't match"));
+    }
+    Ok(passphrase)
+}
+
+/// Parses a `key=value` label value
+fn parse_label_value(s: &str) -> Result<(String, String)> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Invalid label value '{s}', expected 'key=value'"))?;
+    Ok((key.trim().to_owned(), value.to_owned()))
+}
+
+/// Reads the label values from a JSON object on stdin
+fn read_label_values() -> Result<HashMap<String, String>> {
+    let values: HashMap<String, serde_json::Value> =
+        serde_json::from_reader(io::stdin()).context("Error reading label values, expected a JSON object on stdin")?;
+    Ok(values
+        .into_iter()
+        .map(|(key, value)| match value {
+            serde_json::Value::String(value) => (key, value),
+            value => (key, value.to_string()),
+        })
+        .collect())
+}
+
+fn import_summary(changes: &[ImportChange]) -> String {
+    let count = |f: fn(&ImportChange) -> bool| changes.iter().filter(|c| f(c)).count();
+    let header = format!(
+        " -> Import would add {} new commands, update {}, keep {} on other categories, merge {} equivalent ones and \
+         leave {} unchanged",
+        count(|c| matches!(c, ImportChange::New(_))),
+        count(|c| matches!(c, ImportChange::Updated { .. })),
+        count(|c| matches!(c, ImportChange::Conflict { .. })),
+        count(|c| matches!(c, ImportChange::Merged { .. })),
+        count(|c| matches!(c, ImportChange::Unchanged(_))),
+    );
+    changes
+        .iter()
+        .filter(|c| !matches!(c, ImportChange::Unchanged(_)))
+        .map(ToString::to_string)
+        .fold(header, |acc, c| format!("{acc}\n{c}"))
+}
+
+/// Options of the search interface opened on every iteration of the repl
+#[derive(Clone, Copy)]
+struct SearchOptions {
+    /// Period of recent activity considered by the focus filter
+    focus_period: Duration,
+    /// Maximum number of results loaded at once
+    page_size: usize,
+}
+
+fn repl(
+    storage: &SqliteStorage,
+    context: ExecutionContext,
+    options: ExecutionOptions,
+    search: SearchOptions,
+    inline: bool,
+    inline_extra_line: bool,
+    mouse: bool,
+) -> Result<()> {
+    let stdin = io::stdin();
+    loop {
+        // Read the filter, exiting on EOF
+        print!("intelli-shell> ");
+        io::stdout().flush()?;
+        let mut filter = String::new();
+        if stdin.read_line(&mut filter)? == 0 {
+            println!();
+            return Ok(());
+        }
+        let filter = filter.trim();
+        if filter == "exit" || filter == "quit" {
+            return Ok(());
+        }
+
+        // Search for the command and execute it, persisting its usage once the UI is closed
+        let res = SearchProcess::new(storage, filter.to_owned(), search.page_size, context)
+            .map(|process| process.with_focus_period(search.focus_period))
+            .and_then(|process| exec(inline, inline_extra_line, mouse, process))
+            .and_then(|res| storage.flush_usage().map(|_| res))
+            .and_then(|res| {
+                if let Some(msg) = res.message {
+                    println!("{msg}");
+                }
+                match res
+                    .output
+                    .map(|cmd| strip_cursor_marker(&cmd, false))
+                    .filter(|cmd| !cmd.trim().is_empty())
+                {
+                    Some(cmd) => {
+                        println!("$ {cmd}");
+                        let (status, duration) = execute_shell_command_with(&cmd, options)?;
+                        if let Some(command_id) = res.command_id {
+                            if let Err(err) = storage.record_execution(command_id, status.code(), duration) {
+                                eprintln!(" -> Warning: Error recording the execution: {err:#}");
+                            }
+                        }
+                        Ok(Some(status))
+                    }
+                    None => Ok(None),
+                }
+            });
+        match res {
+            Ok(Some(status)) if !status.success() => println!(" -> Command failed: {status}"),
+            Ok(_) => (),
+            Err(err) => eprintln!(" -> Error: {err}"),
+        }
+    }
+}
+
+fn exec<P>(inline: bool, inline_extra_line: bool, mouse: bool, process: P) -> Result<ProcessOutput>
 where
     P: Process,
 {
     if inline {
-        exec_inline(process, inline_extra_line)
+        exec_inline(process, inline_extra_line, mouse)
     } else {
-        exec_alt_screen(process)
+        exec_alt_screen(process, mouse)
     }
 }
 
-fn exec_alt_screen<P>(mut process: P) -> Result<ProcessOutput>
+fn exec_alt_screen<P>(mut process: P, mouse: bool) -> Result<ProcessOutput>
 where
     P: Process,
 {
@@ -210,7 +1483,10 @@ where
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen)?;
+    if mouse {
+        execute!(stdout, EnableMouseCapture)?;
+    }
 
     // Prepare terminal
     let backend = CrosstermBackend::new(stdout);
@@ -221,14 +1497,17 @@ where
 
     // Restore terminal
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
+    if mouse {
+        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+    }
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     terminal.show_cursor()?;
 
     // Return
     res
 }
 
-fn exec_inline<P>(mut process: P, extra_line: bool) -> Result<ProcessOutput>
+fn exec_inline<P>(mut process: P, extra_line: bool, mouse: bool) -> Result<ProcessOutput>
 where
     P: Process,
 {
@@ -255,6 +1534,9 @@ where
     let (cursor_x, cursor_y) = cursor::position()?;
 
     enable_raw_mode()?;
+    if mouse {
+        execute!(stdout, EnableMouseCapture)?;
+    }
 
     // Prepare terminal
     let backend = CrosstermBackend::new(stdout);
@@ -277,6 +1559,9 @@ where
 
     // Restore terminal
     disable_raw_mode()?;
+    if mouse {
+        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+    }
     terminal
         .backend_mut()
         .queue(cursor::MoveTo(
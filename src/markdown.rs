@@ -0,0 +1,176 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use itertools::Itertools;
+
+use crate::common::{flatten_str, remove_newlines};
+
+/// Languages of the fenced code blocks containing shell commands
+const SHELL_LANGUAGES: [&str; 5] = ["sh", "bash", "zsh", "shell", "console"];
+
+/// Reads every markdown file within the given directory recursively, returning the commands of their shell code
+/// blocks along with a description built from the nearest heading and tags derived from the file path
+pub fn read_markdown_dir(dir: impl AsRef<Path>) -> Result<Vec<(String, String)>> {
+    let dir = dir.as_ref();
+    let mut commands = Vec::new();
+    for file in markdown_files(dir)? {
+        let content = fs::read_to_string(&file).with_context(|| format!("Error reading {}", file.display()))?;
+        let tags = file
+            .strip_prefix(dir)
+            .unwrap_or(&file)
+            .with_extension("")
+            .iter()
+            .map(|part| slugify(&part.to_string_lossy()))
+            .filter(|tag| !tag.is_empty())
+            .collect_vec();
+        commands.append(&mut parse_markdown(&content, &tags));
+    }
+    Ok(commands.into_iter().unique_by(|(cmd, _)| cmd.clone()).collect())
+}
+
+/// Computes a fingerprint of the markdown files within the given directory recursively, which changes whenever any
+/// of them is added, removed or modified
+pub fn markdown_dir_fingerprint(dir: impl AsRef<Path>) -> Result<String> {
+    let dir = dir.as_ref();
+    let mut hasher = DefaultHasher::new();
+    dir.hash(&mut hasher);
+    for file in markdown_files(dir)? {
+        let metadata = fs::metadata(&file).with_context(|| format!("Error reading {}", file.display()))?;
+        file.hash(&mut hasher);
+        metadata.len().hash(&mut hasher);
+        metadata.modified().ok().hash(&mut hasher);
+    }
+    Ok(format!("{:x}", hasher.finish()))
+}
+
+/// Lists the markdown files within the given directory recursively, sorted by path and skipping hidden entries
+fn markdown_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let entries = fs::read_dir(dir).with_context(|| format!("Error reading directory {}", dir.display()))?;
+    for entry in entries {
+        let path = entry.context("Error reading directory entry")?.path();
+        if path
+            .file_name()
+            .map(|n| n.to_string_lossy().starts_with('.'))
+            .unwrap_or(true)
+        {
+            continue;
+        }
+        if path.is_dir() {
+            files.append(&mut markdown_files(&path)?);
+        } else if path.extension().map(|e| e == "md").unwrap_or(false) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Parses the shell code blocks of a markdown document, returning every command found along with its description:
+/// the nearest heading followed by the given tags
+pub fn parse_markdown(content: &str, tags: &[String]) -> Vec<(String, String)> {
+    let tags = tags.iter().map(|t| format!("#{t}")).join(" ");
+    let mut commands = Vec::new();
+    let mut heading = "";
+    // Fence and language of the code block being read, if any
+    let mut block: Option<(&str, String)> = None;
+    let mut pending: Option<String> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        match &block {
+            None => {
+                if let Some(fence) = ["```", "~~~"].into_iter().find(|f| trimmed.starts_with(f)) {
+                    let language = trimmed.trim_start_matches(fence).split_whitespace().next();
+                    block = Some((fence, language.unwrap_or_default().to_lowercase()));
+                } else if trimmed.starts_with('#') {
+                    heading = trimmed.trim_start_matches('#').trim();
+                }
+            }
+            Some((fence, _)) if trimmed.starts_with(fence) => {
+                block = None;
+                pending = None;
+            }
+            Some((_, language)) if SHELL_LANGUAGES.contains(&language.as_str()) => {
+                // Multiline commands have a trailing backslash on every line but the last one
+                let line = match pending.take() {
+                    Some(prev) => format!("{prev}\n{line}"),
+                    None => {
+                        // Console blocks mix commands, prefixed with a prompt, and their output
+                        match trimmed.strip_prefix("$ ") {
+                            Some(cmd) => cmd.to_owned(),
+                            None if language == "console" => continue,
+                            None => trimmed.to_owned(),
+                        }
+                    }
+                };
+                if line.ends_with('\\') {
+                    pending = Some(line);
+                    continue;
+                }
+                let cmd = remove_newlines(line).trim().to_owned();
+                if !cmd.is_empty() && !cmd.starts_with('#') {
+                    let description = format!("{heading} {tags}").trim().to_owned();
+                    commands.push((cmd, description));
+                }
+            }
+            Some(_) => (),
+        }
+    }
+
+    commands
+}
+
+/// Converts some text into a tag, keeping only alphanumeric chars separated by dashes
+fn slugify(text: &str) -> String {
+    flatten_str(text)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_markdown() {
+        let content = r#"# Docker
+
+Some text with `inline` code.
+
+## Clean everything
+```bash
+# comment
+docker system prune \
+  --all
+```
+
+```console
+$ docker ps
+CONTAINER ID   IMAGE
+```
+
+```rust
+fn main() {}
+```
+"#;
+        assert_eq!(
+            parse_markdown(content, &["notes".to_owned(), "docker".to_owned()]),
+            vec![
+                (
+                    "docker system prune --all".to_owned(),
+                    "Clean everything #notes #docker".to_owned()
+                ),
+                ("docker ps".to_owned(), "Clean everything #notes #docker".to_owned()),
+            ]
+        );
+
+        assert_eq!(slugify("My Notes/Año 2023"), "my-notes-ano-2023");
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+
+/// Separator of the nested categories, like `cloud/aws/ec2`
+pub const CATEGORY_SEPARATOR: char = '/';
+
+/// Node of the category tree
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub struct CategoryNode {
+    /// Full path of the category
+    pub path: String,
+    /// Depth of the category on the tree, starting at zero
+    pub depth: usize,
+    /// Number of commands within the category, including the nested ones
+    pub commands: u64,
+}
+
+impl CategoryNode {
+    /// Retrieves the last segment of the category path
+    pub fn name(&self) -> &str {
+        self.path.rsplit(CATEGORY_SEPARATOR).next().unwrap_or(&self.path)
+    }
+}
+
+/// Determines if the given category is the parent one or any of its nested categories
+pub fn is_within_category(category: &str, parent: &str) -> bool {
+    category
+        .strip_prefix(parent)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(CATEGORY_SEPARATOR))
+}
+
+/// Builds the category tree from the given categories along with their number of commands, including the intermediate
+/// categories even if they don't contain any command directly.
+///
+/// The nodes are returned in depth-first order, so they can be rendered as an indented list.
+pub fn category_tree(categories: &[(String, u64)]) -> Vec<CategoryNode> {
+    let mut counts: BTreeMap<Vec<&str>, u64> = BTreeMap::new();
+    for (category, count) in categories {
+        let segments = category.split(CATEGORY_SEPARATOR).collect::<Vec<_>>();
+        for len in 1..=segments.len() {
+            *counts.entry(segments[..len].to_vec()).or_default() += count;
+        }
+    }
+    counts
+        .into_iter()
+        .map(|(segments, commands)| CategoryNode {
+            path: segments.join(&CATEGORY_SEPARATOR.to_string()),
+            depth: segments.len() - 1,
+            commands,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_category_tree() {
+        let categories = vec![
+            ("cloud/aws/ec2".to_owned(), 2),
+            ("cloud/aws/s3".to_owned(), 1),
+            ("cloud/gcp".to_owned(), 3),
+            ("user".to_owned(), 4),
+        ];
+        let tree = category_tree(&categories)
+            .into_iter()
+            .map(|n| (n.path, n.depth, n.commands))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tree,
+            vec![
+                ("cloud".to_owned(), 0, 6),
+                ("cloud/aws".to_owned(), 1, 3),
+                ("cloud/aws/ec2".to_owned(), 2, 2),
+                ("cloud/aws/s3".to_owned(), 2, 1),
+                ("cloud/gcp".to_owned(), 1, 3),
+                ("user".to_owned(), 0, 4),
+            ]
+        );
+
+        assert!(is_within_category("cloud/aws", "cloud"));
+        assert!(is_within_category("cloud", "cloud"));
+        assert!(!is_within_category("cloudy", "cloud"));
+    }
+}
@@ -0,0 +1,57 @@
+use std::{fmt::Display, time::Duration};
+
+/// Outcomes of the latest executions of a command
+#[derive(Clone, Copy)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub struct ExecutionStats {
+    /// Number of executions considered
+    pub runs: u64,
+    /// Number of them that didn't exit successfully
+    pub failures: u64,
+    /// Average duration of the executions
+    pub avg_duration: Duration,
+}
+
+impl ExecutionStats {
+    /// Determines if any of the executions failed
+    pub fn is_failing(&self) -> bool {
+        self.failures > 0
+    }
+}
+
+impl Display for ExecutionStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let last = match self.runs {
+            1 => "the last run".to_owned(),
+            runs => format!("last {runs} runs"),
+        };
+        if self.is_failing() {
+            write!(f, "failed {} of {last}", self.failures)?;
+        } else {
+            write!(f, "succeeded {last}")?;
+        }
+        write!(f, " (avg {:.1}s)", self.avg_duration.as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_execution_stats_display() {
+        let stats = ExecutionStats {
+            runs: 5,
+            failures: 3,
+            avg_duration: Duration::from_millis(1300),
+        };
+        assert_eq!(stats.to_string(), "failed 3 of last 5 runs (avg 1.3s)");
+
+        let stats = ExecutionStats {
+            runs: 1,
+            failures: 0,
+            avg_duration: Duration::from_millis(40),
+        };
+        assert_eq!(stats.to_string(), "succeeded the last run (avg 0.0s)");
+    }
+}
@@ -0,0 +1,168 @@
+use std::{fmt::Write, str::FromStr};
+
+use anyhow::{bail, Result};
+use serde_json::json;
+
+use super::Command;
+
+/// Format of the exported commands
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum ExportFormat {
+    /// Plain text format, the only one that can be imported back
+    #[default]
+    Text,
+    /// JSON array of commands
+    Json,
+    /// YAML sequence of commands
+    Yaml,
+    /// Markdown table, to be rendered on docs or wikis
+    Markdown,
+}
+
+impl ExportFormat {
+    /// Retrieves the extension of the files on this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Json => "json",
+            ExportFormat::Yaml => "yaml",
+            ExportFormat::Markdown => "md",
+        }
+    }
+
+    /// Formats the given commands
+    pub fn format(self, commands: &[Command]) -> String {
+        match self {
+            ExportFormat::Text => commands.iter().fold(String::new(), |mut out, command| {
+                let _ = writeln!(out, "{}", command.to_text());
+                out
+            }),
+            ExportFormat::Json => {
+                let commands = commands.iter().map(command_json).collect::<Vec<_>>();
+                let mut out = serde_json::to_string_pretty(&commands).expect("json values are always serializable");
+                out.push('\n');
+                out
+            }
+            ExportFormat::Yaml => {
+                // Every JSON string is also a valid YAML double-quoted scalar
+                let quote = |text: &str| serde_json::to_string(text).expect("strings are always serializable");
+                commands.iter().fold(String::new(), |mut out, command| {
+                    let alias = command.alias.as_deref().map(quote).unwrap_or_else(|| "null".to_owned());
+                    let _ = writeln!(out, "- alias: {alias}");
+                    let _ = writeln!(out, "  command: {}", quote(&command.cmd));
+                    let _ = writeln!(out, "  description: {}", quote(&command.description));
+                    out
+                })
+            }
+            ExportFormat::Markdown => {
+                // Table rows can't contain line breaks, so they're rendered as html ones
+                let cell = |text: &str| text.replace('|', "\\|").lines().collect::<Vec<_>>().join("<br>");
+                let code = |line: &str| match line.trim() {
+                    "" => String::new(),
+                    line if line.contains('`') => format!("`` {} ``", cell(line)),
+                    line => format!("`{}`", cell(line)),
+                };
+                commands.iter().fold(
+                    String::from("| Alias | Command | Description |\n| --- | --- | --- |\n"),
+                    |mut out, command| {
+                        // Each line of the command gets its own code span, as html breaks aren't rendered within them
+                        let cmd = command.cmd.lines().map(code).collect::<Vec<_>>().join("<br>");
+                        let alias = command.alias.as_deref().map(cell).unwrap_or_default();
+                        let _ = writeln!(out, "| {alias} | {cmd} | {} |", cell(&command.description));
+                        out
+                    },
+                )
+            }
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" | "txt" => Ok(ExportFormat::Text),
+            "json" => Ok(ExportFormat::Json),
+            "yaml" | "yml" => Ok(ExportFormat::Yaml),
+            "md" | "markdown" => Ok(ExportFormat::Markdown),
+            _ => bail!("Expected one of: text, json, yaml, md"),
+        }
+    }
+}
+
+/// Builds the JSON value of an exported command
+fn command_json(command: &Command) -> serde_json::Value {
+    json!({
+        "alias": command.alias,
+        "command": command.cmd,
+        "description": command.description,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_format() {
+        let commands = vec![
+            Command {
+                alias: Some("ll".to_owned()),
+                ..Command::new("user", "ls -la | less", "List \"all\" files")
+            },
+            Command::new("user", "echo `date`", "Print the date #time"),
+        ];
+        let multiline = vec![Command::new(
+            "user",
+            "docker run \\\n  -it `image`\r\n\nexit",
+            "Run a container\nand exit",
+        )];
+
+        assert_eq!(
+            ExportFormat::Text.format(&commands),
+            "# alias: ll\nls -la | less ## List \"all\" files\necho `date` ## Print the date #time\n"
+        );
+        let nested = Command {
+            alias: Some("gs".to_owned()),
+            ..Command::new("user/git", "git status", "Show the status")
+        };
+        assert_eq!(
+            ExportFormat::Text.format(&[nested]),
+            "# category: user/git\n# alias: gs\ngit status ## Show the status\n"
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&ExportFormat::Json.format(&commands)).unwrap(),
+            json!([
+                { "alias": "ll", "command": "ls -la | less", "description": "List \"all\" files" },
+                { "alias": null, "command": "echo `date`", "description": "Print the date #time" },
+            ])
+        );
+        assert_eq!(
+            ExportFormat::Yaml.format(&commands),
+            r#"- alias: "ll"
+  command: "ls -la | less"
+  description: "List \"all\" files"
+- alias: null
+  command: "echo `date`"
+  description: "Print the date #time"
+"#
+        );
+        assert_eq!(
+            ExportFormat::Markdown.format(&commands),
+            r#"| Alias | Command | Description |
+| --- | --- | --- |
+| ll | `ls -la \| less` | List "all" files |
+|  | `` echo `date` `` | Print the date #time |
+"#
+        );
+        assert_eq!(
+            ExportFormat::Markdown.format(&multiline),
+            r#"| Alias | Command | Description |
+| --- | --- | --- |
+|  | `docker run \`<br>`` -it `image` ``<br><br>`exit` | Run a container<br>and exit |
+"#
+        );
+    }
+}
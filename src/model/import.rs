@@ -0,0 +1,73 @@
+use std::fmt::Display;
+
+use super::Command;
+
+/// Change that importing a command would produce on the storage
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum ImportChange {
+    /// The command is not stored yet
+    New(Command),
+    /// The command is already stored and its description would be updated
+    Updated { current: Command, description: String },
+    /// The command is already stored on a different category, where it would be kept, along with the description it
+    /// would be updated to, if any
+    Conflict {
+        current: Command,
+        category: String,
+        description: Option<String>,
+    },
+    /// The command is already stored as imported
+    Unchanged(Command),
+    /// The command is equivalent to a stored or previously imported one, so it would be merged into it
+    Merged { command: Command, equivalent: String },
+}
+
+/// Outcome of an import
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub struct ImportStats {
+    /// Number of newly inserted commands
+    pub new: u64,
+    /// Number of commands merged into an equivalent one
+    pub merged: u64,
+}
+
+impl Display for ImportChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportChange::New(command) => write!(f, " + {} ## {}", command.cmd, command.description),
+            ImportChange::Updated { current, description } => {
+                write!(f, " ~ {} ## {} => {}", current.cmd, current.description, description)
+            }
+            ImportChange::Conflict {
+                current,
+                category,
+                description,
+            } => {
+                write!(
+                    f,
+                    " ! {} (stored on '{}' instead of '{}')",
+                    current.cmd, current.category, category
+                )?;
+                match description {
+                    Some(description) => write!(f, " ## {} => {}", current.description, description),
+                    None => Ok(()),
+                }
+            }
+            ImportChange::Unchanged(command) => write!(f, " = {}", command.cmd),
+            ImportChange::Merged { command, equivalent } => {
+                write!(f, " * {} (equivalent to '{}')", command.cmd, equivalent)
+            }
+        }
+    }
+}
+
+impl Display for ImportStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} new commands", self.new)?;
+        if self.merged > 0 {
+            write!(f, ", merging {} equivalent ones", self.merged)?;
+        }
+        Ok(())
+    }
+}
@@ -1,13 +1,34 @@
-use std::fmt::{Display, Formatter};
+use std::{
+    collections::HashMap,
+    env,
+    fmt::{Display, Formatter},
+};
 
+use anyhow::{bail, Result};
+use itertools::Itertools;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
 use super::Command;
-use crate::common::{flatten_str, SplitCaptures, SplitItem};
+use crate::common::{execute_shell_filter, flatten_str, format_current_time, SplitCaptures, SplitItem};
+
+/// Header of the label values on the text format, followed by the root command, label, suggestion and usage
+pub const VALUE_HEADER: &str = "# value: ";
+
+/// Marker of the position the shell integration places the cursor at, instead of at the end, when inserting a command
+pub const CURSOR_MARKER: &str = "{{%cursor%}}";
+
+/// Removes the cursor marker from the given command, or just its repetitions when the first one is kept
+pub fn strip_cursor_marker(cmd: &str, keep_first: bool) -> String {
+    match cmd.split_once(CURSOR_MARKER) {
+        Some((before, after)) if keep_first => format!("{before}{CURSOR_MARKER}{}", after.replace(CURSOR_MARKER, "")),
+        _ => cmd.replace(CURSOR_MARKER, ""),
+    }
+}
 
 /// Type to represent label suggestions.
 #[cfg_attr(debug_assertions, derive(Debug))]
+#[derive(Clone)]
 pub struct LabelSuggestion {
     pub flat_root_cmd: String,
     pub flat_label: String,
@@ -19,6 +40,27 @@ impl LabelSuggestion {
     pub fn increment_usage(&mut self) {
         self.usage += 1;
     }
+
+    /// Formats this suggestion on the text format used to export and import, as a header ignored by older versions
+    pub fn to_text(&self) -> String {
+        format!(
+            "{VALUE_HEADER}{} ## {} ## {} ## {}",
+            self.flat_root_cmd, self.flat_label, self.suggestion, self.usage
+        )
+    }
+
+    /// Parses a suggestion from a line of the text format, if it's a value header
+    pub fn from_text(line: &str) -> Option<Self> {
+        let (rest, usage) = line.strip_prefix(VALUE_HEADER)?.rsplit_once(" ## ")?;
+        let mut parts = rest.splitn(3, " ## ");
+        let (flat_root_cmd, flat_label, suggestion) = (parts.next()?, parts.next()?, parts.next()?);
+        Some(Self {
+            flat_root_cmd: flatten_str(flat_root_cmd),
+            flat_label: flatten_str(flat_label),
+            suggestion: suggestion.to_owned(),
+            usage: usage.trim().parse().ok()?,
+        })
+    }
 }
 
 /// A [Command] containing labels
@@ -30,25 +72,141 @@ pub struct LabeledCommand {
 }
 
 impl LabeledCommand {
+    /// Retrieves the offset and name of the next label to be filled, without its function or metadata if any
     pub fn next_label(&self) -> Option<(usize, &str)> {
+        self.next_label_spec().map(|(ix, spec)| (ix, spec.name))
+    }
+
+    /// Retrieves the offset and details of the next label to be filled
+    pub fn next_label_spec(&self) -> Option<(usize, LabelSpec<'_>)> {
         let mut ix = 0;
         for part in self.parts.iter() {
             match part {
                 CommandPart::Text(t) => ix += t.len(),
                 CommandPart::LabelValue(v) => ix += v.len(),
-                CommandPart::Label(l) => return Some((ix, l)),
+                CommandPart::Label(l) => return Some((ix, parse_label(l))),
             }
         }
         None
     }
 
-    pub fn set_next_label(&mut self, value: impl Into<String>) {
+    /// Retrieves the distinct names of the labels pending to be filled
+    pub fn pending_labels(&self) -> Vec<&str> {
+        self.parts
+            .iter()
+            .filter_map(|p| match p {
+                CommandPart::Label(l) => Some(parse_label(l).name),
+                _ => None,
+            })
+            .unique()
+            .collect()
+    }
+
+    /// Fills the next label with the given value, along with every other label with the same name, transforming it
+    /// with the function of each label if any
+    pub fn set_next_label(&mut self, value: impl Into<String>) -> Result<()> {
+        let value = value.into();
+        let Some(name) = self.next_label().map(|(_, name)| name.to_owned()) else {
+            return Ok(());
+        };
+        for part in self.parts.iter_mut() {
+            if let CommandPart::Label(label) = part {
+                let spec = parse_label(label);
+                if spec.name == name {
+                    *part = CommandPart::LabelValue(apply_label_function(spec.function, &value)?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Expands the dynamic labels, which are filled without prompting. Their names are wrapped between `%`, so they
+    /// can't clash with regular labels:
+    /// - `{{%today%}}`: current date, like `2024-02-29`
+    /// - `{{%now%}}`: current date and time, like `2024-02-29T17:05:00`
+    /// - `{{%now:<format>%}}`: current date and time with a `strftime` format, like `{{%now:%Y%m%d-%H%M%}}`
+    pub fn expand_dynamic_labels(&mut self) -> Result<()> {
+        for part in self.parts.iter_mut() {
+            if let CommandPart::Label(label) = part {
+                let name = parse_label(label).name;
+                let Some(name) = name.strip_prefix('%').and_then(|n| n.strip_suffix('%')) else {
+                    continue;
+                };
+                let format = match name {
+                    "today" => Some("%Y-%m-%d"),
+                    "now" => Some("%Y-%m-%dT%H:%M:%S"),
+                    name => name.strip_prefix("now:").filter(|f| !f.is_empty()),
+                };
+                if let Some(format) = format {
+                    *part = CommandPart::LabelValue(format_current_time(format)?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Determines if any of the labels pending to be filled is a secret one, wrapped between `*`
+    pub fn has_secrets(&self) -> bool {
+        self.parts.iter().any(|p| match p {
+            CommandPart::Label(l) => {
+                let name = parse_label(l).name;
+                name.len() > 1 && name.starts_with('*') && name.ends_with('*')
+            }
+            _ => false,
+        })
+    }
+
+    /// Fills every label with the given values, keyed by label name (secret labels can be referenced without the
+    /// wrapping `*`) or with their default value, failing with the list of missing labels if any of them has no value
+    pub fn fill(mut self, values: &HashMap<String, String>) -> Result<String> {
+        self.expand_dynamic_labels()?;
+        // The default value applies to every label with the same name
+        let defaults = self
+            .parts
+            .iter()
+            .filter_map(|p| match p {
+                CommandPart::Label(l) => Some(parse_label(l)),
+                _ => None,
+            })
+            .filter_map(|spec| Some((spec.name.to_owned(), spec.default?.to_owned())))
+            .collect::<HashMap<_, _>>();
+        let mut missing = Vec::new();
         for part in self.parts.iter_mut() {
-            if let CommandPart::Label(_) = part {
-                *part = CommandPart::LabelValue(value.into());
-                break;
+            if let CommandPart::Label(label) = part {
+                let spec = parse_label(label);
+                let value = values
+                    .get(label.as_str())
+                    .or_else(|| values.get(spec.name))
+                    .or_else(|| values.get(spec.name.trim_matches('*')))
+                    .or_else(|| defaults.get(spec.name));
+                match value {
+                    Some(value) => *part = CommandPart::LabelValue(apply_label_function(spec.function, value)?),
+                    None => missing.push(spec.name.trim_matches('*').to_owned()),
+                }
             }
         }
+        if !missing.is_empty() {
+            bail!("Missing values for labels: {}", missing.into_iter().unique().join(", "));
+        }
+        Ok(self.to_string())
+    }
+
+    /// Determines if the given command could be produced by filling the labels of this one, each label matching at
+    /// least one char and any amount of whitespace matching any other
+    pub fn matches(&self, cmd: &str) -> bool {
+        let pattern = self
+            .parts
+            .iter()
+            .map(|part| match part {
+                CommandPart::Label(_) => "(.+?)".to_owned(),
+                CommandPart::Text(text) | CommandPart::LabelValue(text) => {
+                    WHITESPACE_REGEX.replace_all(&regex::escape(text), r"\s+").into_owned()
+                }
+            })
+            .join("");
+        Regex::new(&format!(r"^\s*{pattern}\s*$"))
+            .map(|regex| regex.is_match(cmd))
+            .unwrap_or(false)
     }
 
     pub fn new_suggestion_for(&self, label: impl AsRef<str>, suggestion: impl Into<String>) -> LabelSuggestion {
@@ -88,6 +246,82 @@ impl Display for CommandPart {
     }
 }
 
+/// Details of a label, declared as `{{name:function:=default # description}}` where everything but the name is optional
+#[cfg_attr(debug_assertions, derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct LabelSpec<'a> {
+    /// Name of the label
+    pub name: &'a str,
+    /// User-defined function its value must be transformed with
+    pub function: Option<&'a str>,
+    /// Value to pre-fill the label with
+    pub default: Option<&'a str>,
+    /// Description of what the label stands for
+    pub description: Option<&'a str>,
+}
+
+/// Parses a label into its details, like `{{port:=8080 # Port the server listens on}}`
+pub fn parse_label(label: &str) -> LabelSpec<'_> {
+    let (label, description) = match label.split_once(" #") {
+        Some((label, description)) => (label.trim_end(), Some(description.trim()).filter(|d| !d.is_empty())),
+        None => (label, None),
+    };
+    let (label, default) = match label.split_once(":=") {
+        Some((label, default)) if !label.is_empty() => (label, Some(default)),
+        _ => (label, None),
+    };
+    let (name, function) = split_label_function(label);
+    LabelSpec {
+        name,
+        function,
+        default,
+        description,
+    }
+}
+
+/// Environment variable with the user-defined label functions, one `name=command` per line
+const LABEL_FUNCTIONS_ENV: &str = "INTELLI_LABEL_FUNCTIONS";
+
+/// User-defined label functions, by name
+static LABEL_FUNCTIONS: Lazy<HashMap<String, String>> =
+    Lazy::new(|| parse_label_functions(&env::var(LABEL_FUNCTIONS_ENV).unwrap_or_default()));
+
+/// Parses the user-defined label functions, declared as `name=command` per line
+fn parse_label_functions(functions: &str) -> HashMap<String, String> {
+    functions
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(name, command)| (name.trim().to_owned(), command.trim().to_owned()))
+        .filter(|(name, command)| !name.is_empty() && !command.is_empty())
+        .collect()
+}
+
+/// Splits a label into its name and the function its value must be transformed with, if any, like `{{file:b64}}`
+pub fn split_label_function(label: &str) -> (&str, Option<&str>) {
+    split_label_function_with(label, &LABEL_FUNCTIONS)
+}
+
+fn split_label_function_with<'a>(label: &'a str, functions: &HashMap<String, String>) -> (&'a str, Option<&'a str>) {
+    // Labels could contain colons (like urls), so only known functions are split
+    match label.rsplit_once(':') {
+        Some((name, function)) if !name.is_empty() && functions.contains_key(function.trim()) => {
+            (name, Some(function.trim()))
+        }
+        _ => (label, None),
+    }
+}
+
+/// Transforms the value of a label with the given user-defined function, if any
+fn apply_label_function(function: Option<&str>, value: &str) -> Result<String> {
+    match function.and_then(|f| LABEL_FUNCTIONS.get(f)) {
+        Some(command) => execute_shell_filter(command, value),
+        None => Ok(value.to_owned()),
+    }
+}
+
+/// Regex to match runs of whitespace
+static WHITESPACE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s+").unwrap());
+
 /// Regex to parse commands with labels
 static COMMAND_LABEL_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\{\{([^}]+)}}"#).unwrap());
 
@@ -104,11 +338,15 @@ impl AsLabeledCommand for str {
         let parts = splitter
             .map(|e| match e {
                 SplitItem::Unmatched(t) => CommandPart::Text(t.to_owned()),
+                // The cursor marker isn't a label to be filled
+                SplitItem::Captured(l) if l.get(0).unwrap().as_str() == CURSOR_MARKER => {
+                    CommandPart::Text(CURSOR_MARKER.to_owned())
+                }
                 SplitItem::Captured(l) => CommandPart::Label(l.get(1).unwrap().as_str().to_owned()),
             })
             .collect::<Vec<_>>();
 
-        if parts.len() <= 1 {
+        if !parts.iter().any(|p| matches!(p, CommandPart::Label(_))) {
             None
         } else {
             Some(LabeledCommand {
@@ -123,3 +361,142 @@ impl AsLabeledCommand for Command {
         self.cmd.as_labeled_command()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill() {
+        let cmd = "docker run {{image}} --name {{name}} -e TOKEN={{*token*}} {{image}}"
+            .as_labeled_command()
+            .unwrap();
+        assert!(cmd.has_secrets());
+        assert!(!"ls {{path}}".as_labeled_command().unwrap().has_secrets());
+        let mut values = HashMap::from([
+            ("image".to_owned(), "nginx".to_owned()),
+            ("token".to_owned(), "s3cr3t".to_owned()),
+        ]);
+        assert_eq!(
+            cmd.clone().fill(&values).unwrap_err().to_string(),
+            "Missing values for labels: name"
+        );
+        values.insert("name".to_owned(), "web".to_owned());
+        assert_eq!(
+            cmd.fill(&values).unwrap(),
+            "docker run nginx --name web -e TOKEN=s3cr3t nginx"
+        );
+
+        let cmd = "nc -l {{port:=8080 # Port to listen on}} {{host # Interface}} -p {{port}}"
+            .as_labeled_command()
+            .unwrap();
+        assert_eq!(
+            cmd.next_label_spec().map(|(_, spec)| spec),
+            Some(LabelSpec {
+                name: "port",
+                function: None,
+                default: Some("8080"),
+                description: Some("Port to listen on"),
+            })
+        );
+        assert_eq!(
+            cmd.clone().fill(&HashMap::new()).unwrap_err().to_string(),
+            "Missing values for labels: host"
+        );
+        assert_eq!(
+            cmd.fill(&HashMap::from([("host".to_owned(), "0.0.0.0".to_owned())]))
+                .unwrap(),
+            "nc -l 8080 0.0.0.0 -p 8080"
+        );
+
+        let cmd = "git commit -m \"{{%cursor%}}\" -- {{path}}"
+            .as_labeled_command()
+            .unwrap();
+        assert_eq!(cmd.pending_labels(), vec!["path"]);
+        assert_eq!(
+            cmd.fill(&HashMap::from([("path".to_owned(), "src".to_owned())]))
+                .unwrap(),
+            "git commit -m \"{{%cursor%}}\" -- src"
+        );
+        assert!("git commit -m \"{{%cursor%}}\"".as_labeled_command().is_none());
+        assert_eq!(
+            strip_cursor_marker("a {{%cursor%}}b{{%cursor%}}", true),
+            "a {{%cursor%}}b"
+        );
+        assert_eq!(strip_cursor_marker("a {{%cursor%}}b{{%cursor%}}", false), "a b");
+    }
+
+    #[test]
+    fn test_label_suggestion_text() {
+        let suggestion = LabelSuggestion {
+            flat_root_cmd: "docker".into(),
+            flat_label: "image".into(),
+            suggestion: "nginx ## latest".into(),
+            usage: 3,
+        };
+        let text = suggestion.to_text();
+        assert_eq!(text, "# value: docker ## image ## nginx ## latest ## 3");
+        let parsed = LabelSuggestion::from_text(&text).unwrap();
+        assert_eq!(parsed.flat_label, "image");
+        assert_eq!(parsed.suggestion, "nginx ## latest");
+        assert_eq!(parsed.usage, 3);
+        assert!(LabelSuggestion::from_text("# alias: ll").is_none());
+    }
+
+    #[test]
+    fn test_label_functions() {
+        let functions = parse_label_functions(
+            "b64 = base64 -w0
+upper=tr a-z A-Z
+invalid
+=empty",
+        );
+        assert_eq!(functions.len(), 2);
+        assert_eq!(functions["b64"], "base64 -w0");
+        assert_eq!(split_label_function_with("file:b64", &functions), ("file", Some("b64")));
+        assert_eq!(
+            split_label_function_with("*token*:upper", &functions),
+            ("*token*", Some("upper"))
+        );
+        assert_eq!(
+            split_label_function_with("http://localhost", &functions),
+            ("http://localhost", None)
+        );
+        assert_eq!(split_label_function_with(":b64", &functions), (":b64", None));
+        assert_eq!(
+            split_label_function_with("start:date", &functions),
+            ("start:date", None)
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        let cmd = "ssh {{user}}@{{host}} -p {{port:=22}}".as_labeled_command().unwrap();
+        assert!(cmd.matches("ssh root@db.local -p 22"));
+        assert!(cmd.matches("ssh  root@db  -p 2222 "));
+        assert!(!cmd.matches("ssh root@db"));
+        assert!(!cmd.matches("scp root@db -p 22"));
+    }
+
+    #[test]
+    fn test_dynamic_labels() {
+        let mut cmd = "tar czf backup-{{%now:%Y%}}.tgz {{dir}} {{today}} # {{%today%}}"
+            .as_labeled_command()
+            .unwrap();
+        cmd.expand_dynamic_labels().unwrap();
+        assert_eq!(cmd.next_label().map(|(_, l)| l), Some("dir"));
+        cmd.set_next_label("src").unwrap();
+        assert_eq!(cmd.next_label().map(|(_, l)| l), Some("today"));
+        cmd.set_next_label("mon").unwrap();
+        let expected = Regex::new(r"^tar czf backup-\d{4}\.tgz src mon # \d{4}-\d{2}-\d{2}$").unwrap();
+        assert!(expected.is_match(&cmd.to_string()), "{cmd}");
+
+        let mut cmd = "touch {{%now:%Q%}}".as_labeled_command().unwrap();
+        assert!(cmd.expand_dynamic_labels().is_err());
+
+        // Labels that look like functions are still prompted, unless the function is a user-defined one
+        let mut cmd = "journalctl --since {{start:date}}".as_labeled_command().unwrap();
+        cmd.expand_dynamic_labels().unwrap();
+        assert_eq!(cmd.pending_labels(), vec!["start:date"]);
+    }
+}
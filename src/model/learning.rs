@@ -0,0 +1,35 @@
+use std::fmt::Display;
+
+/// Progress of a command being learnt
+#[derive(Clone, Copy)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub struct Learning {
+    /// Number of runs to consider the command learnt
+    pub goal: u64,
+    /// Number of runs since the command was flagged to be learnt
+    pub runs: u64,
+    /// Wether the command should be hidden until learnt, to practice recall
+    pub hide: bool,
+}
+
+impl Learning {
+    /// Determines if the goal has been reached
+    pub fn is_learnt(&self) -> bool {
+        self.runs >= self.goal
+    }
+
+    /// Determines if the command must be hidden, to practice recall
+    pub fn is_hidden(&self) -> bool {
+        self.hide && !self.is_learnt()
+    }
+}
+
+impl Display for Learning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_learnt() {
+            write!(f, "[learnt]")
+        } else {
+            write!(f, "[{}/{}]", self.runs, self.goal)
+        }
+    }
+}
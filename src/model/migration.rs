@@ -0,0 +1,88 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Rules to detect outdated commands, updated along with the repository
+static MIGRATION_RULES: Lazy<Vec<MigrationRule>> =
+    Lazy::new(|| parse_rules(include_str!("../../assets/migrations.txt")));
+
+/// Rule mapping an outdated invocation to its modern one
+struct MigrationRule {
+    pattern: Regex,
+    replacement: Option<String>,
+    note: String,
+}
+
+/// Hint about an outdated command
+#[derive(Clone)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub struct MigrationHint {
+    /// Modern command to replace the outdated one, if any
+    pub replacement: Option<String>,
+    /// Reason why the command is outdated
+    pub note: String,
+}
+
+/// Checks if the given command is outdated, returning a hint to update it
+pub fn migration_hint(cmd: &str) -> Option<MigrationHint> {
+    let mut replaced = cmd.to_owned();
+    let mut notes = Vec::new();
+    for rule in MIGRATION_RULES.iter().filter(|r| r.pattern.is_match(cmd)) {
+        if let Some(replacement) = &rule.replacement {
+            replaced = rule.pattern.replace_all(&replaced, replacement.as_str()).into_owned();
+        }
+        notes.push(rule.note.as_str());
+    }
+    (!notes.is_empty()).then(|| MigrationHint {
+        replacement: (replaced != cmd).then_some(replaced),
+        note: notes.join(". "),
+    })
+}
+
+/// Parses the migration rules, skipping comments and invalid lines
+fn parse_rules(content: &str) -> Vec<MigrationRule> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (rule, note) = line.split_once(" ## ")?;
+            let (pattern, replacement) = rule.split_once(" => ")?;
+            Some(MigrationRule {
+                pattern: Regex::new(pattern.trim()).ok()?,
+                replacement: Some(replacement.trim()).filter(|r| *r != "-").map(ToOwned::to_owned),
+                note: note.trim().to_owned(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migration_hint() {
+        let rules = include_str!("../../assets/migrations.txt");
+        let valid = rules.lines().filter(|l| !l.trim().is_empty() && !l.starts_with('#'));
+        assert_eq!(parse_rules(rules).len(), valid.count(), "every rule must be valid");
+
+        assert!(migration_hint("docker compose up -d").is_none());
+        let hint = migration_hint("docker-compose up -d | egrep 'error|warn'").unwrap();
+        assert_eq!(
+            hint.replacement.as_deref(),
+            Some("docker compose up -d | grep -E 'error|warn'")
+        );
+        assert_eq!(
+            hint.note,
+            "Compose is now a plugin of the docker CLI. egrep is obsolescent"
+        );
+        let hint = migration_hint("sudo docker-compose -f docker-compose.yml up && fgrep -r x .").unwrap();
+        assert_eq!(
+            hint.replacement.as_deref(),
+            Some("sudo docker compose -f docker-compose.yml up && grep -F -r x .")
+        );
+        assert!(migration_hint("cat egrep.log docker-compose.yml").is_none());
+        let hint = migration_hint("kubectl get cs").unwrap();
+        assert!(hint.replacement.is_none());
+    }
+}
@@ -1,5 +1,21 @@
+mod category;
 mod command;
+mod execution;
+mod export;
+mod import;
 mod label;
+mod learning;
+mod migration;
+mod query;
+mod template;
 
+pub use category::*;
 pub use command::*;
+pub use execution::*;
+pub use export::*;
+pub use import::*;
 pub use label::*;
+pub use learning::*;
+pub use migration::*;
+pub use query::*;
+pub use template::*;
@@ -0,0 +1,224 @@
+use std::fmt::{Display, Formatter};
+
+use super::Command;
+use crate::common::flatten_str;
+
+/// Search criteria parsed from the text typed by the user, already flattened.
+///
+/// Besides plain terms, it supports `"exact phrases"`, `!excluded` terms or phrases and filters on a single field of
+/// the commands, like `tag:docker`, `desc:"clean up"`, `cmd:compose` or `val:prod`. Exclusions aren't prefixed with a
+/// dash, as it would clash with the flags of the commands being searched, like `ls -la`.
+#[derive(Default)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub struct SearchQuery {
+    /// Plain terms
+    pub terms: Vec<String>,
+    /// Phrases that must be found as typed
+    pub phrases: Vec<String>,
+    /// Terms or phrases that must not be found as whole words
+    pub excluded: Vec<String>,
+    /// Hashtags the description must contain, from `tag:` filters
+    pub tags: Vec<String>,
+    /// Text the description must contain, from `desc:` filters
+    pub descriptions: Vec<String>,
+    /// Text the command must contain, from `cmd:` filters
+    pub commands: Vec<String>,
+    /// Text of the values some label of the command must have been filled with, from `val:` filters
+    pub values: Vec<String>,
+}
+
+/// Prefixes of the filters on a single field
+const FIELD_PREFIXES: [&str; 4] = ["tag:", "desc:", "cmd:", "val:"];
+
+impl SearchQuery {
+    /// Parses the given search text
+    pub fn parse(search: &str) -> Self {
+        let mut query = SearchQuery::default();
+        let flat_search = flatten_str(search);
+        let mut chars = flat_search.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c.is_whitespace() {
+                continue;
+            }
+            let excluded = c == '!' && chars.peek().map(|n| !n.is_whitespace()).unwrap_or(false);
+            let first = if excluded { chars.next() } else { Some(c) };
+            let (token, is_phrase) = match first {
+                Some('"') => (chars.by_ref().take_while(|c| *c != '"').collect::<String>(), true),
+                Some(first) => {
+                    let mut token = first.to_string();
+                    while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                        token.push(c);
+                        // Filters can be followed by a phrase, like `desc:"clean up"`
+                        if c == ':' && chars.peek() == Some(&'"') && FIELD_PREFIXES.contains(&token.as_str()) {
+                            chars.next();
+                            token.extend(chars.by_ref().take_while(|c| *c != '"'));
+                            break;
+                        }
+                    }
+                    (token, false)
+                }
+                None => continue,
+            };
+            let token = token.split_whitespace().collect::<Vec<_>>().join(" ");
+            if token.is_empty() {
+                continue;
+            }
+            let filter = FIELD_PREFIXES
+                .iter()
+                .find_map(|prefix| Some((*prefix, token.strip_prefix(prefix)?.trim())))
+                .filter(|(_, value)| !value.is_empty() && !excluded && !is_phrase);
+            if let Some((prefix, value)) = filter {
+                let value = value.to_owned();
+                match prefix {
+                    "tag:" => query.tags.push(value.trim_start_matches('#').to_owned()),
+                    "desc:" => query.descriptions.push(value),
+                    "cmd:" => query.commands.push(value),
+                    _ => query.values.push(value),
+                }
+                continue;
+            }
+            match (excluded, is_phrase) {
+                (true, _) => query.excluded.push(token),
+                (false, true) => query.phrases.push(token),
+                (false, false) => query.terms.push(token),
+            }
+        }
+        query
+    }
+
+    /// Retrieves the words that results must match: every term and the words of every phrase
+    pub fn words(&self) -> Vec<&str> {
+        self.terms
+            .iter()
+            .map(String::as_str)
+            .chain(self.phrases.iter().flat_map(|p| p.split_whitespace()))
+            .collect()
+    }
+
+    /// Retrieves the terms, phrases and text filters to be highlighted on the results
+    pub fn highlights(&self) -> Vec<&str> {
+        self.terms
+            .iter()
+            .chain(self.phrases.iter())
+            .chain(self.descriptions.iter())
+            .chain(self.commands.iter())
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Determines if the given command satisfies the phrases (unless relaxed), exclusions and filters of this query,
+    /// except for the `val:` ones which depend on the stored label values
+    pub fn accepts(&self, command: &Command, relaxed: bool) -> bool {
+        let flat_cmd = flatten_str(&command.cmd);
+        let flat_description = flatten_str(&command.description);
+        let contains = |text: &String| flat_cmd.contains(text.as_str()) || flat_description.contains(text.as_str());
+        let contains_word = |text: &String| has_word(&flat_cmd, text) || has_word(&flat_description, text);
+        // Tags match the hashtags they prefix, like `tag:dock` does with `#docker`
+        let tagged = |tag: &String| {
+            flat_description
+                .split_whitespace()
+                .filter_map(|word| word.strip_prefix('#'))
+                .any(|hashtag| hashtag.starts_with(tag.as_str()))
+        };
+        (relaxed || self.phrases.iter().all(contains))
+            && !self.excluded.iter().any(contains_word)
+            && self.tags.iter().all(tagged)
+            && self.descriptions.iter().all(|d| flat_description.contains(d.as_str()))
+            && self.commands.iter().all(|c| flat_cmd.contains(c.as_str()))
+    }
+}
+
+/// Determines if the given text contains the word (or words), not being part of a longer one
+fn has_word(text: &str, word: &str) -> bool {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(word).any(|(ix, _)| {
+        !text[..ix].chars().next_back().is_some_and(is_word_char)
+            && !text[ix + word.len()..].chars().next().is_some_and(is_word_char)
+    })
+}
+
+/// How a command matched the search, which determines its tier on the ranking before its usage is considered
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum SearchMatch {
+    /// The search is the alias of the command, being the only result
+    Alias,
+    /// The alias of the command contains the searched words
+    AliasWords,
+    /// The command starts with the searched words, in order
+    CommandPrefix,
+    /// Every searched word (or any of them, when relaxed) prefixes a word of the command or description
+    Words,
+    /// The searched words are found anywhere within the command or description
+    Substring,
+    /// There are no words to search for, commands are just listed
+    Listed,
+}
+
+impl SearchMatch {
+    /// Builds the match from the tier computed when searching, the higher the better
+    pub(crate) fn from_tier(tier: i64) -> Self {
+        match tier {
+            3 => SearchMatch::AliasWords,
+            2 => SearchMatch::CommandPrefix,
+            1 => SearchMatch::Words,
+            _ => SearchMatch::Substring,
+        }
+    }
+}
+
+impl Display for SearchMatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchMatch::Alias => write!(f, "alias"),
+            SearchMatch::AliasWords => write!(f, "alias words"),
+            SearchMatch::CommandPrefix => write!(f, "command prefix"),
+            SearchMatch::Words => write!(f, "words"),
+            SearchMatch::Substring => write!(f, "substring"),
+            SearchMatch::Listed => write!(f, "listed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        let query = SearchQuery::parse(r#"Docker  "Run  -it" !rm !"no cache" ! #ops "unclosed"#);
+        assert_eq!(query.terms, vec!["docker", "!", "#ops"]);
+        assert_eq!(query.phrases, vec!["run -it", "unclosed"]);
+        assert_eq!(query.excluded, vec!["rm", "no cache"]);
+        assert_eq!(query.words(), vec!["docker", "!", "#ops", "run", "-it", "unclosed"]);
+
+        let command = Command::new("user", "docker run -it --rm ubuntu", "Run a container #ops");
+        assert!(!query.accepts(&command, false));
+        assert!(SearchQuery::parse(r#""run -it" #ops"#).accepts(&command, false));
+        assert!(!SearchQuery::parse(r#""it run""#).accepts(&command, false));
+        assert!(SearchQuery::parse(r#""it run""#).accepts(&command, true));
+        assert!(!SearchQuery::parse("!ubuntu").accepts(&command, false));
+        assert!(SearchQuery::parse("!ubunt !contain").accepts(&command, false));
+
+        // Flags are searched as regular terms
+        let query = SearchQuery::parse("ls -la");
+        assert_eq!(query.terms, vec!["ls", "-la"]);
+        assert!(query.excluded.is_empty());
+        assert!(query.accepts(&Command::new("user", "ls -la", "List files"), false));
+        let query = SearchQuery::parse("tar -xzf");
+        assert_eq!(query.terms, vec!["tar", "-xzf"]);
+        assert!(query.accepts(&Command::new("user", "tar -xzf {{file}}", "Extract #tar"), false));
+
+        let query = SearchQuery::parse(r#"run tag:#OPS desc:"a  container" cmd:ubuntu val:prod tag:"#);
+        assert_eq!(query.terms, vec!["run", "tag:"]);
+        assert_eq!(query.tags, vec!["ops"]);
+        assert_eq!(query.descriptions, vec!["a container"]);
+        assert_eq!(query.commands, vec!["ubuntu"]);
+        assert_eq!(query.values, vec!["prod"]);
+        assert_eq!(query.words(), vec!["run", "tag:"]);
+        assert!(query.accepts(&command, false));
+        assert!(SearchQuery::parse("tag:op").accepts(&command, false));
+        assert!(!SearchQuery::parse("tag:docker").accepts(&command, false));
+        assert!(!SearchQuery::parse(r#"desc:"run it""#).accepts(&command, false));
+    }
+}
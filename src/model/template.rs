@@ -0,0 +1,230 @@
+use std::ops::Range;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Regex to match IPv4 addresses, with an optional port
+static IP_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\d{1,3}(\.\d{1,3}){3}(:\d{1,5})?$").unwrap());
+
+/// Part of a command that could be turned into a label when bookmarking it
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub struct TemplateCandidate {
+    /// Byte range of the value within the command
+    pub range: Range<usize>,
+    /// Name of the label it would be turned into
+    pub name: String,
+}
+
+/// Detects the parts of a command likely to change between runs: IPs, urls, paths, numbers and quoted strings.
+///
+/// Equal values get the same name, so they're filled only once, while different values of the same kind are numbered.
+pub fn template_candidates(cmd: &str) -> Vec<TemplateCandidate> {
+    let mut candidates: Vec<(Range<usize>, &'static str)> = Vec::new();
+    for (ix, range) in shell_words(cmd).into_iter().enumerate() {
+        let word = &cmd[range.clone()];
+        // The program itself and the labels already there are never candidates
+        if ix == 0 || word.contains("{{") || word.contains("}}") || word.contains(" #") {
+            continue;
+        }
+        // Only the value of flags is considered, like `--port=8080`
+        let range = if word.starts_with('-') {
+            match word.find('=') {
+                Some(eq) => range.start + eq + 1..range.end,
+                None => continue,
+            }
+        } else {
+            range
+        };
+        let value = &cmd[range.clone()];
+        let quoted = value.len() >= 2
+            && ((value.starts_with('"') && value.ends_with('"')) || (value.starts_with('\'') && value.ends_with('\'')));
+        let kind = if quoted {
+            candidates.push((range.start + 1..range.end - 1, "text"));
+            continue;
+        } else if IP_REGEX.is_match(value) {
+            "ip"
+        } else if value.contains("://") {
+            "url"
+        } else if value.starts_with('/')
+            || value.starts_with("~/")
+            || value.starts_with("./")
+            || value.starts_with("../")
+        {
+            "path"
+        } else if !value.is_empty() && value.chars().all(|c| c.is_ascii_digit()) {
+            "number"
+        } else {
+            continue;
+        };
+        candidates.push((range, kind));
+    }
+
+    // Name the candidates after their kind, numbering the different values of the same kind
+    let mut named: Vec<(&str, String)> = Vec::new();
+    candidates
+        .into_iter()
+        .filter(|(range, _)| !range.is_empty())
+        .map(|(range, kind)| {
+            let value = &cmd[range.clone()];
+            let name = match named.iter().find(|(v, _)| *v == value) {
+                Some((_, name)) => name.clone(),
+                None => {
+                    let count = named.iter().filter(|(_, n)| n.split('-').next() == Some(kind)).count();
+                    let name = match count {
+                        0 => kind.to_owned(),
+                        n => format!("{kind}-{}", n + 1),
+                    };
+                    named.push((value, name.clone()));
+                    name
+                }
+            };
+            TemplateCandidate { range, name }
+        })
+        .collect()
+}
+
+/// Turns the given candidates of a command into labels, keeping their current value as the default one
+pub fn templatize(cmd: &str, candidates: &[&TemplateCandidate]) -> String {
+    let mut candidates = candidates.to_vec();
+    candidates.sort_by_key(|c| c.range.start);
+    let mut templated = String::with_capacity(cmd.len());
+    let mut last = 0;
+    for candidate in candidates {
+        templated.push_str(&cmd[last..candidate.range.start]);
+        templated.push_str(&format!(
+            "{{{{{}:={}}}}}",
+            candidate.name,
+            &cmd[candidate.range.clone()]
+        ));
+        last = candidate.range.end;
+    }
+    templated.push_str(&cmd[last..]);
+    templated
+}
+
+/// Normalizes a command so that the ones written slightly differently are equal: extra whitespace is dropped and
+/// quotes are only kept when needed (preferring single ones).
+///
+/// Flags are never reordered nor rewritten, as their order and spelling can change the meaning of a command.
+pub fn normalize_command(cmd: &str) -> String {
+    shell_words(cmd)
+        .into_iter()
+        .map(|range| normalize_quotes(&cmd[range]))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Rewrites the quotes of a word (or the value of a `--flag=value` one) into their canonical form: no quotes when not
+/// needed and single quotes unless double ones are required
+fn normalize_quotes(word: &str) -> String {
+    let (prefix, value) = match word.find('=') {
+        Some(eq) if word.starts_with('-') => word.split_at(eq + 1),
+        _ => ("", word),
+    };
+    // Double quotes are only rewritten when they don't expand or escape anything
+    let quoted = value.len() >= 2
+        && ((value.starts_with('\'') && value.ends_with('\''))
+            || (value.starts_with('"')
+                && value.ends_with('"')
+                && !value[1..value.len() - 1].contains(['$', '`', '\\', '\'', '"'])));
+    if !quoted {
+        return word.to_owned();
+    }
+    let unquoted = &value[1..value.len() - 1];
+    if !unquoted.is_empty()
+        && unquoted
+            .chars()
+            .all(|c| c.is_alphanumeric() || "-_./:@%+=,".contains(c))
+    {
+        format!("{prefix}{unquoted}")
+    } else {
+        format!("{prefix}'{unquoted}'")
+    }
+}
+
+/// Splits a command into its words, keeping quoted strings together
+fn shell_words(cmd: &str) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut quote = None;
+    for (ix, c) in cmd.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => (),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                start.get_or_insert(ix);
+            }
+            (None, c) if c.is_whitespace() => {
+                if let Some(start) = start.take() {
+                    words.push(start..ix);
+                }
+            }
+            (None, _) => {
+                start.get_or_insert(ix);
+            }
+        }
+    }
+    if let Some(start) = start {
+        words.push(start..cmd.len());
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_command() {
+        assert_eq!(normalize_command(r#"ls  -la "src""#), "ls -la src");
+        assert_eq!(
+            normalize_command(r#"grep -rn "foo bar" .  --color"#),
+            normalize_command("grep -rn 'foo bar' . --color")
+        );
+        assert_eq!(
+            normalize_command(r#"git log --oneline --author="john doe" -n 5"#),
+            "git log --oneline --author='john doe' -n 5"
+        );
+        // Flags are never reordered nor rewritten
+        assert_ne!(normalize_command("cut -d , -f 1"), normalize_command("cut -f , -d 1"));
+        assert_ne!(
+            normalize_command("rsync --include=*.c --exclude=* src dst"),
+            normalize_command("rsync --exclude=* --include=*.c src dst")
+        );
+        assert_ne!(
+            normalize_command("gcc -lfoo -lbar"),
+            normalize_command("gcc -lbar -lfoo")
+        );
+        assert_eq!(normalize_command("find . -name x"), "find . -name x");
+        assert_eq!(normalize_command(r#"echo "$HOME" -n"#), r#"echo "$HOME" -n"#);
+    }
+
+    #[test]
+    fn test_templatize() {
+        let cmd = r#"scp -P 2222 ./app.tar user@10.0.0.1:/tmp --limit=100 -m "hello world" 10.0.0.2 10.0.0.1"#;
+        let candidates = template_candidates(cmd);
+        let found = candidates
+            .iter()
+            .map(|c| (c.name.as_str(), &cmd[c.range.clone()]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                ("number", "2222"),
+                ("path", "./app.tar"),
+                ("number-2", "100"),
+                ("text", "hello world"),
+                ("ip", "10.0.0.2"),
+                ("ip-2", "10.0.0.1"),
+            ]
+        );
+
+        let selected = [&candidates[1], &candidates[3]];
+        assert_eq!(
+            templatize(cmd, &selected),
+            r#"scp -P 2222 {{path:=./app.tar}} user@10.0.0.1:/tmp --limit=100 -m "{{text:=hello world}}" 10.0.0.2 10.0.0.1"#
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    env, fs,
+    hash::{Hash, Hasher},
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::storage::data_dir;
+
+/// Environment variable with the paste endpoint the shared commands are uploaded to
+const PASTE_URL_ENV: &str = "INTELLI_PASTE_URL";
+
+/// Default paste endpoint, when not configured
+const DEFAULT_PASTE_URL: &str = "https://paste.rs";
+
+/// Determines if the given location is a remote one, to be downloaded instead of read from disk
+pub fn is_url(location: &str) -> bool {
+    location.starts_with("https://") || location.starts_with("http://")
+}
+
+/// Retrieves the paste endpoint the shared commands are uploaded to
+pub fn paste_endpoint() -> String {
+    env::var(PASTE_URL_ENV)
+        .ok()
+        .map(|url| url.trim().to_owned())
+        .filter(|url| !url.is_empty())
+        .unwrap_or_else(|| DEFAULT_PASTE_URL.to_owned())
+}
+
+/// Uploads the given text to the configured paste endpoint, which must accept the raw content on a `POST` and reply
+/// with the url of the paste, returning it
+pub fn upload_paste(text: &str) -> Result<String> {
+    let endpoint = paste_endpoint();
+    let mut child = curl()
+        .args(["--data-binary", "@-", &endpoint])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Couldn't run curl, is it installed?")?;
+    child
+        .stdin
+        .take()
+        .context("Couldn't write to curl")?
+        .write_all(text.as_bytes())
+        .context("Couldn't write to curl")?;
+    let url = read_output(child.wait_with_output().context("Couldn't run curl")?, &endpoint)?;
+    let url = url.trim();
+    if !is_url(url) {
+        bail!("The paste endpoint '{endpoint}' didn't reply with a url: {url}");
+    }
+    Ok(url.to_owned())
+}
+
+/// Downloads the content of the given url, which is kept on a local cache when `keep` is set so one-shot pastes can
+/// be downloaded again (like when previewing an import before applying it). The cached content is used and removed
+/// once downloaded without `keep`.
+pub fn download_cached(url: &str, keep: bool) -> Result<String> {
+    let cache = cache_file(url)?;
+    if let Ok(content) = fs::read_to_string(&cache) {
+        if !keep {
+            let _ = fs::remove_file(&cache);
+        }
+        return Ok(content);
+    }
+    let content = download_url(url)?;
+    if keep {
+        fs::create_dir_all(cache.parent().unwrap_or(&cache)).context("Error creating the downloads cache dir")?;
+        fs::write(&cache, &content).context("Error writing the downloads cache")?;
+    }
+    Ok(content)
+}
+
+/// Retrieves the file where the content of the given url is cached
+fn cache_file(url: &str) -> Result<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    Ok(data_dir()?.join("downloads").join(format!("{:x}.txt", hasher.finish())))
+}
+
+/// Downloads the content of the given url
+fn download_url(url: &str) -> Result<String> {
+    let output = curl()
+        .args(["--location", url])
+        .output()
+        .context("Couldn't run curl, is it installed?")?;
+    read_output(output, url)
+}
+
+/// Builds a silent curl command failing on http errors
+fn curl() -> Command {
+    let mut command = Command::new("curl");
+    command.args(["--silent", "--show-error", "--fail"]);
+    command
+}
+
+/// Reads the standard output of a curl run, failing with its error if it didn't succeed
+fn read_output(output: std::process::Output, url: &str) -> Result<String> {
+    if !output.status.success() {
+        bail!(
+            "Request to '{url}' failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout).with_context(|| format!("The content of '{url}' is not valid UTF-8"))
+}
@@ -0,0 +1,159 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+};
+
+use anyhow::{bail, Context, Result};
+use itertools::Itertools;
+use serde::Deserialize;
+
+use crate::storage::data_dir;
+
+/// Command produced by an importer plugin, on the same shape as the JSON export
+#[derive(Deserialize)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub struct PluginCommand {
+    pub command: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub alias: Option<String>,
+}
+
+/// Runs the importer of the given plugin as `<plugin> import [location]`, which must print a JSON array of commands
+/// (objects with `command` and optionally `description` and `alias`) to its standard output
+pub fn import_plugin_commands(plugin: &str, location: Option<&str>) -> Result<Vec<PluginCommand>> {
+    let output = run_plugin(plugin, "import", location, None)?;
+    parse_plugin_commands(&output).with_context(|| format!("The '{plugin}' plugin didn't print a valid commands array"))
+}
+
+/// Runs the exporter of the given plugin as `<plugin> export [location]`, writing the JSON array of commands to its
+/// standard input, returning whatever it printed to its standard output
+pub fn export_plugin_commands(plugin: &str, location: Option<&str>, commands_json: &str) -> Result<String> {
+    run_plugin(plugin, "export", location, Some(commands_json))
+}
+
+/// Retrieves the directory where the plugins are discovered from, within the data dir
+fn plugins_dir() -> Result<PathBuf> {
+    Ok(data_dir()?.join("plugins"))
+}
+
+/// Lists the names of the plugins available on the given dir, the file names of its executables without extension
+fn list_plugins_in(dir: impl AsRef<Path>) -> Result<Vec<String>> {
+    let Ok(entries) = fs::read_dir(dir.as_ref()) else {
+        return Ok(Vec::new());
+    };
+    Ok(entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+        .filter(|name| !name.starts_with('.'))
+        .sorted()
+        .dedup()
+        .collect())
+}
+
+/// Finds the executable of the given plugin on the plugins dir
+fn find_plugin(plugin: &str) -> Result<PathBuf> {
+    let dir = plugins_dir()?;
+    let found = fs::read_dir(&dir)
+        .ok()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| path.is_file() && path.file_stem().is_some_and(|stem| stem == plugin));
+    match found {
+        Some(path) => Ok(path),
+        None => {
+            let available = list_plugins_in(&dir)?;
+            if available.is_empty() {
+                bail!("Plugin '{plugin}' not found, there are no plugins on {}", dir.display())
+            } else {
+                bail!(
+                    "Plugin '{plugin}' not found on {}, available ones: {}",
+                    dir.display(),
+                    available.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// Runs a plugin action, feeding the given input if any, returning its standard output
+fn run_plugin(plugin: &str, action: &str, location: Option<&str>, input: Option<&str>) -> Result<String> {
+    let path = find_plugin(plugin)?;
+    let mut child = Command::new(&path)
+        .arg(action)
+        .args(location)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Couldn't run the '{plugin}' plugin at {}", path.display()))?;
+    // The input is written from another thread while the output is drained, so a plugin printing while reading can't
+    // deadlock once the pipe buffers fill up
+    let stdin = child.stdin.take();
+    let (written, output) = thread::scope(|s| {
+        let writer = s.spawn(|| match (input, stdin) {
+            (Some(input), Some(mut stdin)) => stdin.write_all(input.as_bytes()),
+            _ => Ok(()),
+        });
+        let output = child.wait_with_output();
+        (writer.join(), output)
+    });
+    let output = output.with_context(|| format!("Error running the '{plugin}' plugin"))?;
+    match written {
+        Ok(Ok(())) => (),
+        Ok(Err(err)) if err.kind() == io::ErrorKind::BrokenPipe => (),
+        Ok(Err(err)) => return Err(err).with_context(|| format!("Error writing to the '{plugin}' plugin")),
+        Err(_) => bail!("Error writing to the '{plugin}' plugin"),
+    }
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("The '{plugin}' plugin failed to {action}: {}", stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parses the commands printed by an importer plugin, skipping the ones without a command
+fn parse_plugin_commands(output: &str) -> Result<Vec<PluginCommand>> {
+    let commands: Vec<PluginCommand> = serde_json::from_str(output)?;
+    Ok(commands.into_iter().filter(|c| !c.command.trim().is_empty()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plugin_commands() {
+        let output = r#"[
+            { "command": "pet search", "description": "Search snippets #pet", "alias": "ps" },
+            { "command": "ls -la" },
+            { "command": " ", "description": "Empty" }
+        ]"#;
+        let commands = parse_plugin_commands(output)
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.command, c.description, c.alias))
+            .collect_vec();
+        assert_eq!(
+            commands,
+            vec![
+                (
+                    "pet search".to_owned(),
+                    "Search snippets #pet".to_owned(),
+                    Some("ps".to_owned())
+                ),
+                ("ls -la".to_owned(), String::new(), None),
+            ]
+        );
+
+        assert!(parse_plugin_commands("not json").is_err());
+    }
+}
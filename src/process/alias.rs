@@ -0,0 +1,53 @@
+use anyhow::Result;
+use crossterm::event::Event;
+use ratatui::{backend::Backend, layout::Rect, Frame};
+
+use crate::{storage::SqliteStorage, Process, ProcessOutput};
+
+/// Process to expand the current shell buffer into a command whose alias starts with it, cycling through the
+/// candidates by their index
+///
+/// This process will provide no UI, it will perform the job on `peek`
+pub struct CycleAliasProcess<'a> {
+    /// Storage
+    storage: &'a SqliteStorage,
+    /// Alias prefix typed on the shell buffer
+    prefix: String,
+    /// Index of the candidate to expand, wrapping around after the last one
+    index: usize,
+}
+
+impl<'a> CycleAliasProcess<'a> {
+    pub fn new(storage: &'a SqliteStorage, prefix: String, index: usize) -> Self {
+        Self { storage, prefix, index }
+    }
+}
+
+impl<'a> Process for CycleAliasProcess<'a> {
+    fn min_height(&self) -> usize {
+        1
+    }
+
+    fn peek(&mut self) -> Result<Option<ProcessOutput>> {
+        let prefix = self.prefix.trim();
+        if prefix.is_empty() || prefix.contains(char::is_whitespace) {
+            return Ok(Some(ProcessOutput::empty()));
+        }
+        let candidates = self.storage.find_commands_by_alias_prefix(prefix)?;
+        Ok(Some(match candidates.len() {
+            0 => ProcessOutput::empty(),
+            len => {
+                let command = &candidates[self.index % len];
+                ProcessOutput::output(command.prefixed_cmd()).sensitive(command.is_sensitive())
+            }
+        }))
+    }
+
+    fn render<B: Backend>(&mut self, _frame: &mut Frame<B>, _area: Rect) {
+        unreachable!()
+    }
+
+    fn process_raw_event(&mut self, _event: Event) -> Result<Option<ProcessOutput>> {
+        unreachable!()
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use crossterm::event::Event;
+use ratatui::{backend::Backend, layout::Rect, Frame};
+use serde_json::json;
+
+use crate::{model::split_tags, storage::SqliteStorage, Process, ProcessOutput};
+
+/// Max number of candidates returned to the completer
+const MAX_CANDIDATES: usize = 25;
+
+/// Process to complete the words typed on nushell, outputting the candidates on the JSON shape expected by its
+/// `external_completer`
+///
+/// This process will provide no UI, it will perform the job on `peek`
+pub struct NuCompleterProcess<'a> {
+    /// Storage
+    storage: &'a SqliteStorage,
+    /// Words typed so far, the last one being completed
+    spans: Vec<String>,
+}
+
+impl<'a> NuCompleterProcess<'a> {
+    pub fn new(storage: &'a SqliteStorage, spans: Vec<String>) -> Self {
+        Self { storage, spans }
+    }
+}
+
+impl<'a> Process for NuCompleterProcess<'a> {
+    fn min_height(&self) -> usize {
+        1
+    }
+
+    fn peek(&mut self) -> Result<Option<ProcessOutput>> {
+        let prefix = self.spans.join(" ");
+        let candidates = match self.spans.last() {
+            Some(last) if !prefix.trim().is_empty() => {
+                // Nushell replaces the span being completed, so candidates start from it
+                let start = prefix.len() - last.len();
+                self.storage
+                    .find_commands_by_prefix(&prefix, "nu", MAX_CANDIDATES)?
+                    .into_iter()
+                    .map(|c| {
+                        let (description, _) = split_tags(&c.description);
+                        json!({ "value": &c.cmd[start..], "description": description })
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        // Written as the output, so it's printed to stdout even with `--quiet`
+        Ok(Some(ProcessOutput::output(
+            serde_json::Value::Array(candidates).to_string(),
+        )))
+    }
+
+    fn render<B: Backend>(&mut self, _frame: &mut Frame<B>, _area: Rect) {
+        unreachable!()
+    }
+
+    fn process_raw_event(&mut self, _event: Event) -> Result<Option<ProcessOutput>> {
+        unreachable!()
+    }
+}
@@ -1,18 +1,21 @@
 use anyhow::Result;
-use crossterm::event::Event;
+use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers};
+use itertools::Itertools;
 use ratatui::{
     backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
-    style::Style,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Borders, Paragraph},
     Frame,
 };
 
 use crate::{
     common::{
-        widget::{CustomParagraph, CustomWidget, TextInput},
+        widget::{CustomParagraph, CustomWidget, TagSuggestions, TextInput},
         ExecutionContext, InteractiveProcess,
     },
-    model::Command,
+    model::{split_tags, template_candidates, templatize, update_tags, Command, TemplateCandidate},
     storage::SqliteStorage,
     Process, ProcessOutput,
 };
@@ -27,20 +30,37 @@ pub struct EditCommandProcess<'s> {
     alias: CustomParagraph<TextInput>,
     /// Command itself
     cmd: CustomParagraph<TextInput>,
-    /// Command description
+    /// Command description, without tags
     description: CustomParagraph<TextInput>,
+    /// Command tags, space-separated
+    tags: CustomParagraph<TextInput>,
+    /// Suggestions to autocomplete the tag being typed
+    tag_suggestions: CustomParagraph<TagSuggestions>,
     /// Kind of field currently active
     active_field_kind: ActiveFieldKind,
+    /// Parts of the command being picked to be turned into labels, if templatizing
+    templating: Option<Templating>,
     /// Execution context
     ctx: ExecutionContext,
 }
 
+/// Parts of a command that could be turned into labels, along with the ones picked
+struct Templating {
+    candidates: Vec<TemplateCandidate>,
+    selected: Vec<bool>,
+    current: usize,
+}
+
 pub enum ActiveFieldKind {
     Alias,
     Command,
     Description,
+    Tags,
 }
 
+/// Max number of tags suggested at once
+const MAX_TAG_SUGGESTIONS: usize = 5;
+
 impl<'s> EditCommandProcess<'s> {
     pub fn new(storage: &'s SqliteStorage, command: Command, ctx: ExecutionContext) -> Result<Self> {
         let active_field_kind = if !command.cmd.is_empty() && command.description.is_empty() {
@@ -61,16 +81,29 @@ impl<'s> EditCommandProcess<'s> {
             .block_title("Command")
             .style(Style::default());
 
-        let mut description = CustomParagraph::new(TextInput::new(&command.description))
+        let (description_text, tags) = split_tags(&command.description);
+
+        let mut description = CustomParagraph::new(TextInput::new(description_text))
             .inline(ctx.inline)
             .inline_title("Description:")
             .block_title("Description")
             .style(Style::default());
 
+        let mut tags = CustomParagraph::new(TextInput::new(tags.join(" ")))
+            .inline(ctx.inline)
+            .inline_title("(tags)")
+            .block_title("Tags")
+            .style(Style::default().fg(ctx.theme.description));
+
+        let tag_suggestions = CustomParagraph::new(TagSuggestions::default())
+            .inline(true)
+            .style(Style::default());
+
         match active_field_kind {
             ActiveFieldKind::Alias => alias.set_focus(true),
             ActiveFieldKind::Command => cmd.set_focus(true),
             ActiveFieldKind::Description => description.set_focus(true),
+            ActiveFieldKind::Tags => tags.set_focus(true),
         };
 
         Ok(Self {
@@ -79,23 +112,162 @@ impl<'s> EditCommandProcess<'s> {
             alias,
             cmd,
             description,
+            tags,
+            tag_suggestions,
             active_field_kind,
+            templating: None,
             ctx,
         })
     }
 
+    /// Starts picking the parts of the command to be turned into labels, if there's any candidate
+    fn start_templating(&mut self) {
+        let candidates = template_candidates(self.cmd.inner().as_str());
+        if !candidates.is_empty() {
+            self.templating = Some(Templating {
+                selected: vec![false; candidates.len()],
+                candidates,
+                current: 0,
+            });
+        }
+    }
+
+    /// Turns the picked parts of the command into labels, keeping their value as the default one
+    fn apply_templating(&mut self) {
+        if let Some(templating) = self.templating.take() {
+            let picked = templating
+                .candidates
+                .iter()
+                .zip(templating.selected)
+                .filter_map(|(candidate, selected)| selected.then_some(candidate))
+                .collect_vec();
+            let mut input = TextInput::new(templatize(self.cmd.inner().as_str(), &picked));
+            input.move_end();
+            *self.cmd.inner_mut() = input;
+        }
+    }
+
+    /// Processes the events while templatizing the command
+    fn process_templating_event(&mut self, event: Event) {
+        let Some(templating) = &mut self.templating else {
+            return;
+        };
+        let Event::Key(k) = event else {
+            return;
+        };
+        if k.kind != KeyEventKind::Press {
+            return;
+        }
+        let len = templating.candidates.len();
+        match k.code {
+            KeyCode::Left | KeyCode::Up | KeyCode::BackTab => templating.current = (templating.current + len - 1) % len,
+            KeyCode::Right | KeyCode::Down | KeyCode::Tab => templating.current = (templating.current + 1) % len,
+            KeyCode::Char(' ') => templating.selected[templating.current] = !templating.selected[templating.current],
+            KeyCode::Enter => self.apply_templating(),
+            KeyCode::Esc => self.templating = None,
+            KeyCode::Char('t') if k.modifiers.contains(KeyModifiers::CONTROL) => self.templating = None,
+            _ => (),
+        }
+    }
+
+    /// Renders the command highlighting the parts that can be turned into labels
+    fn render_templating<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let Some(templating) = &self.templating else {
+            return;
+        };
+        let cmd = self.cmd.inner().as_str();
+        let mut spans = Vec::new();
+        if self.ctx.inline {
+            spans.push(Span::raw("Templatize: "));
+        }
+        let mut last = 0;
+        for (ix, candidate) in templating.candidates.iter().enumerate() {
+            spans.push(Span::raw(&cmd[last..candidate.range.start]));
+            let mut style = Style::default().fg(self.ctx.theme.secondary);
+            if templating.selected[ix] {
+                style = Style::default().fg(self.ctx.theme.highlight);
+            }
+            if ix == templating.current {
+                style = style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+            }
+            let text = if templating.selected[ix] {
+                format!("{{{{{}}}}}", candidate.name)
+            } else {
+                cmd[candidate.range.clone()].to_owned()
+            };
+            spans.push(Span::styled(text, style));
+            last = candidate.range.end;
+        }
+        spans.push(Span::raw(&cmd[last..]));
+        let mut paragraph = Paragraph::new(Line::from(spans));
+        if !self.ctx.inline {
+            paragraph = paragraph.block(
+                self.ctx
+                    .theme
+                    .block(Borders::ALL)
+                    .title(" Templatize (space to toggle, enter to apply) "),
+            );
+        }
+        frame.render_widget(paragraph, area);
+    }
+
     fn active_input(&mut self) -> &mut CustomParagraph<TextInput> {
         match self.active_field_kind {
             ActiveFieldKind::Alias => &mut self.alias,
             ActiveFieldKind::Command => &mut self.cmd,
             ActiveFieldKind::Description => &mut self.description,
+            ActiveFieldKind::Tags => &mut self.tags,
+        }
+    }
+
+    /// Returns the tag being typed, if any
+    fn current_tag(&self) -> Option<&str> {
+        let tags = self.tags.inner().as_str();
+        match tags.ends_with(char::is_whitespace) {
+            true => None,
+            false => tags.split_whitespace().last(),
         }
     }
 
+    /// Updates the tag suggestions based on the tag being typed
+    fn refresh_tag_suggestions(&mut self) -> Result<()> {
+        let suggestions = match (&self.active_field_kind, self.current_tag()) {
+            (ActiveFieldKind::Tags, Some(current)) => {
+                let typed = self.tags.inner().as_str().split_whitespace().collect_vec();
+                self.storage
+                    .find_tags(current)?
+                    .into_iter()
+                    .map(|(tag, _)| tag)
+                    .filter(|tag| !typed.contains(&tag.as_str()))
+                    .take(MAX_TAG_SUGGESTIONS)
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        *self.tag_suggestions.inner_mut() = TagSuggestions(suggestions);
+        Ok(())
+    }
+
+    /// Replaces the tag being typed with the first suggestion, if any
+    fn complete_tag(&mut self) -> Result<bool> {
+        let Some(suggestion) = self.tag_suggestions.inner().0.first().cloned() else {
+            return Ok(false);
+        };
+        let mut tags = self.tags.inner().as_str().split_whitespace().collect_vec();
+        tags.pop();
+        tags.push(&suggestion);
+        let mut input = TextInput::new(format!("{} ", tags.join(" ")));
+        input.move_end();
+        *self.tags.inner_mut() = input;
+        self.refresh_tag_suggestions()?;
+        Ok(true)
+    }
+
     fn update_focus(&mut self) {
         self.alias.set_focus(false);
         self.cmd.set_focus(false);
         self.description.set_focus(false);
+        self.tags.set_focus(false);
 
         self.active_input().set_focus(true);
     }
@@ -108,7 +280,8 @@ impl<'s> EditCommandProcess<'s> {
             Some(self.alias.inner().as_str().to_owned())
         };
         self.command.cmd = self.cmd.inner().as_str().to_owned();
-        self.command.description = self.description.inner().as_str().to_owned();
+        let tags = self.tags.inner().as_str().split_whitespace().collect_vec();
+        self.command.description = update_tags(&self.command.description, self.description.inner().as_str(), &tags);
 
         // Insert / update
         Ok(if self.command.is_persisted() {
@@ -127,7 +300,11 @@ impl<'s> EditCommandProcess<'s> {
 
 impl<'s> Process for EditCommandProcess<'s> {
     fn min_height(&self) -> usize {
-        (self.alias.min_size().height + self.cmd.min_size().height + self.description.min_size().height) as usize
+        (self.alias.min_size().height
+            + self.cmd.min_size().height
+            + self.description.min_size().height
+            + self.tags.min_size().height
+            + self.tag_suggestions.min_size().height) as usize
     }
 
     fn peek(&mut self) -> Result<Option<ProcessOutput>> {
@@ -147,41 +324,75 @@ impl<'s> Process for EditCommandProcess<'s> {
                 Constraint::Length(self.alias.min_size().height),
                 Constraint::Length(self.cmd.min_size().height),
                 Constraint::Length(self.description.min_size().height),
+                Constraint::Length(self.tags.min_size().height),
+                Constraint::Length(self.tag_suggestions.min_size().height),
             ])
             .split(area);
 
         let alias_area = chunks[0];
         let command_area = chunks[1];
         let description_area = chunks[2];
+        let tags_area = chunks[3];
+        let tag_suggestions_area = chunks[4];
 
         // Render components
         self.alias.render_in(frame, alias_area, self.ctx.theme);
-        self.cmd.render_in(frame, command_area, self.ctx.theme);
+        if self.templating.is_some() {
+            self.render_templating(frame, command_area);
+        } else {
+            self.cmd.render_in(frame, command_area, self.ctx.theme);
+        }
         self.description.render_in(frame, description_area, self.ctx.theme);
+        self.tags.render_in(frame, tags_area, self.ctx.theme);
+        self.tag_suggestions
+            .render_in(frame, tag_suggestions_area, self.ctx.theme);
     }
 
     fn process_raw_event(&mut self, event: Event) -> Result<Option<ProcessOutput>> {
-        self.process_event(event)
+        if self.templating.is_some() {
+            self.process_templating_event(event);
+            return Ok(None);
+        }
+        match event {
+            // `ctrl + t` - Pick the parts of the command to be turned into labels
+            Event::Key(k)
+                if k.kind == KeyEventKind::Press
+                    && k.modifiers.contains(KeyModifiers::CONTROL)
+                    && k.code == KeyCode::Char('t') =>
+            {
+                self.start_templating();
+                Ok(None)
+            }
+            // `tab` - Complete the tag being typed, if there's any suggestion
+            Event::Key(k) if k.kind == KeyEventKind::Press && k.code == KeyCode::Tab && self.complete_tag()? => {
+                Ok(None)
+            }
+            event => self.process_event(event),
+        }
     }
 }
 
 impl<'s> InteractiveProcess for EditCommandProcess<'s> {
     fn move_up(&mut self) {
         self.active_field_kind = match self.active_field_kind {
-            ActiveFieldKind::Alias => ActiveFieldKind::Description,
+            ActiveFieldKind::Alias => ActiveFieldKind::Tags,
             ActiveFieldKind::Command => ActiveFieldKind::Alias,
             ActiveFieldKind::Description => ActiveFieldKind::Command,
+            ActiveFieldKind::Tags => ActiveFieldKind::Description,
         };
         self.update_focus();
+        *self.tag_suggestions.inner_mut() = TagSuggestions::default();
     }
 
     fn move_down(&mut self) {
         self.active_field_kind = match self.active_field_kind {
             ActiveFieldKind::Alias => ActiveFieldKind::Command,
             ActiveFieldKind::Command => ActiveFieldKind::Description,
-            ActiveFieldKind::Description => ActiveFieldKind::Alias,
+            ActiveFieldKind::Description => ActiveFieldKind::Tags,
+            ActiveFieldKind::Tags => ActiveFieldKind::Alias,
         };
         self.update_focus();
+        *self.tag_suggestions.inner_mut() = TagSuggestions::default();
     }
 
     fn move_left(&mut self) {
@@ -210,17 +421,17 @@ impl<'s> InteractiveProcess for EditCommandProcess<'s> {
 
     fn insert_text(&mut self, text: String) -> Result<()> {
         self.active_input().inner_mut().insert_text(text);
-        Ok(())
+        self.refresh_tag_suggestions()
     }
 
     fn insert_char(&mut self, c: char) -> Result<()> {
         self.active_input().inner_mut().insert_char(c);
-        Ok(())
+        self.refresh_tag_suggestions()
     }
 
     fn delete_char(&mut self, backspace: bool) -> Result<()> {
         self.active_input().inner_mut().delete_char(backspace);
-        Ok(())
+        self.refresh_tag_suggestions()
     }
 
     fn edit_current(&mut self) -> Result<()> {
@@ -231,6 +442,26 @@ impl<'s> InteractiveProcess for EditCommandProcess<'s> {
         Ok(())
     }
 
+    fn click(&mut self, column: u16, row: u16) -> Result<Option<ProcessOutput>> {
+        let clicked = if self.alias.click(column, row) {
+            Some(ActiveFieldKind::Alias)
+        } else if self.cmd.click(column, row) {
+            Some(ActiveFieldKind::Command)
+        } else if self.description.click(column, row) {
+            Some(ActiveFieldKind::Description)
+        } else if self.tags.click(column, row) {
+            Some(ActiveFieldKind::Tags)
+        } else {
+            None
+        };
+        if let Some(kind) = clicked {
+            self.active_field_kind = kind;
+            self.update_focus();
+            *self.tag_suggestions.inner_mut() = TagSuggestions::default();
+        }
+        Ok(None)
+    }
+
     fn accept_current(&mut self) -> Result<Option<ProcessOutput>> {
         if !self.cmd.inner().as_str().is_empty() && !self.description.inner().as_str().is_empty() {
             // Exit after saving the command
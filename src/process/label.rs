@@ -1,10 +1,11 @@
 use anyhow::{bail, Result};
-use crossterm::event::Event;
+use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers};
 use itertools::Itertools;
 use ratatui::{
     backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Modifier, Style},
+    widgets::Paragraph,
     Frame,
 };
 
@@ -16,8 +17,10 @@ use crate::{
         },
         ExecutionContext, InteractiveProcess,
     },
-    model::LabeledCommand,
-    storage::SqliteStorage,
+    model::{is_dangerous, LabeledCommand},
+    provider::{prefetch_provided_suggestions, provided_suggestions_for, refresh_provided_suggestions_for},
+    secret::SecretResolver,
+    storage::{DeletedLabelSuggestion, SqliteStorage},
     Process, ProcessOutput,
 };
 
@@ -33,17 +36,35 @@ pub struct LabelProcess<'s> {
     current_label: String,
     /// Suggestions for the current label
     suggestions: CustomStatefulList<LabelSuggestionItem>,
+    /// Whether the output must be kept out of the shell history
+    sensitive: bool,
+    /// Stored command being labeled, if any
+    command_id: Option<i64>,
+    /// Suggestions deleted while completing the command, along with their context, to be restored on undo
+    deleted: Vec<DeletedLabelSuggestion>,
+    /// Labels filled so far along with their values, to rank first the suggestions used together with them
+    filled: Vec<(String, String)>,
+    /// Whether the command was already confirmed, so it's not confirmed again when dangerous once filled
+    confirmed: bool,
+    /// Whether the filled command is dangerous and waiting to be confirmed
+    confirming: bool,
     // Execution context
     ctx: ExecutionContext,
 }
 
 impl<'s> LabelProcess<'s> {
-    pub fn new(storage: &'s SqliteStorage, command: LabeledCommand, ctx: ExecutionContext) -> Result<Self> {
+    pub fn new(storage: &'s SqliteStorage, mut command: LabeledCommand, ctx: ExecutionContext) -> Result<Self> {
+        if command.next_label().is_none() {
+            bail!("Command doesn't have labels");
+        }
+        // Dynamic labels are expanded right away, they might be the only ones
+        command.expand_dynamic_labels()?;
+        prefetch_provided_suggestions(storage, &command.root, &command.pending_labels())?;
         let (current_label_ix, current_label) = command
             .next_label()
-            .ok_or_else(|| anyhow::anyhow!("Command doesn't have labels"))?;
-        let current_label = current_label.to_owned();
-        let suggestions = Self::suggestion_items_for(storage, &command.root, &current_label, TextInput::default())?;
+            .map(|(ix, label)| (ix, label.to_owned()))
+            .unwrap_or_default();
+        let suggestions = Self::next_label_items(storage, &command, &[])?;
 
         let suggestions = CustomStatefulList::new(suggestions)
             .inline(ctx.inline)
@@ -56,6 +77,7 @@ impl<'s> LabelProcess<'s> {
             )
             .highlight_symbol(DEFAULT_HIGHLIGHT_SYMBOL_PREFIX);
 
+        let sensitive = command.has_secrets();
         let command = CustomParagraph::new(command)
             .inline(ctx.inline)
             .block_title("Command")
@@ -67,25 +89,139 @@ impl<'s> LabelProcess<'s> {
             current_label_ix,
             current_label,
             suggestions,
+            sensitive,
+            command_id: None,
+            deleted: Vec::new(),
+            filled: Vec::new(),
+            confirmed: false,
+            confirming: false,
             ctx,
         })
     }
 
+    /// Keeps the output out of the shell history when the command is sensitive, which is always the case when it
+    /// contains secret labels
+    pub fn with_sensitive(mut self, sensitive: bool) -> Self {
+        self.sensitive |= sensitive;
+        self
+    }
+
+    /// Sets the stored command being labeled, to be included on the output
+    pub fn with_command_id(mut self, command_id: Option<i64>) -> Self {
+        self.command_id = command_id;
+        self
+    }
+
+    /// Sets whether the command was already confirmed before filling its labels, like dangerous stored commands
+    pub fn with_confirmed(mut self, confirmed: bool) -> Self {
+        self.confirmed = confirmed;
+        self
+    }
+
+    /// Outputs the filled command, unless it's dangerous and it must be confirmed first, as the values of the labels
+    /// could make it dangerous
+    fn output_or_confirm(&mut self) -> Result<Option<ProcessOutput>> {
+        if !self.confirmed && is_dangerous(&self.command.inner().to_string(), "") {
+            self.confirmed = true;
+            self.confirming = true;
+            Ok(None)
+        } else {
+            Ok(Some(self.exit()?))
+        }
+    }
+
+    /// Builds the suggestions for the next label of the command, pre-filling its default value if any
+    fn next_label_items(
+        storage: &SqliteStorage,
+        command: &LabeledCommand,
+        context: &[(String, String)],
+    ) -> Result<Vec<LabelSuggestionItem>> {
+        let Some((_, spec)) = command.next_label_spec() else {
+            return Ok(Vec::new());
+        };
+        let mut suggestions =
+            Self::suggestion_items_for(storage, &command.root, spec.name, context, TextInput::default())?;
+        if let (Some(default), Some(LabelSuggestionItem::New(input) | LabelSuggestionItem::Secret(input))) =
+            (spec.default, suggestions.first_mut())
+        {
+            *input = TextInput::new(default);
+        }
+        Ok(suggestions)
+    }
+
+    /// Fetches again the suggestions of the providers for the current label, keeping the typed value
+    fn refresh_provided_suggestions(&mut self) -> Result<()> {
+        let root_cmd = &self.command.inner().root;
+        refresh_provided_suggestions_for(self.storage, root_cmd, &self.current_label)?;
+        let new_suggestion = match self.suggestions.items().first() {
+            Some(LabelSuggestionItem::New(input)) => input.clone(),
+            _ => TextInput::default(),
+        };
+        let suggestions = Self::suggestion_items_for(
+            self.storage,
+            root_cmd,
+            &self.current_label,
+            &self.filled,
+            new_suggestion,
+        )?;
+        self.suggestions.update_items(suggestions);
+        Ok(())
+    }
+
+    /// Restores the last suggestion deleted, keeping the typed value
+    fn undo_delete(&mut self) -> Result<()> {
+        if let Some(deleted) = self.deleted.pop() {
+            self.storage.restore_label_suggestion(&deleted)?;
+            let new_suggestion = match self.suggestions.items().first() {
+                Some(LabelSuggestionItem::New(input)) => input.clone(),
+                _ => TextInput::default(),
+            };
+            let root_cmd = &self.command.inner().root;
+            let suggestions = Self::suggestion_items_for(
+                self.storage,
+                root_cmd,
+                &self.current_label,
+                &self.filled,
+                new_suggestion,
+            )?;
+            self.suggestions.update_items(suggestions);
+        }
+        Ok(())
+    }
+
     fn suggestion_items_for(
         storage: &SqliteStorage,
         root_cmd: &str,
         label: &str,
+        context: &[(String, String)],
         new_suggestion: TextInput,
     ) -> Result<Vec<LabelSuggestionItem>> {
         if is_secret_label(label) {
-            Ok(vec![LabelSuggestionItem::Secret(new_suggestion)])
+            let mut suggestions = vec![LabelSuggestionItem::Secret(new_suggestion)];
+            suggestions.extend(
+                SecretResolver::enabled()
+                    .into_iter()
+                    .map(LabelSuggestionItem::SecretResolver),
+            );
+            Ok(suggestions)
         } else {
             let mut suggestions = storage
-                .find_suggestions_for(root_cmd, label)?
+                .find_suggestions_for(root_cmd, label, context)?
                 .into_iter()
                 .map(|s| LabelSuggestionItem::Persisted(s, None))
                 .collect_vec();
 
+            let mut provided_suggestions = provided_suggestions_for(storage, root_cmd, label)?
+                .into_iter()
+                .filter(|p| {
+                    !suggestions
+                        .iter()
+                        .any(|s| matches!(s, LabelSuggestionItem::Persisted(s, _) if &s.suggestion == p))
+                })
+                .map(LabelSuggestionItem::Label)
+                .collect_vec();
+            suggestions.append(&mut provided_suggestions);
+
             let mut suggestions_from_label = label
                 .split('|')
                 .map(|l| LabelSuggestionItem::Label(l.trim().to_owned()))
@@ -95,6 +231,7 @@ impl<'s> LabelProcess<'s> {
             if !new_suggestion.as_str().is_empty() {
                 suggestions.retain(|s| match s {
                     LabelSuggestionItem::Secret(_) => true,
+                    LabelSuggestionItem::SecretResolver(_) => true,
                     LabelSuggestionItem::New(_) => true,
                     LabelSuggestionItem::Label(l) => l.contains(new_suggestion.as_str()),
                     LabelSuggestionItem::Persisted(s, _) => s.suggestion.contains(new_suggestion.as_str()),
@@ -109,29 +246,80 @@ impl<'s> LabelProcess<'s> {
 
 impl<'s> Process for LabelProcess<'s> {
     fn min_height(&self) -> usize {
-        (self.suggestions.len() + 1).clamp(4, 15)
+        (self.suggestions.len() + 2).clamp(4, 15)
+    }
+
+    fn peek(&mut self) -> Result<Option<ProcessOutput>> {
+        // Every label might have been expanded already
+        if self.command.inner().next_label().is_none() && !self.confirming {
+            self.output_or_confirm()
+        } else {
+            Ok(None)
+        }
     }
 
     fn render<B: Backend>(&mut self, frame: &mut Frame<B>, area: Rect) {
-        // Prepare main layout
+        // Prepare main layout, with room for the description of the current label if any, or the dangerous warning
+        let description = if self.confirming {
+            Some("(warning) this command is dangerous, press enter again to confirm it".to_owned())
+        } else {
+            self.command
+                .inner()
+                .next_label_spec()
+                .and_then(|(_, spec)| spec.description)
+                .map(|d| format!("{}: {d}", self.current_label))
+        };
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(!self.ctx.inline as u16)
-            .constraints([Constraint::Length(self.command.min_size().height), Constraint::Min(1)])
+            .constraints([
+                Constraint::Length(self.command.min_size().height),
+                Constraint::Length(description.is_some() as u16),
+                Constraint::Min(1),
+            ])
             .split(area);
 
         let header = chunks[0];
-        let body = chunks[1];
+        let hint = chunks[1];
+        let body = chunks[2];
 
         // Display command
         self.command.render_in(frame, header, self.ctx.theme);
 
+        // Display the description of the label
+        if let Some(description) = description {
+            frame.render_widget(
+                Paragraph::new(description).style(Style::default().fg(self.ctx.theme.secondary)),
+                hint,
+            );
+        }
+
         // Display label suggestions
         self.suggestions.render_in(frame, body, self.ctx.theme);
     }
 
     fn process_raw_event(&mut self, event: Event) -> Result<Option<ProcessOutput>> {
-        self.process_event(event)
+        match event {
+            // `ctrl + r` - Refresh the provided suggestions
+            Event::Key(k)
+                if k.kind == KeyEventKind::Press
+                    && k.modifiers.contains(KeyModifiers::CONTROL)
+                    && k.code == KeyCode::Char('r') =>
+            {
+                self.refresh_provided_suggestions()?;
+                Ok(None)
+            }
+            // `ctrl + z` - Restore the last suggestion deleted
+            Event::Key(k)
+                if k.kind == KeyEventKind::Press
+                    && k.modifiers.contains(KeyModifiers::CONTROL)
+                    && k.code == KeyCode::Char('z') =>
+            {
+                self.undo_delete()?;
+                Ok(None)
+            }
+            event => self.process_event(event),
+        }
     }
 }
 
@@ -207,6 +395,7 @@ impl<'s> InteractiveProcess for LabelProcess<'s> {
                     self.storage,
                     &self.command.inner().root,
                     &self.current_label,
+                    &self.filled,
                     suggestion,
                 )?);
             }
@@ -228,6 +417,7 @@ impl<'s> InteractiveProcess for LabelProcess<'s> {
                     self.storage,
                     &self.command.inner().root,
                     &self.current_label,
+                    &self.filled,
                     suggestion,
                 )?);
             }
@@ -249,6 +439,7 @@ impl<'s> InteractiveProcess for LabelProcess<'s> {
                         self.storage,
                         &self.command.inner().root,
                         &self.current_label,
+                        &self.filled,
                         suggestion,
                     )?);
                 }
@@ -270,18 +461,47 @@ impl<'s> InteractiveProcess for LabelProcess<'s> {
     fn delete_current(&mut self) -> Result<()> {
         if let Some(LabelSuggestionItem::Persisted(_, None)) = self.suggestions.current() {
             if let Some(LabelSuggestionItem::Persisted(suggestion, _)) = self.suggestions.delete_current() {
-                self.storage.delete_label_suggestion(&suggestion)?;
+                if let Some(deleted) = self.storage.take_label_suggestion(&suggestion)? {
+                    self.deleted.push(deleted);
+                }
             }
         }
         Ok(())
     }
 
+    fn click(&mut self, column: u16, row: u16) -> Result<Option<ProcessOutput>> {
+        // The selection is kept while a suggestion is being edited
+        if let Some(LabelSuggestionItem::Persisted(_, Some(_))) = self.suggestions.current() {
+            return Ok(None);
+        }
+        if self.suggestions.click(column, row) == Some(true) {
+            self.accept_current()
+        } else {
+            Ok(None)
+        }
+    }
+
     fn accept_current(&mut self) -> Result<Option<ProcessOutput>> {
-        if let Some(suggestion) = self.suggestions.current_mut() {
-            match suggestion {
+        if self.confirming {
+            Ok(Some(self.exit()?))
+        } else if let Some(suggestion) = self.suggestions.current_mut() {
+            // Secrets are never recorded as the context of other labels
+            let filled_value = match suggestion {
                 LabelSuggestionItem::Secret(value) => {
-                    self.command.inner_mut().set_next_label(value.as_str());
+                    self.command.inner_mut().set_next_label(value.as_str())?;
+                    None
                 }
+                LabelSuggestionItem::SecretResolver(resolver) => match resolver.resolve(&self.current_label) {
+                    Some(value) => {
+                        self.command.inner_mut().set_next_label(value)?;
+                        None
+                    }
+                    None => {
+                        // The secret couldn't be fetched from this resolver, remove it to allow typing or another one
+                        self.suggestions.delete_current();
+                        return Ok(None);
+                    }
+                },
                 LabelSuggestionItem::New(value) => {
                     if !value.as_str().is_empty() {
                         let suggestion = self
@@ -290,40 +510,47 @@ impl<'s> InteractiveProcess for LabelProcess<'s> {
                             .new_suggestion_for(&self.current_label, value.as_str());
                         self.storage.insert_label_suggestion(&suggestion)?;
                     }
-                    self.command.inner_mut().set_next_label(value.as_str());
+                    self.command.inner_mut().set_next_label(value.as_str())?;
+                    Some(value.as_str().to_owned()).filter(|v| !v.is_empty())
                 }
                 LabelSuggestionItem::Label(value) => {
-                    self.command.inner_mut().set_next_label(value.clone());
+                    self.command.inner_mut().set_next_label(value.clone())?;
+                    Some(value.clone())
                 }
                 LabelSuggestionItem::Persisted(suggestion, input) => match input.take() {
                     None => {
                         suggestion.increment_usage();
-                        self.storage.update_label_suggestion_usage(suggestion)?;
-                        self.command.inner_mut().set_next_label(&suggestion.suggestion)
+                        self.storage.increment_label_suggestion_usage(suggestion)?;
+                        self.command.inner_mut().set_next_label(&suggestion.suggestion)?;
+                        Some(suggestion.suggestion.clone())
                     }
                     Some(value) => {
                         self.storage.update_label_suggestion(suggestion, value.as_str())?;
                         return Ok(None);
                     }
                 },
+            };
+            if let Some(value) = filled_value {
+                self.storage.record_label_context(
+                    &self.command.inner().root,
+                    &self.current_label,
+                    &value,
+                    &self.filled,
+                )?;
+                self.filled.push((self.current_label.clone(), value));
             }
             match self.command.inner().next_label() {
                 Some((ix, label)) => {
                     self.current_label_ix = ix;
                     self.current_label = label.to_owned();
 
-                    let suggestions = Self::suggestion_items_for(
-                        self.storage,
-                        &self.command.inner().root,
-                        label,
-                        TextInput::default(),
-                    )?;
+                    let suggestions = Self::next_label_items(self.storage, self.command.inner(), &self.filled)?;
                     self.suggestions.update_items(suggestions);
                     self.suggestions.reset_state();
 
                     Ok(None)
                 }
-                None => Ok(Some(ProcessOutput::output(self.command.inner().to_string()))),
+                None => self.output_or_confirm(),
             }
         } else {
             bail!("Expected at least one suggestion")
@@ -331,7 +558,9 @@ impl<'s> InteractiveProcess for LabelProcess<'s> {
     }
 
     fn exit(&mut self) -> Result<ProcessOutput> {
-        Ok(ProcessOutput::output(self.command.inner().to_string()))
+        Ok(ProcessOutput::output(self.command.inner().to_string())
+            .sensitive(self.sensitive)
+            .command_id(self.command_id))
     }
 }
 
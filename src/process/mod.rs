@@ -1,11 +1,17 @@
+mod alias;
+mod complete;
 mod edit;
 #[cfg(feature = "tldr")]
 mod fetch;
 mod label;
 mod search;
+mod suggest;
 
+pub use alias::*;
+pub use complete::*;
 pub use edit::*;
 #[cfg(feature = "tldr")]
 pub use fetch::*;
 pub use label::*;
 pub use search::*;
+pub use suggest::*;
@@ -1,24 +1,37 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
 use anyhow::Result;
-use crossterm::event::Event;
+use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind};
 use ratatui::{
     backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Modifier, Style},
+    text::Text,
+    widgets::{Borders, Paragraph, Wrap},
     Frame,
 };
 
 use super::{EditCommandProcess, LabelProcess};
 use crate::{
     common::{
+        current_project_dir, current_shell, flatten_str, project_dir_of,
         widget::{
-            CustomParagraph, CustomStatefulList, CustomStatefulWidget, CustomWidget, TextInput,
+            CommandPreview, CustomParagraph, CustomStatefulList, CustomStatefulWidget, CustomWidget, EmptySearchAction,
+            HealthBanner, HighlightedCommand, IntoWidget, RecordedCommand, SearchStatus, TextInput,
             DEFAULT_HIGHLIGHT_SYMBOL_PREFIX,
         },
         ExecutionContext, InteractiveProcess, Process,
     },
-    model::{AsLabeledCommand, Command},
-    storage::SqliteStorage,
-    ProcessOutput,
+    model::{
+        category_tree, is_within_category, AsLabeledCommand, CategoryNode, Command, ExecutionStats, Learning,
+        SearchMatch, SearchQuery,
+    },
+    storage::{DeletedCommand, SqliteStorage, TLDR_CATEGORIES, USER_CATEGORY},
+    EmptyQuery, HealthWarning, ProcessOutput,
 };
 
 /// Process to search for [Command]
@@ -27,8 +40,55 @@ pub struct SearchProcess<'s> {
     storage: &'s SqliteStorage,
     /// Current value of the filter box
     filter: CustomParagraph<TextInput>,
-    /// Command list of results
-    commands: CustomStatefulList<Command>,
+    /// Command list of results, highlighting the filter terms
+    commands: CustomStatefulList<HighlightedCommand>,
+    /// Maximum number of results loaded at once, the rest being loaded when scrolling past the last one
+    page_size: usize,
+    /// Results not loaded into the list yet
+    next_pages: Vec<HighlightedCommand>,
+    /// Actions available when there are no results
+    empty_actions: CustomStatefulList<EmptySearchAction>,
+    /// Wether the search is matching any of the terms instead of all of them
+    relaxed: bool,
+    /// Available categories
+    categories: Vec<String>,
+    /// Category the commands are restricted to, if any, including its nested categories
+    category: Option<String>,
+    /// Whether the commands are restricted to the ones fetched from tldr
+    tldr_only: bool,
+    /// Tree of categories being browsed, to pick the one to restrict the commands to
+    category_tree: Option<CustomStatefulList<CategoryNode>>,
+    /// Commands recently executed on the shell and not bookmarked yet, when browsing them
+    recorded: Option<Vec<String>>,
+    /// Recorded commands matching the filter
+    recorded_list: CustomStatefulList<RecordedCommand>,
+    /// Current shell, to hide commands restricted to other shells
+    shell: String,
+    /// Period of recent activity considered by the focus filter
+    focus_period: Duration,
+    /// Commands recently used on the current project, when the focus filter is enabled
+    focus: Option<Vec<Command>>,
+    /// Project the search is performed on, the current one unless overridden
+    project: Option<PathBuf>,
+    /// Commands pinned to the project, shown first
+    pinned: Vec<Command>,
+    /// Wether the preview of the selected command is shown
+    preview: bool,
+    /// Execution stats of the command last previewed, by its id, only loaded again when the selection changes
+    preview_stats: Option<(i64, Option<ExecutionStats>)>,
+    /// Wether the ranking of every command is explained
+    explain: bool,
+    /// Banner with the health warnings, until dismissed
+    banner: Option<CustomParagraph<HealthBanner>>,
+    /// Status of the last search
+    status: CustomParagraph<SearchStatus>,
+    /// Number of consecutive slow searches
+    slow_searches: u32,
+    /// Dangerous command selected to be accepted, waiting for confirmation
+    confirming: Option<i64>,
+    /// Commands deleted during this search, along with the previous state of the command they were merged into (if
+    /// any), to be restored on undo
+    deleted: Vec<(DeletedCommand, Option<Command>)>,
     /// Delegate label widget
     delegate_label: Option<LabelProcess<'s>>,
     /// Delegate edit widget
@@ -37,9 +97,44 @@ pub struct SearchProcess<'s> {
     ctx: ExecutionContext,
 }
 
+/// Time after which a search is considered slow
+const SLOW_SEARCH: Duration = Duration::from_millis(100);
+
+/// Number of consecutive slow searches after which a hint is shown
+const SLOW_SEARCHES_HINT: u32 = 3;
+
+/// Default number of results loaded at once
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Default period of recent activity considered by the focus filter
+pub const DEFAULT_FOCUS_PERIOD: Duration = Duration::from_secs(60 * 60 * 24 * 7);
+
 impl<'s> SearchProcess<'s> {
-    pub fn new(storage: &'s SqliteStorage, filter: String, ctx: ExecutionContext) -> Result<Self> {
-        let commands = storage.find_commands(&filter)?;
+    pub fn new(storage: &'s SqliteStorage, filter: String, page_size: usize, ctx: ExecutionContext) -> Result<Self> {
+        let page_size = page_size.max(1);
+        let shell = current_shell();
+        let project = current_project_dir();
+        let pinned = match &project {
+            Some(project) => storage.get_pinned_commands(project)?,
+            None => Vec::new(),
+        };
+        let start = Instant::now();
+        let commands = find_commands(storage, &filter, false, ctx.empty_query, None, &shell, None, &pinned)?;
+        let mut commands = pinned_first(commands, &pinned);
+        let next_pages = commands.split_off(page_size.min(commands.len()));
+        let status = CustomParagraph::new(SearchStatus {
+            results: commands.len() + next_pages.len(),
+            elapsed: start.elapsed(),
+            slow: false,
+            confirm: false,
+            undo: false,
+            read_only: false,
+        })
+        .inline(true);
+        let categories = category_tree(&storage.get_categories()?)
+            .into_iter()
+            .map(|node| node.path)
+            .collect();
 
         let filter = CustomParagraph::new(TextInput::new(filter))
             .inline(ctx.inline)
@@ -59,20 +154,410 @@ impl<'s> SearchProcess<'s> {
             )
             .highlight_symbol(DEFAULT_HIGHLIGHT_SYMBOL_PREFIX);
 
+        let empty_actions = CustomStatefulList::new(empty_actions_for(filter.inner().as_str(), false, false))
+            .inline(ctx.inline)
+            .block_title("No commands found")
+            .style(Style::default())
+            .highlight_style(
+                Style::default()
+                    .bg(ctx.theme.selected_background)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(DEFAULT_HIGHLIGHT_SYMBOL_PREFIX);
+
+        let recorded_list = CustomStatefulList::new(Vec::new())
+            .inline(ctx.inline)
+            .block_title("Recently executed")
+            .style(Style::default())
+            .highlight_style(
+                Style::default()
+                    .bg(ctx.theme.selected_background)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(DEFAULT_HIGHLIGHT_SYMBOL_PREFIX);
+
         Ok(Self {
             commands,
+            page_size,
+            next_pages,
+            empty_actions,
+            relaxed: false,
+            categories,
+            category: None,
+            tldr_only: false,
+            category_tree: None,
+            recorded: None,
+            recorded_list,
+            shell,
+            focus_period: DEFAULT_FOCUS_PERIOD,
+            focus: None,
+            project,
+            pinned,
+            preview: false,
+            preview_stats: None,
+            explain: false,
             filter,
             storage,
+            banner: None,
+            status,
+            slow_searches: 0,
+            confirming: None,
+            deleted: Vec::new(),
             delegate_label: None,
             delegate_edit: None,
             ctx,
         })
     }
 
-    fn exit_or_label_replace(&mut self, output: ProcessOutput) -> Result<Option<ProcessOutput>> {
+    /// Shows the given health warnings on a banner, until dismissed
+    pub fn with_warnings(mut self, warnings: Vec<HealthWarning>) -> Self {
+        self.banner = (!warnings.is_empty()).then(|| CustomParagraph::new(HealthBanner(warnings)));
+        self
+    }
+
+    /// Sets the period of recent activity considered by the focus filter
+    pub fn with_focus_period(mut self, focus_period: Duration) -> Self {
+        self.focus_period = focus_period;
+        self
+    }
+
+    /// Performs the search on the project of the given directory instead of the current one, for the pinned commands,
+    /// the focus filter and the usage recorded
+    pub fn with_project_dir(mut self, dir: impl AsRef<Path>) -> Result<Self> {
+        let project = project_dir_of(dir.as_ref());
+        self.pinned = self.storage.get_pinned_commands(&project)?;
+        self.project = Some(project);
+        self.refresh_commands()?;
+        Ok(self)
+    }
+
+    /// Wether the search didn't match any command, so the empty actions must be shown instead
+    fn is_empty_state(&self) -> bool {
+        self.commands.is_empty() && !self.filter.inner().as_str().trim().is_empty()
+    }
+
+    /// Loads the next page of results into the list
+    fn load_next_page(&mut self) {
+        let remaining = self.next_pages.split_off(self.page_size.min(self.next_pages.len()));
+        let page = std::mem::replace(&mut self.next_pages, remaining);
+        self.commands.append_items(page);
+    }
+
+    /// Queries the storage again with the current filter
+    fn refresh_commands(&mut self) -> Result<()> {
+        let filter = self.filter.inner().as_str();
+        let start = Instant::now();
+        let commands = find_commands(
+            self.storage,
+            filter,
+            self.relaxed,
+            self.ctx.empty_query,
+            self.category.as_deref(),
+            &self.shell,
+            self.focus.as_deref(),
+            &self.pinned,
+        )?
+        .into_iter()
+        .filter(|c| !self.tldr_only || TLDR_CATEGORIES.contains(&c.command.category.as_str()))
+        .collect();
+        let mut commands = pinned_first(commands, &self.pinned)
+            .into_iter()
+            .map(|c| c.with_explanation(self.explain))
+            .collect::<Vec<_>>();
+        let elapsed = start.elapsed();
+        if elapsed > SLOW_SEARCH {
+            self.slow_searches += 1;
+        } else {
+            self.slow_searches = 0;
+        }
+        *self.status.inner_mut() = SearchStatus {
+            results: commands.len(),
+            elapsed,
+            slow: self.slow_searches >= SLOW_SEARCHES_HINT,
+            confirm: false,
+            undo: false,
+            read_only: false,
+        };
+        self.confirming = None;
+        self.next_pages = commands.split_off(self.page_size.min(commands.len()));
+        self.commands.update_items(commands);
+        self.empty_actions
+            .update_items(empty_actions_for(filter, self.relaxed, self.can_search_tldr()));
+        self.empty_actions.reset_state();
+        self.refresh_recorded();
+        Ok(())
+    }
+
+    /// Determines if the tldr commands can be searched instead of the restricted ones, when fetched
+    fn can_search_tldr(&self) -> bool {
+        !self.tldr_only
+            && (self.category.is_some() || self.focus.is_some())
+            && self
+                .categories
+                .iter()
+                .any(|category| TLDR_CATEGORIES.contains(&category.as_str()))
+    }
+
+    /// Filters the recorded commands with the current filter, if browsing them
+    fn refresh_recorded(&mut self) {
+        if let Some(recorded) = &self.recorded {
+            let words = flatten_str(self.filter.inner().as_str());
+            let words = words.split_whitespace().collect::<Vec<_>>();
+            let matching = recorded
+                .iter()
+                .filter(|cmd| {
+                    let flat_cmd = flatten_str(cmd);
+                    words.iter().all(|word| flat_cmd.contains(word))
+                })
+                .map(|cmd| RecordedCommand(cmd.clone()))
+                .collect();
+            self.recorded_list.update_items(matching);
+            self.recorded_list.reset_state();
+        }
+    }
+
+    /// Toggles the commands recently executed on the shell from the current project, to bookmark them
+    fn toggle_recorded(&mut self) -> Result<()> {
+        self.recorded = match self.recorded {
+            None => Some(self.storage.get_recent_shell_commands(self.project.as_deref())?),
+            Some(_) => None,
+        };
+        self.update_filter_title();
+        self.refresh_recorded();
+        Ok(())
+    }
+
+    /// Restricts the commands to the next category, or removes the restriction after the last one
+    fn cycle_category(&mut self) -> Result<()> {
+        self.category = match &self.category {
+            None => self.categories.first().cloned(),
+            Some(current) => self.categories.iter().skip_while(|c| *c != current).nth(1).cloned(),
+        };
+        self.tldr_only = false;
+        self.update_filter_title();
+        self.commands.reset_state();
+        self.refresh_commands()
+    }
+
+    /// Opens the tree of categories to browse them, preselecting the current one
+    fn browse_categories(&mut self) -> Result<()> {
+        let tree = category_tree(&self.storage.get_categories()?);
+        let selected = self
+            .category
+            .as_ref()
+            .and_then(|current| tree.iter().position(|node| &node.path == current));
+        let mut list = CustomStatefulList::new(tree)
+            .inline(self.ctx.inline)
+            .block_title("Categories")
+            .style(Style::default())
+            .highlight_style(
+                Style::default()
+                    .bg(self.ctx.theme.selected_background)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(DEFAULT_HIGHLIGHT_SYMBOL_PREFIX);
+        for _ in 0..selected.unwrap_or_default() {
+            list.next();
+        }
+        self.category_tree = Some(list);
+        Ok(())
+    }
+
+    /// Processes the events while browsing the tree of categories, restricting the commands to the selected one
+    fn process_category_tree_event(&mut self, event: Event) -> Result<()> {
+        let Some(tree) = &mut self.category_tree else {
+            return Ok(());
+        };
+        if let Event::Mouse(mouse) = event {
+            match mouse.kind {
+                MouseEventKind::ScrollUp => tree.previous(),
+                MouseEventKind::ScrollDown => tree.next(),
+                MouseEventKind::Down(MouseButton::Left) if tree.click(mouse.column, mouse.row) == Some(true) => {
+                    self.restrict_to_tree_category()?
+                }
+                _ => (),
+            }
+            return Ok(());
+        }
+        let Event::Key(k) = event else {
+            return Ok(());
+        };
+        if k.kind != KeyEventKind::Press {
+            return Ok(());
+        }
+        let has_ctrl = k.modifiers.contains(KeyModifiers::CONTROL);
+        match k.code {
+            KeyCode::Up => tree.previous(),
+            KeyCode::Char('k') if has_ctrl => tree.previous(),
+            KeyCode::Down | KeyCode::Tab => tree.next(),
+            KeyCode::Char('j') if has_ctrl => tree.next(),
+            KeyCode::Home => tree.first(),
+            KeyCode::End => tree.last(),
+            KeyCode::Enter => self.restrict_to_tree_category()?,
+            KeyCode::Esc => self.category_tree = None,
+            KeyCode::Char('n') if has_ctrl => self.category_tree = None,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Restricts the commands to the category selected on the tree, closing it
+    fn restrict_to_tree_category(&mut self) -> Result<()> {
+        if let Some(tree) = self.category_tree.take() {
+            self.category = tree.current().map(|node| node.path.clone());
+            self.tldr_only = false;
+            self.update_filter_title();
+            self.commands.reset_state();
+            self.refresh_commands()?;
+        }
+        Ok(())
+    }
+
+    /// Toggles the focus filter, restricting the commands to the ones recently used on the current project
+    fn toggle_focus(&mut self) -> Result<()> {
+        self.focus = match (&self.focus, &self.project) {
+            (None, Some(project)) => Some(self.storage.get_commands_used_in(project, self.focus_period)?),
+            _ => None,
+        };
+        self.tldr_only = false;
+        self.update_filter_title();
+        self.commands.reset_state();
+        self.refresh_commands()
+    }
+
+    /// Restores the last command deleted, along with its pins, and reverts the command it was merged into
+    fn undo_delete(&mut self) -> Result<()> {
+        if let Some((deleted, merged_into)) = self.deleted.pop() {
+            if let Some(previous) = merged_into {
+                self.storage.update_command(&previous)?;
+            }
+            if self.storage.restore_command(&deleted)?.is_some() {
+                if let Some(project) = &self.project {
+                    self.pinned = self.storage.get_pinned_commands(project)?;
+                }
+            }
+            self.refresh_commands()?;
+        }
+        Ok(())
+    }
+
+    /// Pins the selected command to the current project, or unpins it if it was already pinned
+    fn toggle_pin(&mut self) -> Result<()> {
+        if let (Some(HighlightedCommand { command, .. }), Some(project)) = (self.commands.current(), &self.project) {
+            if !self.storage.unpin_command(command.id, project)? {
+                self.storage.pin_command(command.id, project)?;
+            }
+            self.pinned = self.storage.get_pinned_commands(project)?;
+            self.commands.reset_state();
+            self.refresh_commands()?;
+        }
+        Ok(())
+    }
+
+    /// Rewrites the selected command with its modern equivalent, if outdated.
+    ///
+    /// If the modern command was already stored, the outdated one is merged into it instead: its usage is added and its
+    /// alias kept if the modern one has none. The merge can be undone as a deletion.
+    fn update_outdated(&mut self) -> Result<()> {
+        let Some(current) = self.commands.current() else {
+            return Ok(());
+        };
+        if current.command.is_read_only() {
+            self.status.inner_mut().read_only = true;
+            return Ok(());
+        }
+        if let Some(replacement) = current.hint().and_then(|h| h.replacement.clone()) {
+            match self.storage.find_command(&replacement)? {
+                Some(existing) if existing.cmd == replacement => {
+                    let outdated = current.command.clone();
+                    let merged_into = if existing.is_read_only() {
+                        None
+                    } else {
+                        let mut merged = existing.clone();
+                        merged.usage += outdated.usage;
+                        merged.last_used = merged.last_used.max(outdated.last_used);
+                        merged.alias = merged.alias.or_else(|| outdated.alias.clone());
+                        self.storage.update_command(&merged)?;
+                        Some(existing)
+                    };
+                    if let Some(deleted) = self.storage.take_command(&outdated)? {
+                        self.deleted.push((deleted, merged_into));
+                        self.status.inner_mut().undo = true;
+                    }
+                }
+                _ => {
+                    let mut command = current.command.clone();
+                    command.cmd = replacement;
+                    self.storage.update_command(&command)?;
+                }
+            }
+            self.refresh_commands()?;
+        }
+        Ok(())
+    }
+
+    /// Updates the filter title to display the active restrictions
+    fn update_filter_title(&mut self) {
+        let mut chips = String::new();
+        if let Some(category) = &self.category {
+            chips.push_str(&format!(" [{category}]"));
+        }
+        if self.tldr_only {
+            chips.push_str(" [tldr]");
+        }
+        if self.focus.is_some() {
+            chips.push_str(" [focus]");
+        }
+        if self.recorded.is_some() {
+            chips.push_str(" [executed]");
+        }
+        self.filter.set_inline_title(format!("(filter){chips}"));
+        self.filter.set_block_title(format!("Filter{chips}"));
+    }
+
+    /// Runs the given empty action
+    fn run_empty_action(&mut self) -> Result<Option<ProcessOutput>> {
+        match self.empty_actions.current() {
+            Some(EmptySearchAction::Bookmark(query)) => {
+                let command = Command::new(USER_CATEGORY, query, "");
+                self.delegate_edit = Some(EditCommandProcess::new(self.storage, command, self.ctx)?);
+                Ok(None)
+            }
+            Some(EmptySearchAction::Relax) => {
+                self.relaxed = true;
+                self.refresh_commands()?;
+                Ok(None)
+            }
+            Some(EmptySearchAction::SearchTldr) => {
+                self.category = None;
+                self.focus = None;
+                self.tldr_only = true;
+                self.update_filter_title();
+                self.commands.reset_state();
+                self.refresh_commands()?;
+                Ok(None)
+            }
+            Some(EmptySearchAction::KeepQuery(query)) => {
+                // The query was typed by the user, so it's not confirmed even if dangerous
+                let query = query.clone();
+                self.exit_or_label_replace(ProcessOutput::output(query), true)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Outputs the given command or, if it has labels, delegates filling them. Once filled, dangerous commands must be
+    /// confirmed unless they were already confirmed or typed by the user.
+    fn exit_or_label_replace(&mut self, output: ProcessOutput, confirmed: bool) -> Result<Option<ProcessOutput>> {
         if let Some(cmd) = &output.output {
             if let Some(labeled_cmd) = cmd.as_labeled_command() {
-                let w = LabelProcess::new(self.storage, labeled_cmd, self.ctx)?;
+                let mut w = LabelProcess::new(self.storage, labeled_cmd, self.ctx)?
+                    .with_sensitive(output.sensitive)
+                    .with_command_id(output.command_id)
+                    .with_confirmed(confirmed);
+                if let Some(output) = w.peek()? {
+                    return Ok(Some(output));
+                }
                 self.delegate_label = Some(w);
                 return Ok(None);
             }
@@ -83,7 +568,7 @@ impl<'s> SearchProcess<'s> {
 
 impl<'s> Process for SearchProcess<'s> {
     fn min_height(&self) -> usize {
-        (self.commands.len() + 1).clamp(4, 15)
+        (self.commands.len() + 1).clamp(4, 15) + self.banner.is_some() as usize + 1
     }
 
     fn peek(&mut self) -> Result<Option<ProcessOutput>> {
@@ -94,14 +579,19 @@ impl<'s> Process for SearchProcess<'s> {
                     - Or execute 'intelli-shell fetch' to download a bunch of tldr's useful commands"# 
             };
             Ok(Some(ProcessOutput::message(message)))
-        } else if !self.filter.inner().as_str().is_empty() && self.commands.len() == 1 {
-            if let Some(command) = self.commands.current_mut() {
-                command.increment_usage();
-                self.storage.update_command(command)?;
-                let cmd = command.cmd.clone();
-                self.exit_or_label_replace(ProcessOutput::output(cmd))
-            } else {
-                Ok(None)
+        } else if !self.filter.inner().as_str().is_empty() && self.commands.len() == 1 && self.next_pages.is_empty() {
+            match self.commands.current_mut() {
+                // Dangerous commands are never accepted right away, they must be confirmed
+                Some(HighlightedCommand { command, .. }) if !command.is_dangerous() => {
+                    command.increment_usage();
+                    self.storage
+                        .increment_command_usage_in(command.id, self.project.as_deref())?;
+                    let output = ProcessOutput::output(command.prefixed_cmd())
+                        .sensitive(command.is_sensitive())
+                        .command_id(Some(command.id));
+                    self.exit_or_label_replace(output, false)
+                }
+                _ => Ok(None),
             }
         } else {
             Ok(None)
@@ -123,43 +613,205 @@ impl<'s> Process for SearchProcess<'s> {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(!self.ctx.inline as u16)
-            .constraints([Constraint::Length(self.filter.min_size().height), Constraint::Min(1)])
+            .constraints([
+                Constraint::Length(self.banner.is_some() as u16),
+                Constraint::Length(self.filter.min_size().height),
+                Constraint::Min(1),
+                Constraint::Length(1),
+            ])
             .split(area);
 
-        let header = chunks[0];
-        let body = chunks[1];
+        let banner = chunks[0];
+        let header = chunks[1];
+        let body = chunks[2];
+        let footer = chunks[3];
+
+        // Render the health warnings, if any
+        if let Some(b) = &self.banner {
+            b.render_in(frame, banner, self.ctx.theme);
+        }
 
         // Render filter
         self.filter.render_in(frame, header, self.ctx.theme);
 
-        // Render command list
-        self.commands.render_in(frame, body, self.ctx.theme);
+        // Render the category tree when browsing it, the command list or the available actions if there are no commands
+        if let Some(tree) = &mut self.category_tree {
+            tree.render_in(frame, body, self.ctx.theme);
+        } else if self.recorded.is_some() {
+            self.recorded_list.render_in(frame, body, self.ctx.theme);
+        } else if self.is_empty_state() {
+            self.empty_actions.render_in(frame, body, self.ctx.theme);
+        } else {
+            // Render the preview of the selected command, if enabled, next to the list
+            let storage = self.storage;
+            let preview_stats = &mut self.preview_stats;
+            let preview =
+                self.commands
+                    .current()
+                    .filter(|_| self.preview)
+                    .map(|HighlightedCommand { command, .. }| {
+                        let stats = match preview_stats {
+                            Some((id, stats)) if *id == command.id => *stats,
+                            _ => {
+                                // The stats are only informative, so they're skipped if they can't be retrieved
+                                let stats = storage.get_execution_stats(command.id).ok().flatten();
+                                *preview_stats = Some((command.id, stats));
+                                stats
+                            }
+                        };
+                        CommandPreview(command.clone(), stats)
+                    });
+            let body = match &preview {
+                Some(preview) => {
+                    let chunks = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+                        .split(body);
+                    let block = if self.ctx.inline {
+                        self.ctx.theme.block(Borders::LEFT)
+                    } else {
+                        self.ctx.theme.block(Borders::ALL).title(" Preview ")
+                    };
+                    let text: Text = preview.into_widget(self.ctx.theme);
+                    let paragraph = Paragraph::new(text).block(block).wrap(Wrap { trim: false });
+                    frame.render_widget(paragraph, chunks[1]);
+                    chunks[0]
+                }
+                None => body,
+            };
+            self.commands.render_in(frame, body, self.ctx.theme);
+        }
+
+        // Render the search status, warning about the dangerous command waiting for confirmation
+        let current = self.commands.current().map(|c| c.command.id);
+        self.status.inner_mut().confirm = self.confirming.is_some() && self.confirming == current;
+        self.status.render_in(frame, footer, self.ctx.theme);
     }
 
     fn process_raw_event(&mut self, event: Event) -> Result<Option<ProcessOutput>> {
         // If there's a delegate active, forward to it
         if let Some(delegate) = &mut self.delegate_label {
-            delegate.process_event(event)
+            delegate.process_raw_event(event)
         } else if let Some(delegate) = &mut self.delegate_edit {
-            if delegate.process_event(event)?.is_some() {
+            if delegate.process_raw_event(event)?.is_some() {
                 self.delegate_edit = None;
-                self.commands
-                    .update_items(self.storage.find_commands(self.filter.inner().as_str())?);
+                // Bookmarked commands are no longer listed as recorded
+                if self.recorded.is_some() {
+                    self.recorded = Some(self.storage.get_recent_shell_commands(self.project.as_deref())?);
+                }
+                self.refresh_commands()?;
             }
             Ok(None)
+        } else if self.category_tree.is_some() {
+            self.process_category_tree_event(event)?;
+            Ok(None)
         } else {
-            self.process_event(event)
+            match event {
+                // `ctrl + t` - Cycle category
+                Event::Key(k)
+                    if k.kind == KeyEventKind::Press
+                        && k.modifiers.contains(KeyModifiers::CONTROL)
+                        && k.code == KeyCode::Char('t') =>
+                {
+                    self.cycle_category()?;
+                    Ok(None)
+                }
+                // `ctrl + n` - Browse the tree of categories
+                Event::Key(k)
+                    if k.kind == KeyEventKind::Press
+                        && k.modifiers.contains(KeyModifiers::CONTROL)
+                        && k.code == KeyCode::Char('n') =>
+                {
+                    self.browse_categories()?;
+                    Ok(None)
+                }
+                // `ctrl + s` - Toggle the commands recently executed on the shell, to bookmark them
+                Event::Key(k)
+                    if k.kind == KeyEventKind::Press
+                        && k.modifiers.contains(KeyModifiers::CONTROL)
+                        && k.code == KeyCode::Char('s') =>
+                {
+                    self.toggle_recorded()?;
+                    Ok(None)
+                }
+                // `esc` - Go back from the recorded commands to the stored ones
+                Event::Key(k) if k.kind == KeyEventKind::Press && k.code == KeyCode::Esc && self.recorded.is_some() => {
+                    self.toggle_recorded()?;
+                    Ok(None)
+                }
+                // `ctrl + z` - Restore the last command deleted
+                Event::Key(k)
+                    if k.kind == KeyEventKind::Press
+                        && k.modifiers.contains(KeyModifiers::CONTROL)
+                        && k.code == KeyCode::Char('z') =>
+                {
+                    self.undo_delete()?;
+                    Ok(None)
+                }
+                // `ctrl + f` - Toggle focus on the commands recently used on the current project
+                Event::Key(k)
+                    if k.kind == KeyEventKind::Press
+                        && k.modifiers.contains(KeyModifiers::CONTROL)
+                        && k.code == KeyCode::Char('f') =>
+                {
+                    self.toggle_focus()?;
+                    Ok(None)
+                }
+                // `ctrl + p` - Pin or unpin the selected command to the current project
+                Event::Key(k)
+                    if k.kind == KeyEventKind::Press
+                        && k.modifiers.contains(KeyModifiers::CONTROL)
+                        && k.code == KeyCode::Char('p') =>
+                {
+                    self.toggle_pin()?;
+                    Ok(None)
+                }
+                // `ctrl + o` - Toggle the preview of the selected command
+                Event::Key(k)
+                    if k.kind == KeyEventKind::Press
+                        && k.modifiers.contains(KeyModifiers::CONTROL)
+                        && k.code == KeyCode::Char('o') =>
+                {
+                    self.preview = !self.preview;
+                    Ok(None)
+                }
+                // `ctrl + x` - Toggle the explanation of the ranking of every command
+                Event::Key(k)
+                    if k.kind == KeyEventKind::Press
+                        && k.modifiers.contains(KeyModifiers::CONTROL)
+                        && k.code == KeyCode::Char('x') =>
+                {
+                    self.explain = !self.explain;
+                    self.refresh_commands()?;
+                    Ok(None)
+                }
+                // `ctrl + g` - Update the selected command to its modern equivalent, if outdated
+                Event::Key(k)
+                    if k.kind == KeyEventKind::Press
+                        && k.modifiers.contains(KeyModifiers::CONTROL)
+                        && k.code == KeyCode::Char('g') =>
+                {
+                    self.update_outdated()?;
+                    Ok(None)
+                }
+                // `esc` - Dismiss the health warnings banner, if any
+                Event::Key(k) if k.kind == KeyEventKind::Press && k.code == KeyCode::Esc && self.banner.is_some() => {
+                    self.banner = None;
+                    Ok(None)
+                }
+                event => self.process_event(event),
+            }
         }
     }
 }
 
 impl<'s> InteractiveProcess for SearchProcess<'s> {
     fn move_up(&mut self) {
-        self.commands.previous()
+        self.prev()
     }
 
     fn move_down(&mut self) {
-        self.commands.next()
+        self.next()
     }
 
     fn move_left(&mut self) {
@@ -171,65 +823,152 @@ impl<'s> InteractiveProcess for SearchProcess<'s> {
     }
 
     fn prev(&mut self) {
-        self.commands.previous()
+        if self.recorded.is_some() {
+            self.recorded_list.previous()
+        } else if self.is_empty_state() {
+            self.empty_actions.previous()
+        } else {
+            // Wrapping around to the last result requires every page to be loaded
+            if self.commands.selected() == Some(0) && !self.next_pages.is_empty() {
+                self.commands.append_items(std::mem::take(&mut self.next_pages));
+            }
+            self.commands.previous()
+        }
     }
 
     fn next(&mut self) {
-        self.commands.next()
+        if self.recorded.is_some() {
+            self.recorded_list.next()
+        } else if self.is_empty_state() {
+            self.empty_actions.next()
+        } else {
+            if self.commands.selected() == Some(self.commands.len().saturating_sub(1)) && !self.next_pages.is_empty() {
+                self.load_next_page();
+            }
+            self.commands.next()
+        }
     }
 
     fn home(&mut self) {
-        self.commands.first()
+        if self.recorded.is_some() {
+            self.recorded_list.first()
+        } else if self.is_empty_state() {
+            self.empty_actions.first()
+        } else {
+            self.commands.first()
+        }
     }
 
     fn end(&mut self) {
-        self.commands.last()
+        if self.recorded.is_some() {
+            self.recorded_list.last()
+        } else if self.is_empty_state() {
+            self.empty_actions.last()
+        } else {
+            self.commands.append_items(std::mem::take(&mut self.next_pages));
+            self.commands.last()
+        }
     }
 
     fn insert_text(&mut self, text: String) -> Result<()> {
         self.filter.inner_mut().insert_text(text);
-        self.commands
-            .update_items(self.storage.find_commands(self.filter.inner().as_str())?);
-        Ok(())
+        self.relaxed = false;
+        self.refresh_commands()
     }
 
     fn insert_char(&mut self, c: char) -> Result<()> {
         self.filter.inner_mut().insert_char(c);
-        self.commands
-            .update_items(self.storage.find_commands(self.filter.inner().as_str())?);
-        Ok(())
+        self.relaxed = false;
+        self.refresh_commands()
     }
 
     fn delete_char(&mut self, backspace: bool) -> Result<()> {
         if self.filter.inner_mut().delete_char(backspace) {
-            self.commands
-                .update_items(self.storage.find_commands(self.filter.inner().as_str())?);
+            self.relaxed = false;
+            self.refresh_commands()?;
         }
         Ok(())
     }
 
     fn edit_current(&mut self) -> Result<()> {
-        if let Some(command) = self.commands.current() {
-            self.delegate_edit = Some(EditCommandProcess::new(self.storage, command.clone(), self.ctx)?);
+        if self.recorded.is_some() {
+            return Ok(());
+        }
+        match self.commands.current() {
+            Some(HighlightedCommand { command, .. }) if command.is_read_only() => {
+                self.status.inner_mut().read_only = true;
+            }
+            Some(HighlightedCommand { command, .. }) => {
+                self.delegate_edit = Some(EditCommandProcess::new(self.storage, command.clone(), self.ctx)?);
+            }
+            None => (),
         }
         Ok(())
     }
 
     fn delete_current(&mut self) -> Result<()> {
-        if let Some(command) = self.commands.delete_current() {
-            self.storage.delete_command(command.id)?;
+        if self.recorded.is_some() {
+            return Ok(());
+        }
+        if self.commands.current().is_some_and(|c| c.command.is_read_only()) {
+            self.status.inner_mut().read_only = true;
+            return Ok(());
+        }
+        if let Some(HighlightedCommand { command, .. }) = self.commands.delete_current() {
+            if let Some(deleted) = self.storage.take_command(&command)? {
+                self.deleted.push((deleted, None));
+                let status = self.status.inner_mut();
+                status.results = status.results.saturating_sub(1);
+                status.undo = true;
+            }
         }
         Ok(())
     }
 
+    fn click(&mut self, column: u16, row: u16) -> Result<Option<ProcessOutput>> {
+        if self.filter.click(column, row) {
+            return Ok(None);
+        }
+        let clicked = if self.recorded.is_some() {
+            self.recorded_list.click(column, row)
+        } else if self.is_empty_state() {
+            self.empty_actions.click(column, row)
+        } else {
+            self.commands.click(column, row)
+        };
+        if clicked == Some(true) {
+            self.accept_current()
+        } else {
+            Ok(None)
+        }
+    }
+
     fn accept_current(&mut self) -> Result<Option<ProcessOutput>> {
-        if let Some(command) = self.commands.current_mut() {
+        if self.recorded.is_some() {
+            // Recorded commands are bookmarked when accepted
+            if let Some(RecordedCommand(cmd)) = self.recorded_list.current() {
+                let command = Command::new(USER_CATEGORY, cmd, "");
+                self.delegate_edit = Some(EditCommandProcess::new(self.storage, command, self.ctx)?);
+            }
+            Ok(None)
+        } else if self.is_empty_state() {
+            self.run_empty_action()
+        } else if let Some(HighlightedCommand { command, .. }) = self.commands.current_mut() {
+            // Dangerous commands must be accepted twice in a row
+            if command.is_dangerous() && self.confirming != Some(command.id) {
+                self.confirming = Some(command.id);
+                return Ok(None);
+            }
             command.increment_usage();
-            self.storage.update_command(command)?;
-            let cmd = command.cmd.clone();
-            self.exit_or_label_replace(ProcessOutput::output(cmd))
+            self.storage
+                .increment_command_usage_in(command.id, self.project.as_deref())?;
+            let confirmed = command.is_dangerous();
+            let output = ProcessOutput::output(command.prefixed_cmd())
+                .sensitive(command.is_sensitive())
+                .command_id(Some(command.id));
+            self.exit_or_label_replace(output, confirmed)
         } else if !self.filter.inner().as_str().is_empty() {
-            self.exit_or_label_replace(ProcessOutput::output(self.filter.inner().as_str()))
+            self.exit_or_label_replace(ProcessOutput::output(self.filter.inner().as_str()), true)
         } else {
             Ok(Some(ProcessOutput::empty()))
         }
@@ -243,3 +982,94 @@ impl<'s> InteractiveProcess for SearchProcess<'s> {
         }
     }
 }
+
+/// Builds the actions available when the given filter doesn't match any command
+fn empty_actions_for(filter: &str, relaxed: bool, tldr: bool) -> Vec<EmptySearchAction> {
+    let filter = filter.trim();
+    if filter.is_empty() {
+        return Vec::new();
+    }
+    let mut actions = vec![EmptySearchAction::Bookmark(filter.to_owned())];
+    if !relaxed && filter.split_whitespace().nth(1).is_some() {
+        actions.push(EmptySearchAction::Relax);
+    }
+    // Without restrictions the tldr commands were already searched
+    if tldr {
+        actions.push(EmptySearchAction::SearchTldr);
+    }
+    actions.push(EmptySearchAction::KeepQuery(filter.to_owned()));
+    actions
+}
+
+/// Flags the pinned commands, moving them first while keeping the order of the rest
+fn pinned_first(commands: Vec<HighlightedCommand>, pinned: &[Command]) -> Vec<HighlightedCommand> {
+    let mut commands = commands
+        .into_iter()
+        .map(|c| {
+            let is_pinned = pinned.iter().any(|p| p.id == c.command.id);
+            c.with_pinned(is_pinned)
+        })
+        .collect::<Vec<_>>();
+    commands.sort_by_key(|c| !c.is_pinned());
+    commands
+}
+
+/// Finds the commands matching the given filter, or the ones configured to be shown when it's empty.
+///
+/// When focused, only the given commands are considered.
+#[allow(clippy::too_many_arguments)]
+fn find_commands(
+    storage: &SqliteStorage,
+    filter: &str,
+    relaxed: bool,
+    empty_query: EmptyQuery,
+    category: Option<&str>,
+    shell: &str,
+    focus: Option<&[Command]>,
+    pinned: &[Command],
+) -> Result<Vec<HighlightedCommand>> {
+    let recent = filter.trim().is_empty() && focus.is_none() && category.is_none() && empty_query == EmptyQuery::Recent;
+    let mut commands = if filter.trim().is_empty() {
+        match (focus, category, empty_query) {
+            (Some(focus), _, _) => Ok(focus.to_vec()),
+            (None, Some(category), _) => storage.get_commands_under(category),
+            (None, None, EmptyQuery::All) => storage.get_commands_under(USER_CATEGORY),
+            (None, None, EmptyQuery::Recent) => storage.get_recent_commands(),
+            (None, None, EmptyQuery::Favorites) => storage.get_most_used_commands(),
+            (None, None, EmptyQuery::Workspace) => Ok(pinned.to_vec()),
+            (None, None, EmptyQuery::Empty) => Ok(Vec::new()),
+        }?
+        .into_iter()
+        .map(|c| (c, SearchMatch::Listed))
+        .collect()
+    } else {
+        storage.find_ranked_commands(filter, relaxed)?
+    };
+    // The recent commands are already sorted by time
+    if !recent {
+        storage.sort_by_decayed_usage(&mut commands);
+    }
+    commands.retain(|(c, _)| {
+        let same_category = category
+            .map(|category| is_within_category(&c.category, category))
+            .unwrap_or(true);
+        let focused = focus.map(|focus| focus.iter().any(|f| f.id == c.id)).unwrap_or(true);
+        same_category && focused && c.is_compatible_with(shell)
+    });
+    let query = SearchQuery::parse(filter);
+    let terms = query.highlights();
+    let learning: HashMap<i64, Learning> = storage
+        .get_learning_commands()?
+        .into_iter()
+        .map(|(c, learning)| (c.id, learning))
+        .collect();
+    Ok(commands
+        .into_iter()
+        .map(|(c, search_match)| {
+            let learning = learning.get(&c.id).copied();
+            HighlightedCommand::new(c, &terms)
+                .with_learning(learning)
+                .with_search_match(search_match)
+        })
+        .collect())
+}
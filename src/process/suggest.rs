@@ -0,0 +1,47 @@
+use anyhow::Result;
+use crossterm::event::Event;
+use ratatui::{backend::Backend, layout::Rect, Frame};
+
+use crate::{common::current_shell, storage::SqliteStorage, Process, ProcessOutput};
+
+/// Process to suggest the top-ranked command for the current shell buffer, to be rendered as ghost text
+///
+/// This process will provide no UI, it will perform the job on `peek`
+pub struct SuggestInlineProcess<'a> {
+    /// Storage
+    storage: &'a SqliteStorage,
+    /// Current shell buffer
+    buffer: String,
+}
+
+impl<'a> SuggestInlineProcess<'a> {
+    pub fn new(storage: &'a SqliteStorage, buffer: String) -> Self {
+        Self { storage, buffer }
+    }
+}
+
+impl<'a> Process for SuggestInlineProcess<'a> {
+    fn min_height(&self) -> usize {
+        1
+    }
+
+    fn peek(&mut self) -> Result<Option<ProcessOutput>> {
+        if self.buffer.trim().is_empty() {
+            return Ok(Some(ProcessOutput::empty()));
+        }
+        Ok(Some(
+            match self.storage.find_command_by_prefix(&self.buffer, &current_shell())? {
+                Some(command) => ProcessOutput::output(command.cmd),
+                None => ProcessOutput::empty(),
+            },
+        ))
+    }
+
+    fn render<B: Backend>(&mut self, _frame: &mut Frame<B>, _area: Rect) {
+        unreachable!()
+    }
+
+    fn process_raw_event(&mut self, _event: Event) -> Result<Option<ProcessOutput>> {
+        unreachable!()
+    }
+}
@@ -0,0 +1,284 @@
+use std::{collections::HashMap, env, fs, process::Command, sync::Mutex, thread, time::Duration};
+
+use anyhow::{anyhow, Result};
+use directories::BaseDirs;
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+
+use crate::{
+    common::{flatten_str, parse_duration},
+    storage::SqliteStorage,
+};
+
+/// Environment variable listing the enabled providers, comma-separated
+const PROVIDERS_ENV: &str = "INTELLI_LABEL_PROVIDERS";
+
+/// Environment variable with the time the provided suggestions are kept on the database, like `10m`
+const PROVIDERS_TTL_ENV: &str = "INTELLI_LABEL_PROVIDERS_TTL";
+
+/// Suggestions already provided during this session, by provider, so they're not fetched again on every keystroke
+static SESSION_CACHE: Lazy<Mutex<HashMap<&'static str, Vec<String>>>> = Lazy::new(Default::default);
+
+/// Native provider of label suggestions
+trait LabelProvider: Sync {
+    /// Name of the provider, to be enabled
+    fn name(&self) -> &'static str;
+
+    /// Determines if this provider can suggest values for the given root command and label
+    fn provides(&self, flat_root_cmd: &str, flat_label: &str) -> bool;
+
+    /// Retrieves the suggested values
+    fn suggestions(&self) -> Vec<String>;
+}
+
+/// Provides hosts from the ssh config and known hosts files
+struct SshHostProvider;
+
+impl LabelProvider for SshHostProvider {
+    fn name(&self) -> &'static str {
+        "ssh-host"
+    }
+
+    fn provides(&self, _flat_root_cmd: &str, flat_label: &str) -> bool {
+        matches!(flat_label, "host" | "hostname" | "ssh-host")
+    }
+
+    fn suggestions(&self) -> Vec<String> {
+        let Some(dirs) = BaseDirs::new() else {
+            return Vec::new();
+        };
+        let ssh_dir = dirs.home_dir().join(".ssh");
+        let config = fs::read_to_string(ssh_dir.join("config")).unwrap_or_default();
+        let known_hosts = fs::read_to_string(ssh_dir.join("known_hosts")).unwrap_or_default();
+        parse_ssh_config_hosts(&config)
+            .into_iter()
+            .chain(parse_known_hosts(&known_hosts))
+            .unique()
+            .collect()
+    }
+}
+
+/// Provides the available docker contexts
+struct DockerContextProvider;
+
+impl LabelProvider for DockerContextProvider {
+    fn name(&self) -> &'static str {
+        "docker-context"
+    }
+
+    fn provides(&self, flat_root_cmd: &str, flat_label: &str) -> bool {
+        flat_label == "docker-context" || (flat_root_cmd == "docker" && flat_label == "context")
+    }
+
+    fn suggestions(&self) -> Vec<String> {
+        match Command::new("docker")
+            .args(["context", "ls", "--format", "{{.Name}}"])
+            .output()
+        {
+            Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(ToOwned::to_owned)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Every available provider
+const PROVIDERS: [&dyn LabelProvider; 2] = [&SshHostProvider, &DockerContextProvider];
+
+/// Retrieves the suggestions of every enabled provider for the given root command and label.
+///
+/// Suggestions are fetched once per session and, if `INTELLI_LABEL_PROVIDERS_TTL` is set, kept on the database to be
+/// reused by other sessions until they expire.
+pub fn provided_suggestions_for(storage: &SqliteStorage, root_cmd: &str, label: &str) -> Result<Vec<String>> {
+    let ttl = providers_ttl();
+    let mut session = SESSION_CACHE.lock().expect("poisoned lock");
+    let mut suggestions = Vec::new();
+    for provider in providers_for(root_cmd, label) {
+        if let Some(cached) = session.get(provider.name()) {
+            suggestions.extend(cached.iter().cloned());
+            continue;
+        }
+        let cached = match ttl {
+            Some(ttl) => storage.get_provider_cache(provider.name(), ttl)?,
+            None => None,
+        };
+        let provided = match cached {
+            Some(cached) => cached,
+            None => {
+                let provided = provider.suggestions();
+                if ttl.is_some() {
+                    storage.set_provider_cache(provider.name(), &provided)?;
+                }
+                provided
+            }
+        };
+        suggestions.extend(provided.iter().cloned());
+        session.insert(provider.name(), provided);
+    }
+    Ok(suggestions.into_iter().unique().collect())
+}
+
+/// Fetches the suggestions of every enabled provider for any of the given labels at once, running them concurrently
+/// instead of one by one as each label is reached
+pub fn prefetch_provided_suggestions(storage: &SqliteStorage, root_cmd: &str, labels: &[&str]) -> Result<()> {
+    let ttl = providers_ttl();
+    let mut pending = Vec::new();
+    let mut session = SESSION_CACHE.lock().expect("poisoned lock");
+    for provider in labels.iter().flat_map(|label| providers_for(root_cmd, label)) {
+        if session.contains_key(provider.name())
+            || pending.iter().any(|p: &&dyn LabelProvider| p.name() == provider.name())
+        {
+            continue;
+        }
+        let cached = match ttl {
+            Some(ttl) => storage.get_provider_cache(provider.name(), ttl)?,
+            None => None,
+        };
+        match cached {
+            Some(cached) => {
+                session.insert(provider.name(), cached);
+            }
+            None => pending.push(provider),
+        }
+    }
+    // The session cache is released while fetching, so a failing provider can't poison it
+    drop(session);
+
+    // Every thread is joined before checking the results, as the scope would panic on any left unjoined that failed
+    let provided = thread::scope(|scope| {
+        pending
+            .into_iter()
+            .map(|provider| (provider.name(), scope.spawn(move || provider.suggestions())))
+            .collect_vec()
+            .into_iter()
+            .map(|(name, handle)| (name, handle.join()))
+            .collect_vec()
+    });
+    let provided = provided
+        .into_iter()
+        .map(|(name, res)| match res {
+            Ok(suggestions) => Ok((name, suggestions)),
+            Err(_) => Err(anyhow!("The {name} label provider failed")),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut session = SESSION_CACHE.lock().expect("poisoned lock");
+    for (name, suggestions) in provided {
+        if ttl.is_some() {
+            storage.set_provider_cache(name, &suggestions)?;
+        }
+        session.insert(name, suggestions);
+    }
+    Ok(())
+}
+
+/// Discards the cached suggestions of every enabled provider for the given root command and label, so they're fetched
+/// again
+pub fn refresh_provided_suggestions_for(storage: &SqliteStorage, root_cmd: &str, label: &str) -> Result<()> {
+    let mut session = SESSION_CACHE.lock().expect("poisoned lock");
+    for provider in providers_for(root_cmd, label) {
+        session.remove(provider.name());
+        storage.clear_provider_cache(provider.name())?;
+    }
+    Ok(())
+}
+
+/// Retrieves the enabled providers for the given root command and label
+fn providers_for(root_cmd: &str, label: &str) -> Vec<&'static dyn LabelProvider> {
+    let enabled = env::var(PROVIDERS_ENV).unwrap_or_default();
+    let enabled = enabled.split(',').map(str::trim).collect_vec();
+    let flat_root_cmd = flatten_str(root_cmd);
+    let flat_label = flatten_str(label);
+
+    PROVIDERS
+        .into_iter()
+        .filter(|p| enabled.contains(&p.name()))
+        .filter(|p| p.provides(&flat_root_cmd, &flat_label))
+        .collect()
+}
+
+/// Retrieves the time the provided suggestions are kept on the database, if configured
+fn providers_ttl() -> Option<Duration> {
+    env::var(PROVIDERS_TTL_ENV)
+        .ok()
+        .and_then(|ttl| parse_duration(&ttl).ok())
+}
+
+/// Retrieves the error of the configured time to keep the provided suggestions, if invalid
+pub fn providers_ttl_error() -> Option<String> {
+    let ttl = env::var(PROVIDERS_TTL_ENV).ok()?;
+    parse_duration(&ttl).err().map(|err| err.to_string())
+}
+
+/// Retrieves the enabled providers that don't exist
+pub fn unknown_providers() -> Vec<String> {
+    env::var(PROVIDERS_ENV)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty() && !PROVIDERS.iter().any(|provider| provider.name() == *p))
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// Parses the host aliases of an ssh config file, skipping patterns
+fn parse_ssh_config_hosts(config: &str) -> Vec<String> {
+    config
+        .lines()
+        .map(str::trim)
+        .filter_map(|line| {
+            let (key, value) = line.split_once(char::is_whitespace)?;
+            key.eq_ignore_ascii_case("host").then_some(value)
+        })
+        .flat_map(str::split_whitespace)
+        .filter(|host| !host.contains(['*', '?', '!']))
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// Parses the hosts of a known hosts file, skipping hashed ones
+fn parse_known_hosts(known_hosts: &str) -> Vec<String> {
+    known_hosts
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('@'))
+        .filter_map(|line| line.split_whitespace().next())
+        .flat_map(|hosts| hosts.split(','))
+        .filter(|host| !host.starts_with('|'))
+        .map(|host| match host.strip_prefix('[').and_then(|h| h.split_once(']')) {
+            Some((host, _port)) => host.to_owned(),
+            None => host.to_owned(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ssh_hosts() {
+        let config = r#"
+            Host *
+                ServerAliveInterval 60
+            Host bastion jump
+                HostName 10.0.0.1
+            host !internal prod-?? staging
+        "#;
+        assert_eq!(parse_ssh_config_hosts(config), vec!["bastion", "jump", "staging"]);
+
+        let known_hosts = r#"
+            github.com,140.82.121.4 ssh-ed25519 AAAA
+            [gitlab.local]:2222 ssh-rsa AAAA
+            |1|JfKTdBh7rNbXkVAQCRp4OQoPfmI=|USECr3SWf1JUPsms5AqfD5QfxkM= ssh-rsa AAAA
+        "#;
+        assert_eq!(
+            parse_known_hosts(known_hosts),
+            vec!["github.com", "140.82.121.4", "gitlab.local"]
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use std::{env, fs, path::Path};
+
+use anyhow::{Context, Result};
+use directories::BaseDirs;
+use itertools::Itertools;
+
+use crate::{
+    common::unix_timestamp,
+    storage::{data_dir, SqliteStorage},
+};
+
+/// Name of the file where panics are recorded, within the data dir
+const PANICS_FILE: &str = "panics.log";
+
+/// Separator of the recorded panics
+const PANIC_HEADER: &str = "--- panic at ";
+
+/// Number of recent panics kept, to be included on the reports
+const REPORT_PANICS: usize = 10;
+
+/// Suffixes of the variables whose values must never be included on the reports
+const REDACTED_SUFFIXES: [&str; 4] = ["PASSPHRASE", "PASSWORD", "SECRET", "TOKEN"];
+
+/// Records a panic on the data dir, so it can be included on the next report.
+///
+/// Only the most recent ones are kept, so the file doesn't grow forever.
+pub fn record_panic(info: &str) -> Result<()> {
+    let dir = data_dir()?;
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(PANICS_FILE);
+    let mut panics = fs::read_to_string(&path).unwrap_or_default();
+    panics.push_str(&format!(
+        "{PANIC_HEADER}{} (v{})\n{info}\n",
+        unix_timestamp(),
+        env!("CARGO_PKG_VERSION")
+    ));
+    fs::write(path, recent_panics(&panics, REPORT_PANICS))?;
+    Ok(())
+}
+
+/// Writes a report to debug issues into the given file, as a tarball with the platform info, the configuration (with
+/// secrets and the home dir redacted), the database schema version, the debug log if any and the recent panics.
+///
+/// Nothing is sent anywhere, the user decides whether to attach it to an issue.
+pub fn write_report(storage: &SqliteStorage, file: impl AsRef<Path>) -> Result<()> {
+    let file = file.as_ref();
+    let mut entries = vec![
+        ("platform.txt", platform_info()),
+        ("config.txt", redacted_config()),
+        (
+            "database.txt",
+            format!(
+                "schema version: {}\nsqlite version: {}\nprofile: {}\n",
+                storage.schema_version()?,
+                rusqlite::version(),
+                storage.profile()
+            ),
+        ),
+    ];
+    // The debug log is written to the working dir when built with the `debug` feature
+    if let Ok(log) = fs::read_to_string("debug.log") {
+        entries.push(("debug.log", redact_home(&log)));
+    }
+    if let Ok(panics) = fs::read_to_string(data_dir()?.join(PANICS_FILE)) {
+        entries.push(("panics.log", redact_home(&recent_panics(&panics, REPORT_PANICS))));
+    }
+
+    let mut tarball = Vec::new();
+    for (name, content) in entries {
+        append_tar_entry(
+            &mut tarball,
+            &format!("intelli-shell-report/{name}"),
+            content.as_bytes(),
+        );
+    }
+    // The archive ends with two empty blocks
+    tarball.extend_from_slice(&[0; 1024]);
+    fs::write(file, tarball).with_context(|| format!("Error writing the report to {}", file.display()))
+}
+
+/// Retrieves the platform info
+fn platform_info() -> String {
+    let shell = env::var("SHELL").unwrap_or_default();
+    let term = env::var("TERM_PROGRAM")
+        .or_else(|_| env::var("TERM"))
+        .unwrap_or_default();
+    format!(
+        "version: {}\nos: {}\nfamily: {}\narch: {}\nshell: {shell}\nterminal: {term}\n",
+        env!("CARGO_PKG_VERSION"),
+        env::consts::OS,
+        env::consts::FAMILY,
+        env::consts::ARCH,
+    )
+}
+
+/// Retrieves the configuration variables, redacting their secrets
+fn redacted_config() -> String {
+    env::vars()
+        .filter(|(name, _)| name.starts_with("INTELLI_"))
+        .sorted()
+        .map(|(name, value)| {
+            let value = if REDACTED_SUFFIXES.iter().any(|s| name.ends_with(s)) {
+                "<redacted>".to_owned()
+            } else {
+                redact_home(&value)
+            };
+            format!("{name}={value}\n")
+        })
+        .collect()
+}
+
+/// Replaces the home dir of the user with `~`, as it usually contains the user name
+fn redact_home(text: &str) -> String {
+    match BaseDirs::new().and_then(|dirs| dirs.home_dir().to_str().map(ToOwned::to_owned)) {
+        Some(home) if home.len() > 1 => text.replace(&home, "~"),
+        _ => text.to_owned(),
+    }
+}
+
+/// Retrieves the last recorded panics
+fn recent_panics(panics: &str, count: usize) -> String {
+    let starts = panics.match_indices(PANIC_HEADER).map(|(ix, _)| ix).collect::<Vec<_>>();
+    let from = starts.len().saturating_sub(count);
+    starts.get(from).map(|ix| panics[*ix..].to_owned()).unwrap_or_default()
+}
+
+/// Appends a regular file to a tarball, on the ustar format
+fn append_tar_entry(tarball: &mut Vec<u8>, name: &str, content: &[u8]) {
+    let mut header = [0u8; 512];
+    let mut field = |offset: usize, value: &[u8]| header[offset..offset + value.len()].copy_from_slice(value);
+    field(0, &name.as_bytes()[..name.len().min(100)]);
+    field(100, b"0000644\0");
+    field(108, b"0000000\0");
+    field(116, b"0000000\0");
+    field(124, format!("{:011o}\0", content.len()).as_bytes());
+    field(136, format!("{:011o}\0", unix_timestamp()).as_bytes());
+    field(156, b"0");
+    field(257, b"ustar\0");
+    field(263, b"00");
+    // The checksum is computed with its own field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum: u32 = header.iter().map(|b| *b as u32).sum();
+    header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+
+    tarball.extend_from_slice(&header);
+    tarball.extend_from_slice(content);
+    // Content is padded up to a full block
+    tarball.resize(tarball.len().next_multiple_of(512), 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_parts() {
+        let mut tarball = Vec::new();
+        append_tar_entry(&mut tarball, "report/info.txt", b"hello");
+        assert_eq!(tarball.len(), 1024);
+        assert_eq!(&tarball[..15], b"report/info.txt");
+        assert_eq!(&tarball[124..136], b"00000000005\0");
+        assert_eq!(&tarball[512..517], b"hello");
+        let checksum = u32::from_str_radix(std::str::from_utf8(&tarball[148..154]).unwrap(), 8).unwrap();
+        let expected: u32 = tarball[..512]
+            .iter()
+            .enumerate()
+            .map(|(ix, b)| {
+                if (148..156).contains(&ix) {
+                    b' ' as u32
+                } else {
+                    *b as u32
+                }
+            })
+            .sum();
+        assert_eq!(checksum, expected);
+
+        let panics = "--- panic at 1 (v0.1)\nfirst\n--- panic at 2 (v0.1)\nsecond\n--- panic at 3 (v0.1)\nthird\n";
+        assert_eq!(
+            recent_panics(panics, 2),
+            "--- panic at 2 (v0.1)\nsecond\n--- panic at 3 (v0.1)\nthird\n"
+        );
+        assert_eq!(recent_panics("", 2), "");
+    }
+}
@@ -0,0 +1,120 @@
+use std::{env, fmt, process::Command, str::FromStr};
+
+use anyhow::bail;
+use itertools::Itertools;
+
+/// Environment variable listing the enabled secret resolvers, comma-separated
+const RESOLVERS_ENV: &str = "INTELLI_SECRET_RESOLVERS";
+
+/// Resolver of secret label values from an external store, values are never persisted
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum SecretResolver {
+    /// Environment variable named after the label, like `MY_SECRET` for `{{*my-secret*}}`
+    Env,
+    /// [pass](https://www.passwordstore.org/) entry named after the label
+    Pass,
+    /// 1Password CLI item named after the label
+    OnePassword,
+    /// Bitwarden CLI item named after the label
+    Bitwarden,
+}
+
+impl SecretResolver {
+    /// Retrieves the enabled resolvers, in the configured order
+    pub fn enabled() -> Vec<SecretResolver> {
+        env::var(RESOLVERS_ENV)
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .filter_map(|r| r.parse().ok())
+            .unique()
+            .collect()
+    }
+
+    /// Retrieves the enabled resolvers that don't exist
+    pub fn unknown() -> Vec<String> {
+        env::var(RESOLVERS_ENV)
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|r| !r.is_empty() && r.parse::<SecretResolver>().is_err())
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+
+    /// Resolves the value of the given secret label, if found
+    pub fn resolve(&self, label: &str) -> Option<String> {
+        let name = label.trim_matches('*').trim();
+        match self {
+            SecretResolver::Env => env::var(env_var_name(name)).ok().filter(|v| !v.is_empty()),
+            SecretResolver::Pass => run_first_line("pass", &["show", name]),
+            SecretResolver::OnePassword => {
+                run_first_line("op", &["item", "get", name, "--fields", "password", "--reveal"])
+            }
+            SecretResolver::Bitwarden => run_first_line("bw", &["get", "password", name]),
+        }
+    }
+}
+
+impl FromStr for SecretResolver {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "env" => Ok(SecretResolver::Env),
+            "pass" => Ok(SecretResolver::Pass),
+            "op" => Ok(SecretResolver::OnePassword),
+            "bw" => Ok(SecretResolver::Bitwarden),
+            _ => bail!("Unknown secret resolver '{s}', expected one of: env, pass, op, bw"),
+        }
+    }
+}
+
+impl fmt::Display for SecretResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretResolver::Env => write!(f, "env"),
+            SecretResolver::Pass => write!(f, "pass"),
+            SecretResolver::OnePassword => write!(f, "op"),
+            SecretResolver::Bitwarden => write!(f, "bw"),
+        }
+    }
+}
+
+/// Builds the environment variable name for a secret label
+fn env_var_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Runs the given program, returning the first line of its output when successful
+fn run_first_line(program: &str, args: &[&str]) -> Option<String> {
+    match Command::new(program).args(args).output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .next()
+            .map(|l| l.trim_end().to_owned())
+            .filter(|l| !l.is_empty()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_var_name() {
+        assert_eq!(env_var_name("github-token"), "GITHUB_TOKEN");
+        assert_eq!(env_var_name("my secret.key"), "MY_SECRET_KEY");
+    }
+}
@@ -0,0 +1,389 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{BufRead, BufReader, Read, Take, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    model::{AsLabeledCommand, Command},
+    storage::{SqliteStorage, USER_CATEGORY},
+    ErrorKind,
+};
+
+/// Default port of the local server
+pub const DEFAULT_SERVER_PORT: u16 = 9898;
+
+/// Maximum size of the request bodies accepted
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// Maximum size of the request line and headers accepted, so a client can't make the server use unbounded memory
+const MAX_HEAD_SIZE: u64 = 16 * 1024;
+
+/// Maximum time to wait for a client, so an idle connection can't block the server
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serves a local REST API over the given storage, so other tools can integrate with it.
+///
+/// The server only listens on the loopback interface and handles the requests sequentially:
+/// - `GET /commands?query=<text>`: searches for commands within the scope, returning the user ones when there's no
+///   query (or every one within the scope, when it includes any selector)
+/// - `POST /commands`: stores a new user command, from a `{"command", "description", "alias"}` object
+/// - `POST /execute-template`: replaces the labels of a command, from a `{"command", "values"}` object
+///
+/// Every request must be authenticated with an `Authorization: Bearer <token>` header and target the loopback host
+/// (`127.0.0.1:<port>` or `localhost:<port>`), so other sites can't reach it through DNS rebinding. Requests with a
+/// body must also be sent as `application/json`, so browsers can't forge them without a CORS preflight, which is never
+/// allowed.
+///
+/// The scope restricts the commands exposed, with selectors like `#team` or `cloud/aws` to include, or prefixed with
+/// `!` to exclude. Commands with secrets are never exposed.
+pub fn serve(
+    storage: &SqliteStorage,
+    port: u16,
+    token: &str,
+    scope: &[String],
+    mut on_ready: impl FnMut(u16),
+) -> Result<()> {
+    let listener =
+        TcpListener::bind((Ipv4Addr::LOCALHOST, port)).with_context(|| format!("Couldn't bind port {port}"))?;
+    let port = listener.local_addr()?.port();
+    on_ready(port);
+    let hosts = [format!("127.0.0.1:{port}"), format!("localhost:{port}")];
+    // A broken connection shouldn't stop the server
+    for stream in listener.incoming().flatten() {
+        let _ = handle_connection(storage, &hosts, token, scope, stream);
+    }
+    Ok(())
+}
+
+/// Request received by the server
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    host: Option<String>,
+    authorization: Option<String>,
+    content_type: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Response to be sent by the server
+struct Response {
+    status: u16,
+    body: Value,
+}
+
+impl Response {
+    fn new(status: u16, body: Value) -> Self {
+        Self { status, body }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::new(status, json!({ "error": message.into() }))
+    }
+}
+
+/// Error reading a request whose line and headers exceed [MAX_HEAD_SIZE]
+#[derive(Debug)]
+struct HeadTooLarge;
+
+impl fmt::Display for HeadTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The request headers are too large")
+    }
+}
+
+impl std::error::Error for HeadTooLarge {}
+
+#[derive(Deserialize)]
+struct NewCommand {
+    command: String,
+    #[serde(default)]
+    description: String,
+    alias: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Template {
+    command: String,
+    #[serde(default)]
+    values: HashMap<String, String>,
+}
+
+/// Reads a request from the connection and writes back its response
+fn handle_connection(
+    storage: &SqliteStorage,
+    hosts: &[String],
+    token: &str,
+    scope: &[String],
+    stream: TcpStream,
+) -> Result<()> {
+    stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let response = match read_request(&mut reader) {
+        Ok(request) if !request.host.as_ref().is_some_and(|h| hosts.iter().any(|a| a == h)) => {
+            Response::error(403, "Invalid host")
+        }
+        Ok(request) if !request.authorization.as_deref().is_some_and(|a| is_token(a, token)) => {
+            Response::error(401, "Missing or invalid token")
+        }
+        Ok(request) => route(storage, scope, request).unwrap_or_else(|err| {
+            let status = match ErrorKind::of(&err) {
+                ErrorKind::NotFound => 404,
+                ErrorKind::Parse => 400,
+                _ => 500,
+            };
+            Response::error(status, format!("{err:#}"))
+        }),
+        Err(err) if err.is::<HeadTooLarge>() => Response::error(431, format!("{err:#}")),
+        Err(err) => Response::error(400, format!("{err:#}")),
+    };
+    write_response(&stream, response)
+}
+
+/// Checks if the given `Authorization` header value is a bearer one with the expected token
+fn is_token(authorization: &str, token: &str) -> bool {
+    let Some((scheme, value)) = authorization.split_once(' ') else {
+        return false;
+    };
+    let value = value.trim();
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return false;
+    }
+    // Compare every byte, so the time taken doesn't leak how much of the token matched
+    value.len() == token.len() && value.bytes().zip(token.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Dispatches the request to the handler of its route
+fn route(storage: &SqliteStorage, scope: &[String], request: Request) -> Result<Response> {
+    let json_body = request
+        .content_type
+        .as_deref()
+        .is_some_and(|c| c.starts_with("application/json"));
+    match (request.method.as_str(), request.path.as_str()) {
+        (_, "/commands" | "/execute-template") if request.method == "POST" && !json_body => {
+            Ok(Response::error(415, "Expected an application/json body"))
+        }
+        ("GET", "/commands") => {
+            let query = request.query.get("query").map(String::as_str).unwrap_or_default();
+            let commands = storage.find_exposed_commands(query, scope)?;
+            Ok(Response::new(200, commands.iter().map(command_json).collect()))
+        }
+        ("POST", "/commands") => {
+            let new: NewCommand = match serde_json::from_slice(&request.body) {
+                Ok(new) => new,
+                Err(err) => return Ok(Response::error(400, format!("Invalid command: {err}"))),
+            };
+            if new.command.trim().is_empty() {
+                return Ok(Response::error(400, "The command can't be empty"));
+            }
+            let mut command = Command {
+                alias: new.alias.filter(|a| !a.trim().is_empty()),
+                ..Command::new(USER_CATEGORY, new.command.trim(), new.description.trim())
+            };
+            let status = if storage.insert_command(&mut command)? {
+                201
+            } else {
+                200
+            };
+            Ok(Response::new(status, command_json(&command)))
+        }
+        ("POST", "/execute-template") => {
+            let template: Template = match serde_json::from_slice(&request.body) {
+                Ok(template) => template,
+                Err(err) => return Ok(Response::error(400, format!("Invalid template: {err}"))),
+            };
+            let command = match template.command.as_labeled_command() {
+                Some(labeled_command) => match labeled_command.fill(&template.values) {
+                    Ok(command) => command,
+                    Err(err) => return Ok(Response::error(400, err.to_string())),
+                },
+                None => template.command,
+            };
+            Ok(Response::new(200, json!({ "command": command })))
+        }
+        (_, "/commands" | "/execute-template") => Ok(Response::error(405, "Method not allowed")),
+        _ => Ok(Response::error(404, "Not found")),
+    }
+}
+
+/// Builds the JSON value of a command returned by the server
+fn command_json(command: &Command) -> Value {
+    json!({
+        "id": command.id,
+        "category": command.category,
+        "alias": command.alias,
+        "command": command.cmd,
+        "description": command.description,
+        "usage": command.usage,
+    })
+}
+
+/// Reads an HTTP/1.1 request
+fn read_request(reader: &mut impl BufRead) -> Result<Request> {
+    let mut head = reader.by_ref().take(MAX_HEAD_SIZE);
+    let mut line = String::new();
+    read_head_line(&mut head, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        bail!("Malformed request line");
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_owned(),
+        path: path.to_owned(),
+        query: parse_query(query),
+        host: None,
+        authorization: None,
+        content_type: None,
+        body: Vec::new(),
+    };
+
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        read_head_line(&mut head, &mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse().context("Invalid content length")?;
+            } else if name.eq_ignore_ascii_case("content-type") {
+                request.content_type = Some(value.to_lowercase());
+            } else if name.eq_ignore_ascii_case("host") {
+                request.host = Some(value.to_lowercase());
+            } else if name.eq_ignore_ascii_case("authorization") {
+                request.authorization = Some(value.to_owned());
+            }
+        }
+    }
+    if content_length > MAX_BODY_SIZE {
+        bail!("The body is too large");
+    }
+    request.body = vec![0; content_length];
+    reader.read_exact(&mut request.body)?;
+    Ok(request)
+}
+
+/// Reads a line of the request head, failing with [HeadTooLarge] when it doesn't end within the size limit
+fn read_head_line(head: &mut Take<impl BufRead>, line: &mut String) -> Result<()> {
+    let read = head.read_line(line);
+    if head.limit() == 0 && !line.ends_with('\n') {
+        return Err(HeadTooLarge.into());
+    }
+    read?;
+    Ok(())
+}
+
+/// Writes an HTTP/1.1 response, closing the connection afterwards
+fn write_response(mut stream: &TcpStream, response: Response) -> Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        415 => "Unsupported Media Type",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    };
+    let body = response.body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: \
+         close\r\n\r\n{body}",
+        response.status,
+        body.len()
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Parses an url query string, decoding its keys and values
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+/// Decodes a percent-encoded url component, where `+` stands for a space
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut ix = 0;
+    while ix < bytes.len() {
+        match bytes[ix] {
+            b'+' => decoded.push(b' '),
+            b'%' => match text
+                .get(ix + 1..ix + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                Some(byte) => {
+                    decoded.push(byte);
+                    ix += 2;
+                }
+                None => decoded.push(b'%'),
+            },
+            b => decoded.push(b),
+        }
+        ix += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_request() {
+        let raw = "POST /commands?query=git+log%20-n&flag HTTP/1.1\r\nHost: localhost\r\nContent-Type: \
+                   application/json\r\ncontent-length: 2\r\n\r\n{}";
+        let request = read_request(&mut raw.as_bytes()).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/commands");
+        assert_eq!(request.query.get("query").map(String::as_str), Some("git log -n"));
+        assert_eq!(request.query.get("flag").map(String::as_str), Some(""));
+        assert_eq!(request.host.as_deref(), Some("localhost"));
+        assert_eq!(request.content_type.as_deref(), Some("application/json"));
+        assert_eq!(request.body, b"{}");
+
+        let long_header = format!(
+            "GET /commands HTTP/1.1\r\nHost: localhost\r\nX-Long: {}",
+            "a".repeat(20 * 1024)
+        );
+        let err = read_request(&mut long_header.as_bytes()).err().unwrap();
+        assert!(err.is::<HeadTooLarge>());
+        let many_headers = format!("GET /commands HTTP/1.1\r\n{}\r\n", "X-Header: a\r\n".repeat(2 * 1024));
+        let err = read_request(&mut many_headers.as_bytes()).err().unwrap();
+        assert!(err.is::<HeadTooLarge>());
+        let long_line = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(20 * 1024));
+        let err = read_request(&mut long_line.as_bytes()).err().unwrap();
+        assert!(err.is::<HeadTooLarge>());
+
+        assert!(is_token("Bearer s3cr3t", "s3cr3t"));
+        assert!(is_token("bearer  s3cr3t ", "s3cr3t"));
+        assert!(!is_token("Bearer s3cr3", "s3cr3t"));
+        assert!(!is_token("Basic s3cr3t", "s3cr3t"));
+        assert!(!is_token("s3cr3t", "s3cr3t"));
+
+        assert_eq!(percent_decode("100%25%zz%e2%9c%93"), "100%%zz✓");
+    }
+}
@@ -0,0 +1,196 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Regex to match the variables of navi cheats, like `<branch>`
+static NAVI_VARIABLE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"<([a-zA-Z0-9_][\w-]*)>").unwrap());
+
+/// Regex to match the parameters of pet snippets, with an optional default value, like `<host=localhost>`
+static PET_PARAMETER_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"<([a-zA-Z0-9_][\w-]*)(?:=([^<>]*))?>").unwrap());
+
+/// Regex to match the TOML strings, either basic (double-quoted) or literal (single-quoted) ones
+static TOML_STRING_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#""((?:[^"\\]|\\.)*)"|'([^']*)'"#).unwrap());
+
+/// Snippet formats of other tools that can be imported
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum SnippetFormat {
+    /// navi `.cheat` files
+    Navi,
+    /// pet `snippet.toml` files
+    Pet,
+}
+
+impl SnippetFormat {
+    /// Detects the format of the given file from its extension and content, if it's not the text format
+    pub fn detect(path: impl AsRef<Path>, content: &str) -> Option<Self> {
+        let extension = path.as_ref().extension().and_then(|e| e.to_str()).unwrap_or_default();
+        let first_line = content
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty() && !l.starts_with(';'));
+        if extension == "cheat" || first_line.is_some_and(|l| l.starts_with('%')) {
+            Some(Self::Navi)
+        } else if content.lines().any(|l| l.trim() == "[[snippets]]") {
+            Some(Self::Pet)
+        } else {
+            None
+        }
+    }
+
+    /// Parses the commands of the given content along with their description, with their placeholders turned into
+    /// labels and their tags into hashtags
+    pub fn parse(self, content: &str) -> Result<Vec<(String, String)>> {
+        match self {
+            Self::Navi => Ok(parse_navi_cheat(content)),
+            Self::Pet => parse_pet_snippets(content),
+        }
+    }
+}
+
+/// Parses a navi cheat, where each command is described by the comment right before it and tagged by the `%` header
+fn parse_navi_cheat(content: &str) -> Vec<(String, String)> {
+    let mut commands = Vec::new();
+    let mut tags = String::new();
+    let mut description = String::new();
+    let mut cmd: Option<String> = None;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        // Lines ending with a backslash continue on the next one
+        if let Some(current) = cmd.as_mut().filter(|c| c.ends_with('\\')) {
+            current.push('\n');
+            current.push_str(&NAVI_VARIABLE_REGEX.replace_all(line.trim_end(), "{{$1}}"));
+            continue;
+        }
+        if let Some(cmd) = cmd.take() {
+            commands.push((cmd, hashtagged(&description, &tags)));
+        }
+        if let Some(header) = trimmed.strip_prefix('%') {
+            tags = header
+                .split(',')
+                .map(|tag| tag.split_whitespace().join("-"))
+                .filter(|tag| !tag.is_empty())
+                .map(|tag| format!("#{tag}"))
+                .join(" ");
+            description.clear();
+        } else if let Some(comment) = trimmed.strip_prefix('#') {
+            description = comment.trim().to_owned();
+        } else if trimmed.is_empty() || trimmed.starts_with([';', '$', '@']) {
+            continue;
+        } else {
+            cmd = Some(NAVI_VARIABLE_REGEX.replace_all(line.trim_end(), "{{$1}}").into_owned());
+        }
+    }
+    if let Some(cmd) = cmd {
+        commands.push((cmd, hashtagged(&description, &tags)));
+    }
+    commands
+}
+
+/// Parses the snippets of a pet `snippet.toml` file, which only contains `[[snippets]]` tables with string values
+fn parse_pet_snippets(content: &str) -> Result<Vec<(String, String)>> {
+    let mut snippets: Vec<(String, String, String)> = Vec::new();
+    for (ix, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line == "[[snippets]]" {
+            snippets.push(Default::default());
+            continue;
+        }
+        let (Some(snippet), Some((key, value))) = (snippets.last_mut(), line.split_once('=')) else {
+            continue;
+        };
+        let mut values = TOML_STRING_REGEX.captures_iter(value).map(|caps| match caps.get(1) {
+            Some(basic) => serde_json::from_str::<String>(&format!("\"{}\"", basic.as_str()))
+                .with_context(|| format!("Invalid string on line {} of the pet snippets", ix + 1)),
+            None => Ok(caps[2].to_owned()),
+        });
+        match key.trim() {
+            "command" => snippet.0 = values.next().transpose()?.unwrap_or_default(),
+            "description" => snippet.1 = values.next().transpose()?.unwrap_or_default(),
+            "tag" => {
+                snippet.2 = values
+                    .map_ok(|tag| format!("#{}", tag.split_whitespace().join("-")))
+                    .collect::<Result<Vec<_>>>()?
+                    .join(" ")
+            }
+            _ => (),
+        }
+    }
+    Ok(snippets
+        .into_iter()
+        .filter(|(cmd, _, _)| !cmd.trim().is_empty())
+        .map(|(cmd, description, tags)| {
+            let cmd = PET_PARAMETER_REGEX.replace_all(&cmd, |caps: &regex::Captures| match caps.get(2) {
+                Some(default) => format!("{{{{{}:={}}}}}", &caps[1], default.as_str()),
+                None => format!("{{{{{}}}}}", &caps[1]),
+            });
+            (cmd.into_owned(), hashtagged(&description, &tags))
+        })
+        .collect())
+}
+
+/// Appends the hashtags to the description, if any
+fn hashtagged(description: &str, hashtags: &str) -> String {
+    format!("{description} {hashtags}").trim().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_snippets() {
+        let cheat = r#"% git, code review
+
+# Change branch
+git checkout <branch>
+
+; Multi-line commands
+# Log with a format
+git log \
+  --author=<author>
+
+$ branch: git branch | awk '{print $NF}'
+"#;
+        assert_eq!(SnippetFormat::detect("git.txt", cheat), Some(SnippetFormat::Navi));
+        assert_eq!(
+            SnippetFormat::Navi.parse(cheat).unwrap(),
+            vec![
+                (
+                    "git checkout {{branch}}".to_owned(),
+                    "Change branch #git #code-review".to_owned()
+                ),
+                (
+                    "git log \\\n  --author={{author}}".to_owned(),
+                    "Log with a format #git #code-review".to_owned()
+                ),
+            ]
+        );
+
+        let toml = r#"[[snippets]]
+  description = "Ping a \"host\""
+  command = "ping <host=8.8.8.8> -c <count>"
+  tag = ["network", 'dns']
+  output = ""
+
+[[snippets]]
+  command = 'echo hi'
+"#;
+        assert_eq!(SnippetFormat::detect("snippet.toml", toml), Some(SnippetFormat::Pet));
+        assert_eq!(
+            SnippetFormat::Pet.parse(toml).unwrap(),
+            vec![
+                (
+                    "ping {{host:=8.8.8.8}} -c {{count}}".to_owned(),
+                    "Ping a \"host\" #network #dns".to_owned()
+                ),
+                ("echo hi".to_owned(), String::new()),
+            ]
+        );
+
+        assert_eq!(SnippetFormat::detect("cmds.txt", "ls ## List files\n"), None);
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use directories::BaseDirs;
+use itertools::Itertools;
+
+/// Determines the ssh config file of the current user, if any
+pub fn default_ssh_config_file() -> Option<PathBuf> {
+    Some(BaseDirs::new()?.home_dir().join(".ssh").join("config"))
+}
+
+/// Reads the given ssh config file, along with the `known_hosts` file next to it if any, returning the connection
+/// commands of every host found as `(alias, cmd, description)`
+pub fn read_ssh_hosts(config_file: impl AsRef<Path>) -> Result<Vec<(Option<String>, String, String)>> {
+    let config_file = config_file.as_ref();
+    let config = fs::read_to_string(config_file)
+        .with_context(|| format!("Error reading ssh config file {}", config_file.display()))?;
+    let known_hosts = config_file
+        .parent()
+        .map(|dir| dir.join("known_hosts"))
+        .and_then(|file| fs::read_to_string(file).ok())
+        .unwrap_or_default();
+
+    let hosts = parse_ssh_config(&config);
+    let mut commands = Vec::new();
+    for host in hosts.iter() {
+        let target = match (&host.user, &host.hostname) {
+            (Some(user), Some(hostname)) => format!(" ({user}@{hostname})"),
+            (None, Some(hostname)) => format!(" ({hostname})"),
+            (Some(user), None) => format!(" ({user})"),
+            (None, None) => String::new(),
+        };
+        let name = &host.name;
+        commands.push((
+            Some(name.clone()),
+            format!("ssh {name}"),
+            format!("Connect to {name}{target} #ssh"),
+        ));
+        commands.push((
+            None,
+            format!("scp {{{{local-path}}}} {name}:{{{{remote-path}}}}"),
+            format!("Copy a file to {name}{target} #ssh #scp"),
+        ));
+        commands.push((
+            None,
+            format!("scp {name}:{{{{remote-path}}}} {{{{local-path}}}}"),
+            format!("Copy a file from {name}{target} #ssh #scp"),
+        ));
+    }
+    for (hostname, port) in parse_known_hosts(&known_hosts) {
+        let configured = hosts
+            .iter()
+            .any(|h| h.name == hostname || h.hostname.as_deref() == Some(hostname.as_str()));
+        if !configured {
+            let port = port.map(|p| format!("-p {p} ")).unwrap_or_default();
+            commands.push((
+                None,
+                format!("ssh {port}{{{{user}}}}@{hostname}"),
+                format!("Connect to {hostname} #ssh #known-hosts"),
+            ));
+        }
+    }
+    Ok(commands)
+}
+
+/// Host declared on the ssh config
+#[cfg_attr(debug_assertions, derive(Debug))]
+#[derive(PartialEq, Eq)]
+struct SshHost {
+    name: String,
+    hostname: Option<String>,
+    user: Option<String>,
+}
+
+/// Parses the content of a ssh config file, returning the hosts declared without patterns
+fn parse_ssh_config(content: &str) -> Vec<SshHost> {
+    let mut hosts: Vec<SshHost> = Vec::new();
+    // Index of the first host of the current block, as a block might declare many of them
+    let mut block = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (keyword, value) = match line.split_once(|c: char| c.is_whitespace() || c == '=') {
+            Some((keyword, value)) => (
+                keyword.to_lowercase(),
+                value.trim_start_matches([' ', '\t', '=']).trim(),
+            ),
+            None => continue,
+        };
+        match keyword.as_str() {
+            "host" => {
+                block = Some(hosts.len());
+                for name in value.split_whitespace() {
+                    if !name.contains(['*', '?', '!']) && !hosts.iter().any(|h| h.name == name) {
+                        hosts.push(SshHost {
+                            name: name.to_owned(),
+                            hostname: None,
+                            user: None,
+                        });
+                    }
+                }
+            }
+            "match" => block = None,
+            "hostname" | "user" => {
+                if let Some(block) = block {
+                    for host in hosts.iter_mut().skip(block) {
+                        let field = if keyword == "hostname" {
+                            &mut host.hostname
+                        } else {
+                            &mut host.user
+                        };
+                        // The first obtained value is the one used by ssh
+                        field.get_or_insert_with(|| value.to_owned());
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    hosts
+}
+
+/// Parses the content of a `known_hosts` file, returning the distinct hosts along with their port, if not the default
+/// one. Hashed entries can't be read and are skipped, as well as raw IP addresses (usually recorded along with the host
+/// name, which is more meaningful).
+fn parse_known_hosts(content: &str) -> Vec<(String, Option<u16>)> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('|'))
+        // Markers like @cert-authority or @revoked aren't hosts to connect to
+        .filter(|line| !line.starts_with('@'))
+        .filter_map(|line| line.split_whitespace().next())
+        .flat_map(|hosts| hosts.split(','))
+        .filter(|host| !host.contains(['*', '?', '!']))
+        .map(|host| match host.strip_prefix('[').and_then(|h| h.split_once("]:")) {
+            Some((host, port)) => (host.to_owned(), port.parse().ok().filter(|p| *p != 22)),
+            None => (host.to_owned(), None),
+        })
+        .filter(|(host, _)| host.parse::<IpAddr>().is_err())
+        .unique()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ssh_files() {
+        let config = r#"
+Host *
+    ServerAliveInterval 60
+
+# Databases
+Host db db-replica
+    HostName=10.0.0.5
+    User postgres
+
+Host bastion
+    hostname bastion.example.com
+    HostName ignored.example.com
+
+Match host *.internal
+    User admin
+"#;
+        assert_eq!(
+            parse_ssh_config(config),
+            vec![
+                SshHost {
+                    name: "db".to_owned(),
+                    hostname: Some("10.0.0.5".to_owned()),
+                    user: Some("postgres".to_owned()),
+                },
+                SshHost {
+                    name: "db-replica".to_owned(),
+                    hostname: Some("10.0.0.5".to_owned()),
+                    user: Some("postgres".to_owned()),
+                },
+                SshHost {
+                    name: "bastion".to_owned(),
+                    hostname: Some("bastion.example.com".to_owned()),
+                    user: None,
+                },
+            ]
+        );
+
+        let known_hosts = "github.com,140.82.121.4 ssh-ed25519 AAAA\n[git.example.com]:2222 ssh-rsa AAAA\n|1|abc= \
+                           ssh-rsa AAAA\n@revoked * ssh-rsa AAAA\n[local]:22 ssh-rsa AAAA\n[::1]:2222 ssh-rsa AAAA\n";
+        assert_eq!(
+            parse_known_hosts(known_hosts),
+            vec![
+                ("github.com".to_owned(), None),
+                ("git.example.com".to_owned(), Some(2222)),
+                ("local".to_owned(), None),
+            ]
+        );
+    }
+}
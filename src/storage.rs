@@ -1,22 +1,43 @@
 use core::slice;
 use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
     env, fs,
-    io::{BufRead, BufReader, BufWriter, Write},
+    hash::{Hash, Hasher},
+    io::{BufWriter, Write},
+    iter,
+    path::{Path, PathBuf},
     sync::Mutex,
+    time::Duration,
 };
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{bail, Context, Result};
 use directories::ProjectDirs;
 use iter_flow::Iterflow;
 use itertools::Itertools;
 use once_cell::sync::Lazy;
 use regex::Regex;
-use rusqlite::{params_from_iter, Connection, Error, ErrorCode, OptionalExtension, Row};
+use rusqlite::{
+    named_params, params_from_iter, Connection, Error, ErrorCode, OptionalExtension, Row, ToSql, Transaction,
+    TransactionBehavior,
+};
 use rusqlite_migration::{Migrations, M};
 
 use crate::{
-    common::flatten_str,
-    model::{Command, LabelSuggestion},
+    common::{current_project_dir, flatten_str, unix_timestamp},
+    error::{ErrorKind, UserFacingError},
+    help::read_help_commands,
+    history::parse_history,
+    journal::{UsageIncrement, UsageJournal},
+    markdown::{markdown_dir_fingerprint, read_markdown_dir},
+    model::{
+        is_within_category, normalize_command, split_tags, AsLabeledCommand, Command, ExecutionStats, ExportFormat,
+        ImportChange, ImportStats, LabelSuggestion, Learning, SearchMatch, SearchQuery, ALIAS_HEADER, CATEGORY_HEADER,
+        CATEGORY_SEPARATOR, LIBRARY_CATEGORY, VALUE_HEADER,
+    },
+    paste::download_cached,
+    plugin::{export_plugin_commands, import_plugin_commands},
+    snippets::SnippetFormat,
+    ssh::read_ssh_hosts,
 };
 
 /// Database migrations
@@ -41,60 +62,427 @@ static MIGRATIONS: Lazy<Migrations> = Lazy::new(|| {
                 PRIMARY KEY (flat_root_cmd, flat_label, suggestion)
             );"#,
         ),
+        M::up(r#"ALTER TABLE command ADD COLUMN last_used INTEGER NULL;"#),
+        M::up(
+            r#"CREATE TABLE command_profiled (
+                profile TEXT NOT NULL DEFAULT 'default',
+                category TEXT NOT NULL,
+                alias TEXT NULL,
+                cmd TEXT NOT NULL,
+                description TEXT NOT NULL,
+                usage INTEGER DEFAULT 0,
+                last_used INTEGER NULL,
+                UNIQUE (profile, cmd)
+            );
+            INSERT INTO command_profiled (rowid, category, alias, cmd, description, usage, last_used)
+                SELECT rowid, category, alias, cmd, description, usage, last_used FROM command;
+            DROP TABLE command;
+            ALTER TABLE command_profiled RENAME TO command;"#,
+        ),
+        M::up(
+            r#"CREATE TABLE command_path_usage (
+                command_id INTEGER NOT NULL,
+                path TEXT NOT NULL,
+                usage INTEGER DEFAULT 0,
+                last_used INTEGER NOT NULL,
+                PRIMARY KEY (command_id, path)
+            );"#,
+        ),
+        M::up(
+            r#"CREATE TABLE sync_state (
+                profile TEXT NOT NULL,
+                source TEXT NOT NULL,
+                fingerprint TEXT NOT NULL,
+                PRIMARY KEY (profile, source)
+            );"#,
+        ),
+        M::up(
+            r#"CREATE TABLE command_learning (
+                command_id INTEGER PRIMARY KEY,
+                goal INTEGER NOT NULL,
+                start_usage INTEGER NOT NULL,
+                hide INTEGER NOT NULL DEFAULT 0
+            );"#,
+        ),
+        M::up(
+            r#"CREATE TABLE command_pin (
+                command_id INTEGER NOT NULL,
+                path TEXT NOT NULL,
+                PRIMARY KEY (command_id, path)
+            );"#,
+        ),
+        M::up(
+            r#"CREATE TABLE provider_cache (
+                provider TEXT PRIMARY KEY,
+                suggestions TEXT NOT NULL,
+                fetched_at INTEGER NOT NULL
+            );"#,
+        ),
+        M::up(
+            r#"CREATE TABLE shell_history (
+                profile TEXT NOT NULL,
+                cmd TEXT NOT NULL,
+                path TEXT NOT NULL,
+                last_run INTEGER NOT NULL,
+                PRIMARY KEY (profile, cmd, path)
+            );"#,
+        ),
+        M::up(
+            r#"CREATE TABLE label_suggestion_context (
+                flat_root_cmd TEXT NOT NULL,
+                flat_label TEXT NOT NULL,
+                suggestion TEXT NOT NULL,
+                context_label TEXT NOT NULL,
+                context_value TEXT NOT NULL,
+                usage INTEGER DEFAULT 0,
+                PRIMARY KEY (flat_root_cmd, flat_label, suggestion, context_label, context_value)
+            );"#,
+        ),
+        M::up(
+            r#"CREATE TABLE execution_log (
+                command_id INTEGER NOT NULL,
+                exit_code INTEGER NULL,
+                duration_ms INTEGER NOT NULL,
+                executed_at INTEGER NOT NULL
+            );
+            CREATE INDEX execution_log_command ON execution_log (command_id, executed_at);"#,
+        ),
+        M::up(
+            r#"CREATE TABLE label_suggestion_profiled (
+                profile TEXT NOT NULL DEFAULT 'default',
+                flat_root_cmd TEXT NOT NULL,
+                flat_label TEXT NOT NULL,
+                suggestion TEXT NOT NULL,
+                usage INTEGER DEFAULT 0,
+                PRIMARY KEY (profile, flat_root_cmd, flat_label, suggestion)
+            );
+            INSERT INTO label_suggestion_profiled (flat_root_cmd, flat_label, suggestion, usage)
+                SELECT flat_root_cmd, flat_label, suggestion, usage FROM label_suggestion;
+            DROP TABLE label_suggestion;
+            ALTER TABLE label_suggestion_profiled RENAME TO label_suggestion;
+
+            CREATE TABLE label_suggestion_context_profiled (
+                profile TEXT NOT NULL DEFAULT 'default',
+                flat_root_cmd TEXT NOT NULL,
+                flat_label TEXT NOT NULL,
+                suggestion TEXT NOT NULL,
+                context_label TEXT NOT NULL,
+                context_value TEXT NOT NULL,
+                usage INTEGER DEFAULT 0,
+                PRIMARY KEY (profile, flat_root_cmd, flat_label, suggestion, context_label, context_value)
+            );
+            INSERT INTO label_suggestion_context_profiled
+                (flat_root_cmd, flat_label, suggestion, context_label, context_value, usage)
+                SELECT flat_root_cmd, flat_label, suggestion, context_label, context_value, usage
+                FROM label_suggestion_context;
+            DROP TABLE label_suggestion_context;
+            ALTER TABLE label_suggestion_context_profiled RENAME TO label_suggestion_context;
+
+            CREATE TABLE provider_cache_profiled (
+                profile TEXT NOT NULL DEFAULT 'default',
+                provider TEXT NOT NULL,
+                suggestions TEXT NOT NULL,
+                fetched_at INTEGER NOT NULL,
+                PRIMARY KEY (profile, provider)
+            );
+            INSERT INTO provider_cache_profiled (provider, suggestions, fetched_at)
+                SELECT provider, suggestions, fetched_at FROM provider_cache;
+            DROP TABLE provider_cache;
+            ALTER TABLE provider_cache_profiled RENAME TO provider_cache;"#,
+        ),
     ])
 });
 
 /// Category for user defined commands
 pub const USER_CATEGORY: &str = "user";
 
+/// Category for the commands synced from the markdown vault
+pub const VAULT_CATEGORY: &str = "vault";
+
+/// Categories of the commands fetched from tldr, one per platform folder of the tldr pages
+pub const TLDR_CATEGORIES: [&str; 11] = [
+    "android",
+    "cisco-ios",
+    "common",
+    "dos",
+    "freebsd",
+    "linux",
+    "netbsd",
+    "openbsd",
+    "osx",
+    "sunos",
+    "windows",
+];
+
+/// Profile used when none is selected
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Number of recently executed shell commands kept on each profile
+const SHELL_HISTORY_SIZE: usize = 500;
+
+/// Number of executions kept on the log of each command
+const EXECUTION_LOG_SIZE: usize = 20;
+
+/// Number of latest executions considered by the stats of a command
+const EXECUTION_STATS_RUNS: usize = 5;
+
+/// Time to wait for the database to be unlocked, same as the SQLite connection default
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Regex to match not allowed FTS characters
 static ALLOWED_FTS_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"[^a-zA-Z0-9 ]"#).unwrap());
 
+/// Imported commands merged into an equivalent one, along with it
+type MergedCommands = Vec<(Command, String)>;
+
+/// Command deleted along with the rows related to it, to be restored as it was
+pub struct DeletedCommand {
+    /// The command deleted
+    pub command: Command,
+    /// Paths the command was pinned to
+    pins: Vec<String>,
+    /// Goal, start usage and wether to hide it, if the command was being learnt
+    learning: Option<(i64, i64, bool)>,
+    /// Path, usage and last used timestamp of the command on each path
+    path_usage: Vec<(String, i64, i64)>,
+    /// Exit code, duration and timestamp of the latest executions
+    executions: Vec<(Option<i64>, i64, i64)>,
+}
+
+/// Label suggestion deleted along with the context it was used on, to be restored as it was
+pub struct DeletedLabelSuggestion {
+    /// The suggestion deleted
+    pub suggestion: LabelSuggestion,
+    /// Label, value and usage of the labels filled before the suggestion was used
+    context: Vec<(String, String, i64)>,
+}
+
 /// SQLite-based storage
 pub struct SqliteStorage {
     conn: Mutex<Connection>,
+    /// Usage increments pending to be persisted
+    journal: Mutex<UsageJournal>,
+    /// Profile every command operation is scoped to
+    profile: String,
+    /// Half-life of the usage of the commands when ranking them, if it decays over time
+    usage_half_life: Option<Duration>,
+    /// Wether the imported commands matching a stored labeled command are merged into it
+    import_template_match: bool,
 }
 
 impl SqliteStorage {
     /// Builds a new SQLite storage on the default path
     pub fn new() -> Result<Self> {
-        let path = env::var_os("INTELLI_HOME")
-            .map(Into::into)
-            .map(anyhow::Ok)
-            .unwrap_or_else(|| {
-                Ok(ProjectDirs::from("org", "IntelliShell", "Intelli-Shell")
-                    .context("Error initializing project dir")?
-                    .data_dir()
-                    .to_path_buf())
-            })?;
+        Self::open(None)
+    }
+
+    /// Builds a new SQLite storage on the default path, encrypted with the given passphrase
+    #[cfg(feature = "encryption")]
+    pub fn new_encrypted(passphrase: &str) -> Result<Self> {
+        Self::open(Some(passphrase))
+    }
 
+    /// Opens the SQLite storage on the default path, encrypted with the passphrase if any
+    fn open(passphrase: Option<&str>) -> Result<Self> {
+        let path = data_dir()?;
         fs::create_dir_all(&path).context("Could't create data dir")?;
+        let db_path = path.join("storage.db3");
+
+        let conn = Connection::open(&db_path).context("Error opening SQLite connection")?;
+        let storage = Self {
+            conn: Mutex::new(Self::initialize_connection(conn, passphrase)?),
+            journal: Mutex::new(UsageJournal::new(&db_path)),
+            profile: DEFAULT_PROFILE.to_owned(),
+            usage_half_life: None,
+            import_template_match: false,
+        };
 
-        Ok(Self {
-            conn: Mutex::new(
-                Self::initialize_connection(
-                    Connection::open(path.join("storage.db3")).context("Error opening SQLite connection")?,
-                )
-                .context("Error initializing SQLite connection")?,
-            ),
-        })
+        // Persist any usage left behind by crashed processes
+        storage.flush_orphan_usage()?;
+
+        Ok(storage)
     }
 
     /// Builds a new in-memory SQLite storage for testing purposes
     pub fn new_in_memory() -> Result<Self> {
         Ok(Self {
-            conn: Mutex::new(
-                Self::initialize_connection(Connection::open_in_memory()?)
-                    .context("Error initializing SQLite connection")?,
-            ),
+            conn: Mutex::new(Self::initialize_connection(Connection::open_in_memory()?, None)?),
+            journal: Mutex::new(UsageJournal::in_memory()),
+            profile: DEFAULT_PROFILE.to_owned(),
+            usage_half_life: None,
+            import_template_match: false,
+        })
+    }
+
+    /// Scopes this storage to the given profile
+    pub fn with_profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = profile.into();
+        self
+    }
+
+    /// Decays the usage of the commands with the given half-life when ranking them, so old favorites fade away
+    pub fn with_usage_half_life(mut self, half_life: Option<Duration>) -> Self {
+        self.usage_half_life = half_life;
+        self
+    }
+
+    /// Merges the imported commands that could be produced by filling the labels of a stored command into it, instead
+    /// of storing them
+    pub fn with_import_template_match(mut self, template_match: bool) -> Self {
+        self.import_template_match = template_match;
+        self
+    }
+
+    /// Re-ranks the given commands by their decayed usage within each tier of matching, when a half-life is set
+    pub fn sort_by_decayed_usage(&self, commands: &mut [(Command, SearchMatch)]) {
+        let Some(half_life) = self.usage_half_life else {
+            return;
+        };
+        let now = unix_timestamp();
+        for tier in commands.chunk_by_mut(|(_, a), (_, b)| a == b) {
+            tier.sort_by(|(a, _), (b, _)| {
+                b.decayed_usage(half_life, now)
+                    .total_cmp(&a.decayed_usage(half_life, now))
+                    .then_with(|| {
+                        is_within_category(&b.category, USER_CATEGORY)
+                            .cmp(&is_within_category(&a.category, USER_CATEGORY))
+                    })
+            });
+        }
+    }
+
+    /// Returns the profile this storage is scoped to
+    pub fn profile(&self) -> &str {
+        &self.profile
+    }
+
+    /// Optimizes the full-text index and compacts the database, to speed up slow searches
+    pub fn optimize(&self) -> Result<()> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        conn.execute_batch(
+            r#"INSERT INTO command_fts (command_fts) VALUES ('optimize');
+            ANALYZE;
+            VACUUM;"#,
+        )
+        .context("Error optimizing the database")?;
+        Ok(())
+    }
+
+    /// Checks the integrity of the database, returning the problems found if any
+    pub fn integrity_check(&self) -> Result<Vec<String>> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        let mut stmt = conn.prepare(r#"PRAGMA integrity_check"#)?;
+        let problems = stmt
+            .query_map([], |r| r.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Error checking the database integrity")?;
+        Ok(problems.into_iter().filter(|p| p != "ok").collect())
+    }
+
+    /// Retrieves the version of the database schema, as the number of migrations applied
+    pub fn schema_version(&self) -> Result<i64> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        conn.pragma_query_value(None, "user_version", |r| r.get(0))
+            .context("Error querying the schema version")
+    }
+
+    /// Determines if the database is currently locked for writing by another process
+    pub fn is_locked(&self) -> Result<bool> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        // Don't wait for the lock to be released
+        conn.busy_timeout(Duration::ZERO)?;
+        let locked = match conn.execute_batch("BEGIN IMMEDIATE; ROLLBACK;") {
+            Err(Error::SqliteFailure(err, _)) => {
+                matches!(err.code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+            }
+            _ => false,
+        };
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        Ok(locked)
+    }
+
+    /// Encrypts the database with the given passphrase, which will be required to open it from now on
+    #[cfg(feature = "encryption")]
+    pub fn encrypt(&self, passphrase: &str) -> Result<()> {
+        let mut conn = self.conn.lock().expect("poisoned lock");
+        let db_path = conn
+            .path()
+            .filter(|p| !p.is_empty())
+            .context("Only databases stored on a file can be encrypted")?
+            .to_owned();
+        let encrypted_path = format!("{db_path}.encrypted");
+        let _ = fs::remove_file(&encrypted_path);
+
+        // Export a copy of the whole database into a new encrypted one
+        conn.execute("ATTACH DATABASE ?1 AS encrypted KEY ?2", (&encrypted_path, passphrase))
+            .context("Error creating the encrypted database")?;
+        conn.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))
+            .context("Error exporting into the encrypted database")?;
+        // The schema version, used by migrations, isn't exported
+        let version: i64 = conn.pragma_query_value(None, "user_version", |r| r.get(0))?;
+        conn.pragma_update(
+            Some(rusqlite::DatabaseName::Attached("encrypted")),
+            "user_version",
+            version,
+        )
+        .context("Error exporting into the encrypted database")?;
+        conn.execute("DETACH DATABASE encrypted", [])
+            .context("Error creating the encrypted database")?;
+
+        // Close the current connection before replacing the database file with the encrypted one
+        let plain = std::mem::replace(&mut *conn, Connection::open_in_memory()?);
+        plain
+            .close()
+            .map_err(|(_, err)| err)
+            .context("Error closing SQLite connection")?;
+        for suffix in ["-wal", "-shm"] {
+            let _ = fs::remove_file(format!("{db_path}{suffix}"));
+        }
+        fs::rename(&encrypted_path, &db_path).context("Error replacing the database with the encrypted one")?;
+
+        let encrypted = Connection::open(&db_path).context("Error opening SQLite connection")?;
+        *conn = Self::initialize_connection(encrypted, Some(passphrase))?;
+        Ok(())
+    }
+
+    /// Initializes an SQLite connection, with the encryption passphrase if any, applying migrations and common pragmas
+    fn initialize_connection(conn: Connection, passphrase: Option<&str>) -> Result<Connection> {
+        if let Some(passphrase) = passphrase {
+            // The key must be set before accessing the database at all
+            conn.pragma_update(None, "key", passphrase)
+                .context("Error applying encryption key pragma")?;
+        }
+        Self::apply_pragmas_and_migrations(conn).map_err(|err| {
+            let not_a_database = err.chain().any(|cause| {
+                matches!(
+                    cause.downcast_ref::<Error>(),
+                    Some(Error::SqliteFailure(err, _)) if err.code == ErrorCode::NotADatabase
+                )
+            });
+            match (not_a_database, passphrase) {
+                (true, Some(_)) => UserFacingError::new(
+                    ErrorKind::Auth,
+                    "Couldn't open the database, the passphrase is wrong or it isn't encrypted",
+                )
+                .into(),
+                (true, None) => UserFacingError::new(
+                    ErrorKind::Auth,
+                    "Couldn't open the database, it might be encrypted and require a passphrase",
+                )
+                .into(),
+                (false, _) => err.context("Error initializing SQLite connection"),
+            }
         })
     }
 
-    /// Initializes an SQLite connection applying migrations and common pragmas
-    fn initialize_connection(mut conn: Connection) -> Result<Connection> {
+    /// Applies migrations and common pragmas to an SQLite connection
+    fn apply_pragmas_and_migrations(mut conn: Connection) -> Result<Connection> {
         // Different implementation of the atomicity properties
         conn.pragma_update(None, "journal_mode", "WAL")
             .context("Error applying journal mode pragma")?;
+        // Wait for other processes writing at the same time
+        conn.busy_timeout(BUSY_TIMEOUT)
+            .context("Error applying busy timeout pragma")?;
         // Synchronize less often to the filesystem
         conn.pragma_update(None, "synchronous", "normal")
             .context("Error applying synchronous pragma")?;
@@ -123,34 +511,80 @@ impl SqliteStorage {
     ///
     /// Returns the number of commands inserted (the rest are updated)
     pub fn insert_commands(&self, commands: &mut [Command]) -> Result<u64> {
-        let mut res = 0;
+        self.upsert_commands(commands, false, &[])
+    }
 
-        let mut conn = self.conn.lock().expect("poisoned lock");
-        let tx = conn.transaction()?;
+    /// Inserts a bunch of commands along with their usage and updates its `id` with the inserted value.
+    ///
+    /// If any command already exist on the database, its description will be kept and its usage will be raised to
+    /// the given one if higher.
+    ///
+    /// Returns the number of commands inserted (the rest are updated)
+    pub fn insert_commands_usage(&self, commands: &mut [Command]) -> Result<u64> {
+        self.upsert_commands(commands, true, &[])
+    }
+
+    /// Upserts the given commands and, on the same transaction, merges the given equivalent ones into the command
+    /// they're equivalent to: its usage is raised to theirs if higher and their alias and description are kept when it
+    /// has none
+    fn upsert_commands(
+        &self,
+        commands: &mut [Command],
+        keep_description: bool,
+        merged: &[(Command, String)],
+    ) -> Result<u64> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        let tx = write_transaction(&conn)?;
+        let res = self.upsert_commands_in(&tx, commands, keep_description, merged)?;
+        tx.commit()?;
+        Ok(res)
+    }
+
+    /// Upserts the given commands within the given transaction, see [SqliteStorage::upsert_commands]
+    fn upsert_commands_in(
+        &self,
+        tx: &Transaction,
+        commands: &mut [Command],
+        keep_description: bool,
+        merged: &[(Command, String)],
+    ) -> Result<u64> {
+        let mut res = 0;
 
         {
-            let mut stmt_cmd = tx.prepare(
-                r#"INSERT INTO command (category, alias, cmd, description) VALUES (?, ?, ?, ?)
-                ON CONFLICT(cmd) DO UPDATE SET description=excluded.description
-                RETURNING rowid"#,
-            )?;
+            // The description of the library commands is only updated by the library itself
+            let on_conflict = if keep_description {
+                "usage=max(usage, excluded.usage)".to_owned()
+            } else {
+                format!(
+                    "description=iif(category = '{LIBRARY_CATEGORY}' AND excluded.category != '{LIBRARY_CATEGORY}', \
+                     description, excluded.description)"
+                )
+            };
+            let mut stmt_cmd = tx.prepare(&format!(
+                r#"INSERT INTO command (profile, category, alias, cmd, description, usage) VALUES (?, ?, ?, ?, ?, ?)
+                ON CONFLICT(profile, cmd) DO UPDATE SET {on_conflict}
+                RETURNING rowid, category"#
+            ))?;
             let mut stmt_fts_check = tx.prepare("SELECT rowid FROM command_fts WHERE rowid = ?")?;
             let mut stmt_fts_update = tx.prepare("UPDATE command_fts SET flat_description = ? WHERE rowid = ?")?;
             let mut stmt_fts_insert =
                 tx.prepare("INSERT INTO command_fts (rowid, flat_cmd, flat_description) VALUES (?, ?, ?)")?;
 
             for command in commands {
-                let row_id = stmt_cmd
+                let (row_id, category): (i64, String) = stmt_cmd
                     .query_row(
                         (
+                            &self.profile,
                             &command.category,
                             command.alias.as_deref(),
                             &command.cmd,
                             &command.description,
+                            command.usage,
                         ),
-                        |r| r.get(0),
+                        |r| Ok((r.get(0)?, r.get(1)?)),
                     )
                     .context("Error inserting command")?;
+                let protected = category == LIBRARY_CATEGORY && command.category != LIBRARY_CATEGORY;
 
                 command.id = row_id;
 
@@ -160,6 +594,7 @@ impl SqliteStorage {
                     .context("Error checking fts")?;
 
                 match current_row {
+                    Some(_) if keep_description || protected => (),
                     Some(_) => {
                         stmt_fts_update
                             .execute((flatten_str(&command.description), row_id))
@@ -173,28 +608,57 @@ impl SqliteStorage {
                     }
                 }
             }
-        }
 
-        tx.commit()?;
+            let mut stmt_merge = tx.prepare(&format!(
+                r#"UPDATE command SET
+                    usage = max(usage, :usage),
+                    alias = coalesce(alias, :alias),
+                    description = iif(description = '', :description, description)
+                WHERE profile = :profile AND cmd = :cmd AND category != '{LIBRARY_CATEGORY}'
+                RETURNING rowid, description"#
+            ))?;
+            for (command, equivalent) in merged {
+                let updated: Option<(i64, String)> = stmt_merge
+                    .query_row(
+                        named_params! {
+                            ":usage": command.usage,
+                            ":alias": command.alias.as_deref(),
+                            ":description": &command.description,
+                            ":profile": &self.profile,
+                            ":cmd": equivalent,
+                        },
+                        |r| Ok((r.get(0)?, r.get(1)?)),
+                    )
+                    .optional()
+                    .context("Error merging command")?;
+                if let Some((row_id, description)) = updated {
+                    stmt_fts_update
+                        .execute((flatten_str(&description), row_id))
+                        .context("Error updating command fts")?;
+                }
+            }
+        }
 
         Ok(res)
     }
 
-    /// Updates an existing command
+    /// Updates an existing command, unless it's read-only
     ///
     /// Returns wether the command exists and was updated or not.
     pub fn update_command(&self, command: &Command) -> Result<bool> {
-        let mut conn = self.conn.lock().expect("poisoned lock");
-        let tx = conn.transaction()?;
+        self.ensure_writable(command.id)?;
+        let conn = self.conn.lock().expect("poisoned lock");
+        let tx = write_transaction(&conn)?;
 
         let updated = tx
             .execute(
-                r#"UPDATE command SET alias = ?, cmd = ?, description = ?, usage = ? WHERE rowid = ?"#,
+                r#"UPDATE command SET alias = ?, cmd = ?, description = ?, usage = ?, last_used = ? WHERE rowid = ?"#,
                 (
                     command.alias.as_deref(),
                     &command.cmd,
                     &command.description,
                     command.usage,
+                    command.last_used,
                     command.id,
                 ),
             )
@@ -218,129 +682,963 @@ impl SqliteStorage {
         }
     }
 
-    /// Updates an existing command by incrementing its usage by one
+    /// Increments the usage of an existing command by one.
     ///
-    /// Returns wether the command exists and was updated or not.
-    pub fn increment_command_usage(&self, command_id: i64) -> Result<bool> {
-        let conn = self.conn.lock().expect("poisoned lock");
-        let updated = conn
-            .execute(r#"UPDATE command SET usage = usage + 1 WHERE rowid = ?"#, [command_id])
-            .context("Error updating command usage")?;
+    /// The increment is buffered and won't be persisted until [flush_usage](SqliteStorage::flush_usage) is called,
+    /// which happens at the latest when the storage is dropped.
+    pub fn increment_command_usage(&self, command_id: i64) -> Result<()> {
+        self.increment_command_usage_in(command_id, current_project_dir().as_deref())
+    }
 
-        Ok(updated == 1)
+    /// Increments the usage of an existing command by one, within the given project instead of the current one.
+    ///
+    /// The increment is buffered as well, like on [increment_command_usage](SqliteStorage::increment_command_usage).
+    pub fn increment_command_usage_in(&self, command_id: i64, project: Option<&Path>) -> Result<()> {
+        let mut journal = self.journal.lock().expect("poisoned lock");
+        journal.push(UsageIncrement::command(command_id, project))
     }
 
-    /// Deletes an existing command
+    /// Increments the usage of an existing label suggestion by one.
     ///
-    /// Returns wether the command exists and was deleted or not.
-    pub fn delete_command(&self, command_id: i64) -> Result<bool> {
-        let mut conn = self.conn.lock().expect("poisoned lock");
-        let tx = conn.transaction()?;
+    /// The increment is buffered and won't be persisted until [flush_usage](SqliteStorage::flush_usage) is called,
+    /// which happens at the latest when the storage is dropped.
+    pub fn increment_label_suggestion_usage(&self, suggestion: &LabelSuggestion) -> Result<()> {
+        let mut journal = self.journal.lock().expect("poisoned lock");
+        journal.push(UsageIncrement::LabelSuggestion {
+            profile: self.profile.clone(),
+            flat_root_cmd: suggestion.flat_root_cmd.clone(),
+            flat_label: suggestion.flat_label.clone(),
+            suggestion: suggestion.suggestion.clone(),
+        })
+    }
 
-        let deleted = tx
-            .execute(r#"DELETE FROM command WHERE rowid = ?"#, [command_id])
-            .context("Error deleting command")?;
-
-        if deleted == 1 {
-            let deleted = tx
-                .execute(r#"DELETE FROM command_fts WHERE rowid = ?"#, [command_id])
-                .context("Error deleting command fts")?;
-            if deleted == 1 {
-                tx.commit()?;
-                Ok(true)
-            } else {
-                Ok(false)
-            }
-        } else {
-            Ok(false)
+    /// Persists the buffered usage increments
+    pub fn flush_usage(&self) -> Result<()> {
+        let mut journal = self.journal.lock().expect("poisoned lock");
+        if !journal.is_empty() {
+            self.apply_usage(journal.pending())?;
+            journal.clear();
         }
+        Ok(())
     }
 
-    /// Get commands matching a category
-    pub fn get_commands(&self, category: impl AsRef<str>) -> Result<Vec<Command>> {
-        let category = category.as_ref();
+    /// Persists the usage increments left behind by crashed processes
+    fn flush_orphan_usage(&self) -> Result<()> {
+        let orphans = self.journal.lock().expect("poisoned lock").orphans();
+        for orphan in orphans {
+            self.apply_usage(&orphan.increments)?;
+            orphan.remove();
+        }
+        Ok(())
+    }
 
+    /// Applies the given usage increments to the database, atomically
+    fn apply_usage(&self, increments: &[UsageIncrement]) -> Result<()> {
         let conn = self.conn.lock().expect("poisoned lock");
-        let mut stmt = conn.prepare(
-            r#"SELECT rowid, category, alias, cmd, description, usage 
-            FROM command
-            WHERE category = ?
-            ORDER BY usage DESC"#,
-        )?;
+        let tx = write_transaction(&conn)?;
 
-        let commands = stmt
-            .query([category])?
-            .mapped(command_from_row)
-            .finish_vec()
-            .context("Error querying commands")?;
+        {
+            let mut stmt_cmd = tx.prepare(
+                r#"UPDATE command SET usage = usage + 1, last_used = max(coalesce(last_used, 0), ?) WHERE rowid = ?"#,
+            )?;
+            let mut stmt_path = tx.prepare(
+                r#"INSERT INTO command_path_usage (command_id, path, usage, last_used) VALUES (?, ?, 1, ?)
+                ON CONFLICT(command_id, path) DO UPDATE
+                SET usage = usage + 1, last_used = max(last_used, excluded.last_used)"#,
+            )?;
+            let mut stmt_label = tx.prepare(
+                r#"UPDATE label_suggestion SET usage = usage + 1
+                WHERE profile = ? AND flat_root_cmd = ? AND flat_label = ? AND suggestion = ?"#,
+            )?;
+            for increment in increments {
+                match increment {
+                    UsageIncrement::Command { id, timestamp, path } => {
+                        stmt_cmd
+                            .execute((timestamp, id))
+                            .context("Error updating command usage")?;
+                        if let Some(path) = path {
+                            stmt_path
+                                .execute((id, path, timestamp))
+                                .context("Error updating command path usage")?;
+                        }
+                    }
+                    UsageIncrement::LabelSuggestion {
+                        profile,
+                        flat_root_cmd,
+                        flat_label,
+                        suggestion,
+                    } => {
+                        stmt_label
+                            .execute((profile, flat_root_cmd, flat_label, suggestion))
+                            .context("Error updating label suggestion usage")?;
+                    }
+                }
+            }
+        }
 
-        Ok(commands)
+        tx.commit()?;
+
+        Ok(())
     }
 
-    /// Finds commands matching the given search criteria
-    pub fn find_commands(&self, search: impl AsRef<str>) -> Result<Vec<Command>> {
-        let search = search.as_ref().trim();
-        if search.is_empty() {
-            return self.get_commands(USER_CATEGORY);
+    /// Deletes an existing command, unless it's read-only
+    ///
+    /// Returns wether the command exists and was deleted or not.
+    pub fn delete_command(&self, command_id: i64) -> Result<bool> {
+        self.ensure_writable(command_id)?;
+        self.remove_command(command_id)
+    }
+
+    /// Fails if the given command is read-only, as it belongs to the shared library
+    fn ensure_writable(&self, command_id: i64) -> Result<()> {
+        let category: Option<String> = self
+            .conn
+            .lock()
+            .expect("poisoned lock")
+            .query_row(r#"SELECT category FROM command WHERE rowid = ?"#, [command_id], |r| {
+                r.get(0)
+            })
+            .optional()
+            .context("Error querying command")?;
+        if category.as_deref() == Some(LIBRARY_CATEGORY) {
+            bail!("The command is read-only, as it belongs to the shared library");
         }
-        let flat_search = flatten_str(search);
+        Ok(())
+    }
+
+    /// Fails if the given category is or would be nested within the library one, which is reserved
+    fn ensure_not_library(category: &str) -> Result<()> {
+        if is_within_category(category.trim(), LIBRARY_CATEGORY) {
+            bail!("The '{LIBRARY_CATEGORY}' category is reserved for the shared library, which is read-only");
+        }
+        Ok(())
+    }
 
+    /// Deletes an existing command, along with everything related to it
+    fn remove_command(&self, command_id: i64) -> Result<bool> {
         let conn = self.conn.lock().expect("poisoned lock");
-        let alias_cmd = conn
+        let tx = write_transaction(&conn)?;
+        let deleted = delete_command_rows(&tx, command_id)?;
+        tx.commit()?;
+        Ok(deleted)
+    }
+
+    /// Deletes an existing command like [delete_command](SqliteStorage::delete_command), but returning it along with
+    /// the rows related to it, so it can be restored later
+    pub fn take_command(&self, command: &Command) -> Result<Option<DeletedCommand>> {
+        self.ensure_writable(command.id)?;
+
+        let conn = self.conn.lock().expect("poisoned lock");
+        let tx = write_transaction(&conn)?;
+
+        let pins = tx
+            .prepare(r#"SELECT path FROM command_pin WHERE command_id = ?"#)?
+            .query([command.id])?
+            .mapped(|r| r.get(0))
+            .finish_vec()
+            .context("Error querying command pins")?;
+        let learning = tx
             .query_row(
-                r#"SELECT rowid, category, alias, cmd, description, usage 
-                FROM command
-                WHERE alias = :flat_search OR alias = :search"#,
-                &[(":flat_search", flat_search.as_str()), (":search", search)],
-                command_from_row,
+                r#"SELECT goal, start_usage, hide FROM command_learning WHERE command_id = ?"#,
+                [command.id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
             )
             .optional()
-            .context("Error querying command by alias")?;
-        if let Some(cmd) = alias_cmd {
-            return Ok(vec![cmd]);
+            .context("Error querying command learning")?;
+        let path_usage = tx
+            .prepare(r#"SELECT path, usage, last_used FROM command_path_usage WHERE command_id = ?"#)?
+            .query([command.id])?
+            .mapped(|r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+            .finish_vec()
+            .context("Error querying command path usage")?;
+        let executions = tx
+            .prepare(r#"SELECT exit_code, duration_ms, executed_at FROM execution_log WHERE command_id = ?"#)?
+            .query([command.id])?
+            .mapped(|r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+            .finish_vec()
+            .context("Error querying command execution log")?;
+
+        if !delete_command_rows(&tx, command.id)? {
+            return Ok(None);
         }
+        tx.commit()?;
 
-        let hashtags = flat_search
-            .split_whitespace()
-            .filter(|t| t.starts_with('#'))
-            .collect_vec();
+        Ok(Some(DeletedCommand {
+            command: command.clone(),
+            pins,
+            learning,
+            path_usage,
+            executions,
+        }))
+    }
 
-        let flat_fts_search = ALLOWED_FTS_REGEX.replace_all(&flat_search, "");
-        let flat_fts_search = flat_fts_search.trim();
-        if flat_fts_search.is_empty() || flat_fts_search == " " {
-            drop(conn);
-            return self.get_commands(USER_CATEGORY);
+    /// Restores a command previously taken, along with the rows related to it, so it ranks as before.
+    ///
+    /// The command gets a new id, as the previous one could have been reused since. Returns the restored command, or
+    /// `None` if an equal one was stored since.
+    pub fn restore_command(&self, deleted: &DeletedCommand) -> Result<Option<Command>> {
+        let command = &deleted.command;
+        let conn = self.conn.lock().expect("poisoned lock");
+        let tx = write_transaction(&conn)?;
+
+        let restored = tx
+            .execute(
+                r#"INSERT OR IGNORE INTO command (profile, category, alias, cmd, description, usage, last_used)
+                VALUES (?, ?, ?, ?, ?, ?, ?)"#,
+                (
+                    &self.profile,
+                    &command.category,
+                    command.alias.as_deref(),
+                    &command.cmd,
+                    &command.description,
+                    command.usage,
+                    command.last_used,
+                ),
+            )
+            .context("Error restoring command")?;
+        if restored == 0 {
+            return Ok(None);
+        }
+        let id = tx.last_insert_rowid();
+
+        tx.execute(
+            r#"INSERT INTO command_fts (rowid, flat_cmd, flat_description) VALUES (?, ?, ?)"#,
+            (id, flatten_str(&command.cmd), flatten_str(&command.description)),
+        )
+        .context("Error restoring command fts")?;
+        for path in &deleted.pins {
+            tx.execute(
+                r#"INSERT INTO command_pin (command_id, path) VALUES (?, ?)"#,
+                (id, path),
+            )
+            .context("Error restoring command pins")?;
         }
+        if let Some((goal, start_usage, hide)) = deleted.learning {
+            tx.execute(
+                r#"INSERT INTO command_learning (command_id, goal, start_usage, hide) VALUES (?, ?, ?, ?)"#,
+                (id, goal, start_usage, hide),
+            )
+            .context("Error restoring command learning")?;
+        }
+        for (path, usage, last_used) in &deleted.path_usage {
+            tx.execute(
+                r#"INSERT INTO command_path_usage (command_id, path, usage, last_used) VALUES (?, ?, ?, ?)"#,
+                (id, path, usage, last_used),
+            )
+            .context("Error restoring command path usage")?;
+        }
+        for (exit_code, duration_ms, executed_at) in &deleted.executions {
+            tx.execute(
+                r#"INSERT INTO execution_log (command_id, exit_code, duration_ms, executed_at) VALUES (?, ?, ?, ?)"#,
+                (id, exit_code, duration_ms, executed_at),
+            )
+            .context("Error restoring command execution log")?;
+        }
+        tx.commit()?;
 
-        let mut stmt = conn.prepare(
-            r#"
-                    SELECT DISTINCT rowid, category, alias, cmd, description, usage 
-                    FROM (
-                        SELECT c.rowid, c.category, c.alias, c.cmd, c.description, c.usage, 3 as ord
-                        FROM command c
-                        WHERE c.alias GLOB :glob
-                    
-                        UNION ALL
-                        SELECT c.rowid, c.category, c.alias, c.cmd, c.description, c.usage, 2 as ord
-                        FROM command_fts s
-                        JOIN command c ON s.rowid = c.rowid
-                        WHERE command_fts MATCH :match_cmd_ordered
-                    
-                        UNION ALL
-                        
-                        SELECT c.rowid, c.category, c.alias, c.cmd, c.description, c.usage, 1 as ord
-                        FROM command_fts s
-                        JOIN command c ON s.rowid = c.rowid
-                        WHERE command_fts MATCH :match_simple
+        Ok(Some(Command { id, ..command.clone() }))
+    }
 
-                        UNION ALL
-                        
-                        SELECT c.rowid, c.category, c.alias, c.cmd, c.description, c.usage, 0 as ord
-                        FROM command_fts s
+    /// Deletes the commands fetched from tldr (on any of the [TLDR_CATEGORIES]) which have never been used or, if a
+    /// duration is given, which haven't been used for that long
+    ///
+    /// Returns the number of deleted commands
+    pub fn prune_unused_commands(&self, unused_for: Option<Duration>) -> Result<usize> {
+        let used_since = unused_for.map(|d| unix_timestamp().saturating_sub(d.as_secs()));
+
+        let conn = self.conn.lock().expect("poisoned lock");
+        let tx = write_transaction(&conn)?;
+
+        let tldr_categories = TLDR_CATEGORIES.iter().map(|c| format!("'{c}'")).join(", ");
+        let ids: Vec<i64> = tx
+            .prepare(&format!(
+                r#"SELECT rowid FROM command
+                WHERE profile = :profile AND category IN ({tldr_categories}) AND (usage = 0 OR last_used < :used_since)"#
+            ))?
+            .query(named_params! {
+                ":profile": &self.profile,
+                ":used_since": used_since,
+            })?
+            .mapped(|r| r.get(0))
+            .finish_vec()
+            .context("Error querying unused commands")?;
+
+        let mut deleted = 0;
+        for id in ids {
+            if delete_command_rows(&tx, id)? {
+                deleted += 1;
+            }
+        }
+
+        tx.commit()?;
+
+        Ok(deleted)
+    }
+
+    /// Finds the commands meeting every given criteria, to be pruned
+    pub fn find_prunable_commands(&self, criteria: &PruneCriteria) -> Result<Vec<Command>> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        self.find_prunable_commands_in(&conn, criteria)
+    }
+
+    /// Finds the commands to be pruned within the given connection, see [SqliteStorage::find_prunable_commands]
+    fn find_prunable_commands_in(&self, conn: &Connection, criteria: &PruneCriteria) -> Result<Vec<Command>> {
+        let used_since = criteria
+            .unused_for
+            .map(|d| unix_timestamp().saturating_sub(d.as_secs()));
+
+        let all = conn
+            .prepare(
+                r#"SELECT rowid, category, alias, cmd, description, usage, last_used
+                FROM command
+                WHERE profile = ?
+                ORDER BY usage DESC, rowid ASC"#,
+            )?
+            .query([&self.profile])?
+            .mapped(command_from_row)
+            .finish_vec()
+            .context("Error querying commands")?;
+
+        // The most used command of every group of duplicates is kept, as they're sorted by usage
+        let normalize = |cmd: &str| cmd.split_whitespace().join(" ");
+        let mut keepers = HashMap::new();
+        for command in all.iter() {
+            keepers.entry(normalize(&command.cmd)).or_insert(command.id);
+        }
+
+        Ok(all
+            .into_iter()
+            .filter(|c| !c.is_read_only())
+            .filter(|c| {
+                criteria
+                    .category
+                    .as_ref()
+                    .map(|category| is_within_category(&c.category, category))
+                    .unwrap_or(true)
+            })
+            // Commands used before their last usage was tracked are kept, as they're ranked by their whole usage
+            .filter(|c| match used_since {
+                Some(used_since) => c.usage == 0 || c.last_used.is_some_and(|last_used| last_used < used_since),
+                None => true,
+            })
+            .filter(|c| criteria.pattern.as_ref().map(|p| p.is_match(&c.cmd)).unwrap_or(true))
+            .filter(|c| !criteria.duplicates || keepers.get(&normalize(&c.cmd)) != Some(&c.id))
+            .collect())
+    }
+
+    /// Deletes the commands meeting every given criteria, on a single transaction
+    ///
+    /// Returns the number of deleted commands
+    pub fn prune_commands(&self, criteria: &PruneCriteria) -> Result<usize> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        let tx = write_transaction(&conn)?;
+        let mut deleted = 0;
+        for command in self.find_prunable_commands_in(&tx, criteria)? {
+            if delete_command_rows(&tx, command.id)? {
+                deleted += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(deleted)
+    }
+
+    /// Flags an existing command to be learnt, tracking its runs from now on until the goal is reached
+    pub fn learn_command(&self, command: &Command, goal: u64, hide: bool) -> Result<()> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        conn.execute(
+            r#"INSERT INTO command_learning (command_id, goal, start_usage, hide) VALUES (?, ?, ?, ?)
+            ON CONFLICT(command_id) DO UPDATE SET goal = excluded.goal, hide = excluded.hide"#,
+            (command.id, goal, command.usage, hide),
+        )
+        .context("Error flagging command to be learnt")?;
+        Ok(())
+    }
+
+    /// Stops learning an existing command
+    ///
+    /// Returns wether the command was being learnt or not.
+    pub fn stop_learning_command(&self, command_id: i64) -> Result<bool> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        let deleted = conn
+            .execute(r#"DELETE FROM command_learning WHERE command_id = ?"#, [command_id])
+            .context("Error deleting command learning")?;
+        Ok(deleted == 1)
+    }
+
+    /// Records the outcome of an execution of a command, along with its exit code (if it wasn't terminated by a signal)
+    /// and how long it took
+    pub fn record_execution(&self, command_id: i64, exit_code: Option<i32>, duration: Duration) -> Result<()> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        let tx = write_transaction(&conn)?;
+        tx.execute(
+            r#"INSERT INTO execution_log (command_id, exit_code, duration_ms, executed_at) VALUES (?, ?, ?, ?)"#,
+            (command_id, exit_code, duration.as_millis() as u64, unix_timestamp()),
+        )
+        .context("Error recording command execution")?;
+        tx.execute(
+            r#"DELETE FROM execution_log WHERE command_id = ?1 AND rowid NOT IN (
+                SELECT rowid FROM execution_log WHERE command_id = ?1 ORDER BY executed_at DESC, rowid DESC LIMIT ?2
+            )"#,
+            (command_id, EXECUTION_LOG_SIZE),
+        )
+        .context("Error pruning command execution log")?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get the outcomes of the latest executions of a command, if it was ever executed
+    pub fn get_execution_stats(&self, command_id: i64) -> Result<Option<ExecutionStats>> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        let (runs, failures, avg_duration_ms): (u64, Option<u64>, Option<f64>) = conn
+            .query_row(
+                r#"SELECT count(*), sum(exit_code IS NOT 0), avg(duration_ms)
+                FROM (
+                    SELECT exit_code, duration_ms FROM execution_log
+                    WHERE command_id = ?
+                    ORDER BY executed_at DESC, rowid DESC
+                    LIMIT ?
+                )"#,
+                (command_id, EXECUTION_STATS_RUNS),
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .context("Error querying command execution stats")?;
+        Ok((runs > 0).then(|| ExecutionStats {
+            runs,
+            failures: failures.unwrap_or_default(),
+            avg_duration: Duration::from_millis(avg_duration_ms.unwrap_or_default() as u64),
+        }))
+    }
+
+    /// Get the commands being learnt along with their progress, the ones closer to the goal first
+    pub fn get_learning_commands(&self) -> Result<Vec<(Command, Learning)>> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        let mut stmt = conn.prepare(
+            r#"SELECT c.rowid, c.category, c.alias, c.cmd, c.description, c.usage, c.last_used,
+                l.goal, max(c.usage - l.start_usage, 0), l.hide
+            FROM command_learning l
+            JOIN command c ON c.rowid = l.command_id
+            WHERE c.profile = ?
+            ORDER BY (l.goal - max(c.usage - l.start_usage, 0)) ASC"#,
+        )?;
+
+        let commands = stmt
+            .query([&self.profile])?
+            .mapped(|row| {
+                Ok((
+                    command_from_row(row)?,
+                    Learning {
+                        goal: row.get(7)?,
+                        runs: row.get(8)?,
+                        hide: row.get(9)?,
+                    },
+                ))
+            })
+            .finish_vec()
+            .context("Error querying learning commands")?;
+
+        Ok(commands)
+    }
+
+    /// Finds the hashtags used on the descriptions starting with the given prefix, along with the number of commands
+    /// using them, most used first
+    pub fn find_tags(&self, prefix: impl AsRef<str>) -> Result<Vec<(String, u64)>> {
+        let flat_prefix = flatten_str(format!("#{}", prefix.as_ref().trim_start_matches('#')));
+
+        let conn = self.conn.lock().expect("poisoned lock");
+        let mut stmt = conn.prepare(
+            r#"SELECT description
+            FROM command
+            WHERE profile = ? AND instr(description, '#') > 0"#,
+        )?;
+
+        let descriptions: Vec<String> = stmt
+            .query([&self.profile])?
+            .mapped(|r| r.get(0))
+            .finish_vec()
+            .context("Error querying tags")?;
+
+        Ok(descriptions
+            .iter()
+            .flat_map(|description| split_tags(description).1.into_iter().unique())
+            .filter(|tag| flatten_str(tag).starts_with(&flat_prefix))
+            .counts()
+            .into_iter()
+            .map(|(tag, count)| (tag, count as u64))
+            .sorted_by(|(a_tag, a_count), (b_tag, b_count)| b_count.cmp(a_count).then_with(|| a_tag.cmp(b_tag)))
+            .collect())
+    }
+
+    /// Get the categories along with their number of commands
+    pub fn get_categories(&self) -> Result<Vec<(String, u64)>> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        let mut stmt = conn.prepare(
+            r#"SELECT category, COUNT(*)
+            FROM command
+            WHERE profile = ?
+            GROUP BY category
+            ORDER BY category"#,
+        )?;
+
+        let categories = stmt
+            .query([&self.profile])?
+            .mapped(|r| Ok((r.get(0)?, r.get(1)?)))
+            .finish_vec()
+            .context("Error querying categories")?;
+
+        Ok(categories)
+    }
+
+    /// Renames a category along with its nested ones, failing if the new one already exists
+    ///
+    /// Returns the number of commands updated
+    pub fn rename_category(&self, from: impl AsRef<str>, to: impl AsRef<str>) -> Result<usize> {
+        let to = to.as_ref().trim();
+        if self.get_categories()?.iter().any(|(c, _)| is_within_category(c, to)) {
+            bail!("Category '{to}' already exists, merge them instead");
+        }
+        self.merge_categories(&[from], to)
+    }
+
+    /// Moves a command into the given category, that may not exist yet
+    ///
+    /// Returns wether the command exists and was moved or not
+    pub fn set_command_category(&self, command_id: i64, category: impl AsRef<str>) -> Result<bool> {
+        let category = category.as_ref().trim();
+        if category.is_empty() {
+            bail!("Category can't be empty");
+        }
+        Self::ensure_not_library(category)?;
+        self.ensure_writable(command_id)?;
+
+        let updated = self
+            .conn
+            .lock()
+            .expect("poisoned lock")
+            .execute(
+                r#"UPDATE command SET category = ? WHERE profile = ? AND rowid = ?"#,
+                (category, &self.profile, command_id),
+            )
+            .context("Error updating category")?;
+
+        Ok(updated == 1)
+    }
+
+    /// Moves every command from the given categories into another one, that may already exist. The nested categories
+    /// are moved along with their parent, like `cloud/aws` into `infra/aws` when moving `cloud` into `infra`.
+    ///
+    /// Returns the number of commands updated
+    pub fn merge_categories(&self, from: &[impl AsRef<str>], into: impl AsRef<str>) -> Result<usize> {
+        let into = into.as_ref().trim();
+        if into.is_empty() {
+            bail!("Category can't be empty");
+        }
+        Self::ensure_not_library(into)?;
+        let from = from.iter().map(|c| c.as_ref().trim()).collect_vec();
+        for &category in &from {
+            Self::ensure_not_library(category)?;
+            // The user and vault categories are always expected to exist, they can only be merged into
+            if (category == USER_CATEGORY || category == VAULT_CATEGORY) && category != into {
+                bail!("The '{category}' category can't be renamed nor merged into another one");
+            }
+            if category != into && is_within_category(into, category) {
+                bail!("The '{category}' category can't be moved into one of its nested categories");
+            }
+        }
+
+        let conn = self.conn.lock().expect("poisoned lock");
+        let tx = write_transaction(&conn)?;
+
+        let mut updated = 0;
+        {
+            let mut stmt = tx.prepare(
+                r#"UPDATE command SET category = :into || substr(category, length(:from) + 1)
+                WHERE profile = :profile AND (category = :from OR category GLOB :nested)"#,
+            )?;
+            for category in from {
+                if category == into {
+                    continue;
+                }
+                let res = stmt
+                    .execute(named_params! {
+                        ":into": into,
+                        ":from": category,
+                        ":profile": &self.profile,
+                        ":nested": format!("{}{CATEGORY_SEPARATOR}*", escape_glob(category)),
+                    })
+                    .context("Error updating category")?;
+                if res == 0 {
+                    return Err(UserFacingError::not_found(format!("Category '{category}' not found")).into());
+                }
+                updated += res;
+            }
+        }
+
+        tx.commit()?;
+
+        Ok(updated)
+    }
+
+    /// Get commands matching a category
+    pub fn get_commands(&self, category: impl AsRef<str>) -> Result<Vec<Command>> {
+        let category = category.as_ref();
+
+        let conn = self.conn.lock().expect("poisoned lock");
+        let mut stmt = conn.prepare(
+            r#"SELECT rowid, category, alias, cmd, description, usage, last_used
+            FROM command
+            WHERE profile = ? AND category = ?
+            ORDER BY usage DESC"#,
+        )?;
+
+        let commands = stmt
+            .query([&self.profile, category])?
+            .mapped(command_from_row)
+            .finish_vec()
+            .context("Error querying commands")?;
+
+        Ok(commands)
+    }
+
+    /// Finds the commands to be exposed to other tools, matching the given search (or the user ones when empty) within
+    /// the given scope: selectors like `#team` or `cloud/aws` to include, or prefixed with `!` to exclude. When there's
+    /// any selector to include, an empty search lists every command within them instead.
+    ///
+    /// Commands with secret labels or the `#nohistory` hashtag are never exposed.
+    pub fn find_exposed_commands(&self, search: impl AsRef<str>, scope: &[String]) -> Result<Vec<Command>> {
+        let search = search.as_ref().trim();
+        let (excluded, included): (Vec<&str>, Vec<&str>) = scope
+            .iter()
+            .map(|selector| selector.trim())
+            .filter(|selector| !selector.is_empty())
+            .partition(|selector| selector.starts_with('!'));
+        let excluded = excluded.into_iter().map(|selector| &selector[1..]).collect::<Vec<_>>();
+
+        let commands = if !search.is_empty() {
+            self.find_commands(search)?
+        } else if included.is_empty() {
+            self.get_commands(USER_CATEGORY)?
+        } else {
+            let conn = self.conn.lock().expect("poisoned lock");
+            let mut stmt = conn.prepare(
+                r#"SELECT rowid, category, alias, cmd, description, usage, last_used
+                FROM command
+                WHERE profile = ?
+                ORDER BY usage DESC"#,
+            )?;
+            let commands = stmt
+                .query([&self.profile])?
+                .mapped(command_from_row)
+                .finish_vec()
+                .context("Error querying commands")?;
+            commands
+        };
+
+        Ok(commands
+            .into_iter()
+            .filter(|command| {
+                !command.is_sensitive()
+                    && !command.cmd.as_labeled_command().is_some_and(|c| c.has_secrets())
+                    && (included.is_empty() || included.iter().any(|s| command.matches_selector(s)))
+                    && !excluded.iter().any(|s| command.matches_selector(s))
+            })
+            .collect())
+    }
+
+    /// Get commands of a category, including the ones on its nested categories (like `cloud/aws` for `cloud`)
+    pub fn get_commands_under(&self, category: impl AsRef<str>) -> Result<Vec<Command>> {
+        let category = category.as_ref();
+
+        let conn = self.conn.lock().expect("poisoned lock");
+        let mut stmt = conn.prepare(
+            r#"SELECT rowid, category, alias, cmd, description, usage, last_used
+            FROM command
+            WHERE profile = ?1 AND (category = ?2 OR substr(category, 1, length(?2) + 1) = ?2 || ?3)
+            ORDER BY usage DESC"#,
+        )?;
+
+        let commands = stmt
+            .query((&self.profile, category, CATEGORY_SEPARATOR.to_string()))?
+            .mapped(command_from_row)
+            .finish_vec()
+            .context("Error querying commands")?;
+
+        Ok(commands)
+    }
+
+    /// Get the most recently used commands, from any category
+    pub fn get_recent_commands(&self) -> Result<Vec<Command>> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        let mut stmt = conn.prepare(
+            r#"SELECT rowid, category, alias, cmd, description, usage, last_used
+            FROM command
+            WHERE profile = ? AND last_used IS NOT NULL
+            ORDER BY last_used DESC"#,
+        )?;
+
+        let commands = stmt
+            .query([&self.profile])?
+            .mapped(command_from_row)
+            .finish_vec()
+            .context("Error querying recent commands")?;
+
+        Ok(commands)
+    }
+
+    /// Get the commands used within the given project directory for the last given period, most used first
+    pub fn get_commands_used_in(&self, path: impl AsRef<Path>, period: Duration) -> Result<Vec<Command>> {
+        let used_since = unix_timestamp().saturating_sub(period.as_secs());
+
+        let conn = self.conn.lock().expect("poisoned lock");
+        let mut stmt = conn.prepare(
+            r#"SELECT c.rowid, c.category, c.alias, c.cmd, c.description, c.usage, c.last_used
+            FROM command_path_usage p
+            JOIN command c ON c.rowid = p.command_id
+            WHERE c.profile = ? AND p.path = ? AND p.last_used >= ?
+            ORDER BY p.usage DESC, p.last_used DESC"#,
+        )?;
+
+        let commands = stmt
+            .query((&self.profile, path.as_ref().to_string_lossy(), used_since))?
+            .mapped(command_from_row)
+            .finish_vec()
+            .context("Error querying commands used in path")?;
+
+        Ok(commands)
+    }
+
+    /// Pins an existing command to the given directory, to be shown first when searching from within it
+    pub fn pin_command(&self, command_id: i64, path: impl AsRef<Path>) -> Result<()> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        conn.execute(
+            r#"INSERT OR IGNORE INTO command_pin (command_id, path) VALUES (?, ?)"#,
+            (command_id, path.as_ref().to_string_lossy()),
+        )
+        .context("Error pinning command")?;
+        Ok(())
+    }
+
+    /// Unpins a command from the given directory or any of its ancestors, the same ones it's retrieved from on
+    /// [get_pinned_commands](SqliteStorage::get_pinned_commands)
+    ///
+    /// Returns wether the command was pinned or not.
+    pub fn unpin_command(&self, command_id: i64, path: impl AsRef<Path>) -> Result<bool> {
+        let paths = path
+            .as_ref()
+            .ancestors()
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect_vec();
+
+        let conn = self.conn.lock().expect("poisoned lock");
+        let query = format!(
+            r#"DELETE FROM command_pin WHERE command_id = ?1 AND path IN ({})"#,
+            paths.iter().enumerate().map(|(i, _)| format!("?{}", i + 2)).join(","),
+        );
+
+        let parameters = iter::once(&command_id as &dyn ToSql).chain(paths.iter().map(|p| p as &dyn ToSql));
+        let deleted = conn
+            .execute(&query, params_from_iter(parameters))
+            .context("Error unpinning command")?;
+        Ok(deleted > 0)
+    }
+
+    /// Get the commands pinned to the given directory or any of its ancestors
+    pub fn get_pinned_commands(&self, path: impl AsRef<Path>) -> Result<Vec<Command>> {
+        let mut parameters = path
+            .as_ref()
+            .ancestors()
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect_vec();
+
+        let conn = self.conn.lock().expect("poisoned lock");
+        let mut stmt = conn.prepare(&format!(
+            r#"SELECT DISTINCT c.rowid, c.category, c.alias, c.cmd, c.description, c.usage, c.last_used
+            FROM command_pin p
+            JOIN command c ON c.rowid = p.command_id
+            WHERE c.profile = ?1 AND p.path IN ({})
+            ORDER BY c.usage DESC"#,
+            parameters
+                .iter()
+                .enumerate()
+                .map(|(i, _)| format!("?{}", i + 2))
+                .join(","),
+        ))?;
+
+        parameters.insert(0, self.profile.clone());
+
+        let commands = stmt
+            .query(params_from_iter(parameters.iter()))?
+            .mapped(command_from_row)
+            .finish_vec()
+            .context("Error querying pinned commands")?;
+
+        Ok(commands)
+    }
+
+    /// Records a command executed on the shell from the given project, to be bookmarked later.
+    ///
+    /// Only the most recent ones are kept.
+    pub fn record_shell_command(&self, cmd: impl AsRef<str>, project: impl AsRef<Path>) -> Result<()> {
+        let cmd = cmd.as_ref().trim();
+        if cmd.is_empty() {
+            return Ok(());
+        }
+        let project = project.as_ref().to_string_lossy();
+
+        let conn = self.conn.lock().expect("poisoned lock");
+        conn.execute(
+            r#"INSERT INTO shell_history (profile, cmd, path, last_run) VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(profile, cmd, path) DO UPDATE SET last_run = excluded.last_run"#,
+            (&self.profile, cmd, project.as_ref(), unix_timestamp()),
+        )
+        .context("Error recording shell command")?;
+        conn.execute(
+            r#"DELETE FROM shell_history WHERE profile = ?1 AND rowid NOT IN (
+                SELECT rowid FROM shell_history WHERE profile = ?1 ORDER BY last_run DESC, rowid DESC LIMIT ?2
+            )"#,
+            (&self.profile, SHELL_HISTORY_SIZE),
+        )
+        .context("Error pruning shell history")?;
+
+        Ok(())
+    }
+
+    /// Get the commands recently executed on the shell from the given project (or any, if none), which are not
+    /// bookmarked yet
+    pub fn get_recent_shell_commands(&self, project: Option<&Path>) -> Result<Vec<String>> {
+        let project = project.map(|p| p.to_string_lossy().into_owned());
+
+        let conn = self.conn.lock().expect("poisoned lock");
+        let mut stmt = conn.prepare(
+            r#"SELECT h.cmd
+            FROM shell_history h
+            WHERE h.profile = ?1
+                AND (?2 IS NULL OR h.path = ?2)
+                AND NOT EXISTS (SELECT 1 FROM command c WHERE c.profile = h.profile AND c.cmd = h.cmd)
+            GROUP BY h.cmd
+            ORDER BY MAX(h.last_run) DESC"#,
+        )?;
+
+        let commands = stmt
+            .query((&self.profile, project))?
+            .mapped(|r| r.get(0))
+            .finish_vec()
+            .context("Error querying shell history")?;
+
+        Ok(commands)
+    }
+
+    /// Get the most used commands, from any category
+    pub fn get_most_used_commands(&self) -> Result<Vec<Command>> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        let mut stmt = conn.prepare(
+            r#"SELECT rowid, category, alias, cmd, description, usage, last_used
+            FROM command
+            WHERE profile = ? AND usage > 0
+            ORDER BY usage DESC, (CASE WHEN category = 'user' OR substr(category, 1, 5) = 'user/' THEN 1 ELSE 0 END) DESC"#,
+        )?;
+
+        let commands = stmt
+            .query([&self.profile])?
+            .mapped(command_from_row)
+            .finish_vec()
+            .context("Error querying most used commands")?;
+
+        Ok(commands)
+    }
+
+    /// Finds commands matching the given search criteria
+    pub fn find_commands(&self, search: impl AsRef<str>) -> Result<Vec<Command>> {
+        Ok(self
+            .find_ranked_commands(search, false)?
+            .into_iter()
+            .map(|(c, _)| c)
+            .collect())
+    }
+
+    /// Finds commands matching any of the terms of the given search criteria, instead of all of them
+    pub fn find_commands_relaxed(&self, search: impl AsRef<str>) -> Result<Vec<Command>> {
+        Ok(self
+            .find_ranked_commands(search, true)?
+            .into_iter()
+            .map(|(c, _)| c)
+            .collect())
+    }
+
+    /// Finds commands matching the given search criteria (or any of its terms, when relaxed) along with how they
+    /// matched, ranked by that first and then by their usage, preferring user commands
+    pub fn find_ranked_commands(&self, search: impl AsRef<str>, relaxed: bool) -> Result<Vec<(Command, SearchMatch)>> {
+        let search = search.as_ref().trim();
+        let listed = |commands: Vec<Command>| commands.into_iter().map(|c| (c, SearchMatch::Listed)).collect();
+        if search.is_empty() {
+            return Ok(listed(self.get_commands_under(USER_CATEGORY)?));
+        }
+        let flat_search = flatten_str(search);
+
+        let conn = self.conn.lock().expect("poisoned lock");
+        let alias_cmd = conn
+            .query_row(
+                r#"SELECT rowid, category, alias, cmd, description, usage, last_used
+                FROM command
+                WHERE profile = :profile AND (alias = :flat_search OR alias = :search)"#,
+                &[
+                    (":profile", self.profile.as_str()),
+                    (":flat_search", flat_search.as_str()),
+                    (":search", search),
+                ],
+                command_from_row,
+            )
+            .optional()
+            .context("Error querying command by alias")?;
+        if let Some(cmd) = alias_cmd {
+            return Ok(vec![(cmd, SearchMatch::Alias)]);
+        }
+
+        let query = SearchQuery::parse(search);
+        let words = query.words();
+        let hashtags = query.terms.iter().filter(|t| t.starts_with('#')).collect_vec();
+
+        let flat_words = words.join(" ");
+        let flat_fts_search = ALLOWED_FTS_REGEX.replace_all(&flat_words, "");
+        let flat_fts_search = flat_fts_search.trim();
+        if flat_fts_search.is_empty() || flat_fts_search == " " {
+            drop(conn);
+            let mut commands = self.get_commands_under(USER_CATEGORY)?;
+            commands.retain(|c| query.accepts(c, relaxed));
+            let mut commands = listed(commands);
+            self.retain_with_values(&mut commands, &query.values)?;
+            return Ok(commands);
+        }
+
+        let mut stmt = conn.prepare(
+            r#"
+                    SELECT rowid, category, alias, cmd, description, usage, last_used, MAX(ord) as ord
+                    FROM (
+                        SELECT c.rowid, c.category, c.alias, c.cmd, c.description, c.usage, c.last_used, 3 as ord
+                        FROM command c
+                        WHERE c.profile = :profile AND c.alias GLOB :glob
+                    
+                        UNION ALL
+                        SELECT c.rowid, c.category, c.alias, c.cmd, c.description, c.usage, c.last_used, 2 as ord
+                        FROM command_fts s
+                        JOIN command c ON s.rowid = c.rowid
+                        WHERE c.profile = :profile AND command_fts MATCH :match_cmd_ordered
+                    
+                        UNION ALL
+                        
+                        SELECT c.rowid, c.category, c.alias, c.cmd, c.description, c.usage, c.last_used, 1 as ord
+                        FROM command_fts s
                         JOIN command c ON s.rowid = c.rowid
-                        WHERE s.flat_cmd GLOB :glob OR s.flat_description GLOB :glob
+                        WHERE c.profile = :profile AND command_fts MATCH :match_simple
+
+                        UNION ALL
+                        
+                        SELECT c.rowid, c.category, c.alias, c.cmd, c.description, c.usage, c.last_used, 0 as ord
+                        FROM command_fts s
+                        JOIN command c ON s.rowid = c.rowid
+                        WHERE c.profile = :profile AND (s.flat_cmd GLOB :glob OR s.flat_description GLOB :glob)
                     )
-                    ORDER BY ord DESC, usage DESC, (CASE WHEN category = 'user' THEN 1 ELSE 0 END) DESC
+                    GROUP BY rowid
+                    ORDER BY ord DESC, usage DESC, (CASE WHEN category = 'user' OR substr(category, 1, 5) = 'user/' THEN 1 ELSE 0 END) DESC
                 "#,
         )?;
 
@@ -354,24 +1652,26 @@ impl SqliteStorage {
         let match_simple = flat_fts_search
             .split_whitespace()
             .map(|token| format!("{token}*"))
-            .join(" ");
-        let glob = flat_search
-            .split_whitespace()
-            .map(|token| format!("*{token}*"))
-            .join(" ");
+            .join(if relaxed { " OR " } else { " " });
+        let glob = words.iter().map(|token| format!("*{}*", escape_glob(token))).join(" ");
 
         let commands = stmt
             .query(&[
+                (":profile", &self.profile),
                 (":match_cmd_ordered", &match_cmd_ordered),
                 (":match_simple", &match_simple),
                 (":glob", &glob),
             ])?
-            .mapped(command_from_row)
+            .mapped(|row| Ok((command_from_row(row)?, SearchMatch::from_tier(row.get(7)?))))
+            .filter(|r| r.as_ref().map(|(c, _)| query.accepts(c, relaxed)).unwrap_or(true))
             .filter(|r| {
                 if !hashtags.is_empty() {
-                    if let Ok(command) = r {
+                    if let Ok((command, _)) = r {
+                        if relaxed {
+                            return hashtags.iter().any(|tag| command.description.contains(tag.as_str()));
+                        }
                         for tag in &hashtags {
-                            if !command.description.contains(tag) {
+                            if !command.description.contains(tag.as_str()) {
                                 return false;
                             }
                         }
@@ -381,53 +1681,595 @@ impl SqliteStorage {
             })
             .finish_vec()
             .context("Error querying fts command")?;
+        drop(stmt);
+        drop(conn);
+
+        let mut commands = commands;
+        self.retain_with_values(&mut commands, &query.values)?;
+        Ok(commands)
+    }
+
+    /// Keeps only the commands having a label that was filled with a value containing each of the given (flattened)
+    /// ones, as recorded on the label suggestions
+    fn retain_with_values(&self, commands: &mut Vec<(Command, SearchMatch)>, values: &[String]) -> Result<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+        let suggestions = self.get_label_suggestions()?;
+        commands.retain(|(command, _)| {
+            let Some(labeled) = command.as_labeled_command() else {
+                return false;
+            };
+            let flat_root = flatten_str(&labeled.root);
+            let flat_labels = labeled.pending_labels().into_iter().map(flatten_str).collect_vec();
+            values.iter().all(|value| {
+                suggestions.iter().any(|s| {
+                    s.flat_root_cmd == flat_root
+                        && flat_labels.contains(&s.flat_label)
+                        && flatten_str(&s.suggestion).contains(value.as_str())
+                })
+            })
+        });
+        Ok(())
+    }
+
+    /// Finds a single command by its alias, id or exact command, in that order of preference
+    pub fn find_command(&self, key: impl AsRef<str>) -> Result<Option<Command>> {
+        let key = key.as_ref().trim();
+        let id = key.parse::<i64>().unwrap_or_default();
+
+        let conn = self.conn.lock().expect("poisoned lock");
+        let command = conn
+            .query_row(
+                r#"SELECT rowid, category, alias, cmd, description, usage, last_used
+                FROM command
+                WHERE profile = :profile AND (alias = :key OR rowid = :id OR cmd = :key)
+                ORDER BY (CASE WHEN alias = :key THEN 2 WHEN rowid = :id THEN 1 ELSE 0 END) DESC
+                LIMIT 1"#,
+                named_params! { ":profile": self.profile, ":key": key, ":id": id },
+                command_from_row,
+            )
+            .optional()
+            .context("Error querying command")?;
+
+        Ok(command)
+    }
+
+    /// Finds the most used command starting with the given prefix, excluding the prefix itself, that can be used on the
+    /// given shell
+    pub fn find_command_by_prefix(&self, prefix: impl AsRef<str>, shell: &str) -> Result<Option<Command>> {
+        Ok(self.find_commands_by_prefix(prefix, shell, 1)?.pop())
+    }
+
+    /// Finds the top-ranked commands starting with the given prefix, without being equal to it, that can be used on
+    /// the given shell
+    pub fn find_commands_by_prefix(&self, prefix: impl AsRef<str>, shell: &str, limit: usize) -> Result<Vec<Command>> {
+        let prefix = prefix.as_ref();
+        let conn = self.conn.lock().expect("poisoned lock");
+        let mut stmt = conn.prepare(
+            r#"SELECT rowid, category, alias, cmd, description, usage, last_used
+            FROM command
+            WHERE profile = :profile AND substr(cmd, 1, length(:prefix)) = :prefix AND cmd <> :prefix
+            ORDER BY usage DESC, (CASE WHEN category = 'user' OR substr(category, 1, 5) = 'user/' THEN 1 ELSE 0 END) DESC"#,
+        )?;
+
+        // The rows are filtered as they're read, so the commands for other shells don't take the place of the rest
+        let mut commands = Vec::new();
+        let mut rows = stmt
+            .query(named_params! { ":profile": self.profile, ":prefix": prefix })?
+            .mapped(command_from_row);
+        while commands.len() < limit {
+            let Some(command) = rows.next() else {
+                break;
+            };
+            let command = command.context("Error querying commands by prefix")?;
+            if command.is_compatible_with(shell) {
+                commands.push(command);
+            }
+        }
+
+        Ok(commands)
+    }
+
+    /// Finds the commands with an alias starting with the given prefix, most used first
+    pub fn find_commands_by_alias_prefix(&self, prefix: impl AsRef<str>) -> Result<Vec<Command>> {
+        let glob = format!("{}*", escape_glob(prefix.as_ref()));
+        let conn = self.conn.lock().expect("poisoned lock");
+        let mut stmt = conn.prepare(
+            r#"SELECT rowid, category, alias, cmd, description, usage, last_used
+            FROM command
+            WHERE profile = ? AND alias GLOB ?
+            ORDER BY usage DESC, alias ASC"#,
+        )?;
+
+        let commands = stmt
+            .query([&self.profile, &glob])?
+            .mapped(command_from_row)
+            .finish_vec()
+            .context("Error querying commands by alias")?;
+
+        Ok(commands)
+    }
+
+    /// Exports the commands from a given category (and its nested ones) into the given file path, on the given format.
+    ///
+    /// Label values can be included as well on the text format, so they can be imported back along with the commands.
+    ///
+    /// ## Returns
+    ///
+    /// The number of exported commands and label values
+    pub fn export(
+        &self,
+        category: impl AsRef<str>,
+        file_path: impl Into<String>,
+        format: ExportFormat,
+        include_values: bool,
+    ) -> Result<(usize, usize)> {
+        let category = category.as_ref();
+        let file_path = file_path.into();
+        if include_values && format != ExportFormat::Text {
+            bail!("Label values can only be exported on the text format");
+        }
+        let commands = self.get_commands_under(category)?;
+        let values = if include_values {
+            self.get_label_suggestions()?
+        } else {
+            Vec::new()
+        };
+        let file = fs::File::create(&file_path).context("Error creating output file")?;
+        let mut w = BufWriter::new(file);
+        w.write_all(format.format(&commands).as_bytes())
+            .context("Error writing file")?;
+        for value in &values {
+            writeln!(w, "{}", value.to_text()).context("Error writing file")?;
+        }
+        w.flush().context("Error writing file")?;
+        Ok((commands.len(), values.len()))
+    }
+
+    /// Exports the commands from a given category (and its nested ones) through the exporter of the given plugin,
+    /// which receives them as a JSON array along with the location, if any.
+    ///
+    /// ## Returns
+    ///
+    /// The number of exported commands and the output of the plugin
+    pub fn export_plugin(
+        &self,
+        category: impl AsRef<str>,
+        plugin: &str,
+        location: Option<&str>,
+    ) -> Result<(usize, String)> {
+        let commands = self.get_commands_under(category)?;
+        let output = export_plugin_commands(plugin, location, &ExportFormat::Json.format(&commands))?;
+        Ok((commands.len(), output))
+    }
+
+    /// Imports commands from the given file into a category.
+    ///
+    /// ## Returns
+    ///
+    /// The number of newly inserted commands, along with the ones merged into an equivalent command
+    pub fn import(&self, category: impl AsRef<str>, file_path: String) -> Result<ImportStats> {
+        let commands = read_import_file(category.as_ref(), file_path)?;
+        self.insert_imported_commands(commands, false)
+    }
+
+    /// Imports the label values found on the given file, exported along with the commands.
+    ///
+    /// ## Returns
+    ///
+    /// The number of newly inserted values
+    pub fn import_values(&self, file_path: impl AsRef<Path>) -> Result<u64> {
+        let mut new = 0;
+        for value in read_import_values(file_path)? {
+            if self.insert_label_suggestion(&value)? {
+                new += 1;
+            }
+        }
+        Ok(new)
+    }
+
+    /// Imports the commands shared on the given url into a category, along with the label values exported with them.
+    ///
+    /// ## Returns
+    ///
+    /// The number of newly inserted commands, along with the ones merged into an equivalent command, and the number of
+    /// newly inserted values
+    pub fn import_url(&self, category: impl AsRef<str>, url: impl AsRef<str>) -> Result<(ImportStats, u64)> {
+        // The content is downloaded just once, as pastes could be removed after being read
+        let content = download_cached(url.as_ref(), false)?;
+        let commands = parse_import_file(category.as_ref(), url.as_ref(), &content)?;
+        let stats = self.insert_imported_commands(commands, false)?;
+        let mut new = 0;
+        for value in parse_import_values(&content)? {
+            if self.insert_label_suggestion(&value)? {
+                new += 1;
+            }
+        }
+        Ok((stats, new))
+    }
+
+    /// Computes the changes that importing the commands shared on the given url into a category would produce,
+    /// without applying them
+    pub fn import_url_dry_run(&self, category: impl AsRef<str>, url: impl AsRef<str>) -> Result<Vec<ImportChange>> {
+        // The content is kept, so the actual import doesn't download it again
+        let content = download_cached(url.as_ref(), true)?;
+        let commands = parse_import_file(category.as_ref(), url.as_ref(), &content)?;
+        self.diff_imported_commands(commands, false)
+    }
+
+    /// Computes the changes that importing the given file into a category would produce, without applying them
+    pub fn import_dry_run(&self, category: impl AsRef<str>, file_path: String) -> Result<Vec<ImportChange>> {
+        let commands = read_import_file(category.as_ref(), file_path)?;
+        self.diff_imported_commands(commands, false)
+    }
+
+    /// Imports the commands found on the shell code blocks of the markdown files within the given directory into a
+    /// category, described by their nearest heading and tagged after their file path.
+    ///
+    /// ## Returns
+    ///
+    /// The number of newly inserted commands, along with the ones merged into an equivalent command
+    pub fn import_dir(&self, category: impl AsRef<str>, dir: impl AsRef<Path>) -> Result<ImportStats> {
+        let commands = read_markdown_commands(category.as_ref(), dir)?;
+        self.insert_imported_commands(commands, false)
+    }
+
+    /// Computes the changes that importing the given markdown directory into a category would produce, without
+    /// applying them
+    pub fn import_dir_dry_run(&self, category: impl AsRef<str>, dir: impl AsRef<Path>) -> Result<Vec<ImportChange>> {
+        let commands = read_markdown_commands(category.as_ref(), dir)?;
+        self.diff_imported_commands(commands, false)
+    }
+
+    /// Imports the connection commands of the hosts found on the given ssh config file (and the `known_hosts` file next
+    /// to it) into a category. Hosts named like the alias of another command are imported without it.
+    ///
+    /// ## Returns
+    ///
+    /// The number of newly inserted commands, along with the ones merged into an equivalent command
+    pub fn import_ssh(&self, category: impl AsRef<str>, config_file: impl AsRef<Path>) -> Result<ImportStats> {
+        let commands = self.without_taken_aliases(read_ssh_commands(category.as_ref(), config_file)?)?;
+        self.insert_imported_commands(commands, false)
+    }
+
+    /// Computes the changes that importing the hosts of the given ssh config file into a category would produce,
+    /// without applying them
+    pub fn import_ssh_dry_run(
+        &self,
+        category: impl AsRef<str>,
+        config_file: impl AsRef<Path>,
+    ) -> Result<Vec<ImportChange>> {
+        let commands = self.without_taken_aliases(read_ssh_commands(category.as_ref(), config_file)?)?;
+        self.diff_imported_commands(commands, false)
+    }
+
+    /// Removes the aliases of the given commands that are already taken by other stored commands, so they keep
+    /// resolving to the same command
+    fn without_taken_aliases(&self, mut commands: Vec<Command>) -> Result<Vec<Command>> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        let mut stmt = conn.prepare(r#"SELECT 1 FROM command WHERE profile = ? AND alias = ? AND cmd <> ?"#)?;
+        for command in &mut commands {
+            if let Some(alias) = &command.alias {
+                if stmt
+                    .exists((&self.profile, alias, &command.cmd))
+                    .context("Error querying command by alias")?
+                {
+                    command.alias = None;
+                }
+            }
+        }
+        Ok(commands)
+    }
+
+    /// Imports the examples and long options found on the help of the given tool (its `--help` output or its man page)
+    /// into a category, tagged with the tool name.
+    ///
+    /// ## Returns
+    ///
+    /// The number of newly inserted commands, along with the ones merged into an equivalent command
+    pub fn import_help(&self, category: impl AsRef<str>, tool: impl AsRef<str>) -> Result<ImportStats> {
+        let commands = read_tool_commands(category.as_ref(), tool.as_ref())?;
+        self.insert_imported_commands(commands, false)
+    }
+
+    /// Computes the changes that importing the help of the given tool into a category would produce, without applying
+    /// them
+    pub fn import_help_dry_run(&self, category: impl AsRef<str>, tool: impl AsRef<str>) -> Result<Vec<ImportChange>> {
+        let commands = read_tool_commands(category.as_ref(), tool.as_ref())?;
+        self.diff_imported_commands(commands, false)
+    }
+
+    /// Imports the commands produced by the importer of the given plugin, from the given location if any, into a
+    /// category.
+    ///
+    /// ## Returns
+    ///
+    /// The number of newly inserted commands, along with the ones merged into an equivalent command
+    pub fn import_plugin(
+        &self,
+        category: impl AsRef<str>,
+        plugin: &str,
+        location: Option<&str>,
+    ) -> Result<ImportStats> {
+        let commands = read_plugin_commands(category.as_ref(), plugin, location)?;
+        self.insert_imported_commands(commands, false)
+    }
+
+    /// Computes the changes that importing through the given plugin into a category would produce, without applying
+    /// them
+    pub fn import_plugin_dry_run(
+        &self,
+        category: impl AsRef<str>,
+        plugin: &str,
+        location: Option<&str>,
+    ) -> Result<Vec<ImportChange>> {
+        let commands = read_plugin_commands(category.as_ref(), plugin, location)?;
+        self.diff_imported_commands(commands, false)
+    }
+
+    /// Syncs the vault category with the shell code blocks of the markdown files within the given directory, if they've
+    /// changed since the last sync.
+    ///
+    /// Commands no longer found are removed, while the ones already stored on other categories are left untouched.
+    ///
+    /// ## Returns
+    ///
+    /// Wether the vault had changed and was synced
+    pub fn sync_vault(&self, dir: impl AsRef<Path>) -> Result<bool> {
+        let dir = dir.as_ref();
+        self.sync_category(VAULT_CATEGORY, markdown_dir_fingerprint(dir)?, || {
+            read_markdown_commands(VAULT_CATEGORY, dir)
+        })
+    }
+
+    /// Syncs the read-only library category with the commands of the given shared file (on any of the import
+    /// formats), if it has changed since the last sync.
+    ///
+    /// Commands no longer found are removed, while the ones already stored on other categories are left untouched.
+    ///
+    /// ## Returns
+    ///
+    /// Wether the library had changed and was synced
+    pub fn sync_library(&self, file: impl AsRef<Path>) -> Result<bool> {
+        let file = file.as_ref();
+        self.sync_category(LIBRARY_CATEGORY, file_fingerprint(file)?, || {
+            read_import_file(LIBRARY_CATEGORY, file.to_string_lossy().into_owned())
+        })
+    }
+
+    /// Syncs a category with the commands read from its source, if its fingerprint has changed since the last sync.
+    ///
+    /// The whole sync happens on a single write transaction, so the category is never left half-synced.
+    fn sync_category(
+        &self,
+        category: &str,
+        fingerprint: String,
+        read_commands: impl FnOnce() -> Result<Vec<Command>>,
+    ) -> Result<bool> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        let synced_fingerprint: Option<String> = conn
+            .query_row(
+                r#"SELECT fingerprint FROM sync_state WHERE profile = ? AND source = ?"#,
+                [&self.profile, category],
+                |r| r.get(0),
+            )
+            .optional()
+            .context("Error querying sync state")?;
+        if synced_fingerprint.as_ref() == Some(&fingerprint) {
+            return Ok(false);
+        }
+
+        let commands = read_commands()?;
+        let source_cmds: HashSet<String> = commands.iter().map(|c| c.cmd.clone()).collect();
+
+        let tx = write_transaction(&conn)?;
+
+        // Remove the commands no longer on the source
+        let stored = tx
+            .prepare(r#"SELECT rowid, cmd FROM command WHERE profile = ? AND category = ?"#)?
+            .query([&self.profile, category])?
+            .mapped(|row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+            .finish_vec()
+            .context("Error querying commands")?;
+        for (id, cmd) in stored {
+            if !source_cmds.contains(&cmd) {
+                delete_command_rows(&tx, id)?;
+            }
+        }
+
+        // Insert or update the rest, skipping the ones on other categories
+        let mut commands = self
+            .diff_commands_in(&tx, commands, false)?
+            .into_iter()
+            .filter_map(|change| match change {
+                ImportChange::New(command) => Some(command),
+                ImportChange::Updated { current, description } => {
+                    Some(Command::new(category, current.cmd, description))
+                }
+                ImportChange::Conflict { .. } | ImportChange::Unchanged(_) | ImportChange::Merged { .. } => None,
+            })
+            .collect_vec();
+        self.upsert_commands_in(&tx, &mut commands, false, &[])?;
 
-        Ok(commands)
+        tx.execute(
+            r#"INSERT INTO sync_state (profile, source, fingerprint) VALUES (?, ?, ?)
+            ON CONFLICT(profile, source) DO UPDATE SET fingerprint = excluded.fingerprint"#,
+            [&self.profile, category, &fingerprint],
+        )
+        .context("Error updating sync state")?;
+
+        tx.commit()?;
+
+        Ok(true)
     }
 
-    /// Exports the commands from a given category into the given file path
+    /// Imports the commands from the given shell history file into a category, preserving how many times each one
+    /// was run as its usage.
     ///
     /// ## Returns
     ///
-    /// The number of exported commands
-    pub fn export(&self, category: impl AsRef<str>, file_path: impl Into<String>) -> Result<usize> {
-        let category = category.as_ref();
-        let file_path = file_path.into();
-        let commands = self.get_commands(category)?;
-        let size = commands.len();
-        let file = fs::File::create(&file_path).context("Error creating output file")?;
-        let mut w = BufWriter::new(file);
+    /// The number of newly inserted commands, along with the ones merged into an equivalent command
+    pub fn import_history(&self, category: impl AsRef<str>, file_path: impl AsRef<Path>) -> Result<ImportStats> {
+        let commands = read_history_file(category.as_ref(), file_path)?;
+        self.insert_imported_commands(commands, true)
+    }
+
+    /// Computes the changes that importing the given shell history file into a category would produce, without
+    /// applying them
+    pub fn import_history_dry_run(
+        &self,
+        category: impl AsRef<str>,
+        file_path: impl AsRef<Path>,
+    ) -> Result<Vec<ImportChange>> {
+        let commands = read_history_file(category.as_ref(), file_path)?;
+        self.diff_imported_commands(commands, true)
+    }
+
+    /// Inserts the given imported commands, merging the ones equivalent to another command first
+    fn insert_imported_commands(&self, commands: Vec<Command>, with_usage: bool) -> Result<ImportStats> {
+        let (mut commands, merged) = self.merge_equivalent_commands(commands)?;
+        let new = self.upsert_commands(&mut commands, with_usage, &merged)?;
+        Ok(ImportStats {
+            new,
+            merged: merged.len() as u64,
+        })
+    }
+
+    /// Compares the given imported commands against the stored ones, merging the ones equivalent to another command
+    /// first
+    fn diff_imported_commands(&self, commands: Vec<Command>, keep_description: bool) -> Result<Vec<ImportChange>> {
+        let (commands, merged) = self.merge_equivalent_commands(commands)?;
+        let mut changes = self.diff_commands(commands, keep_description)?;
+        changes.extend(
+            merged
+                .into_iter()
+                .map(|(command, equivalent)| ImportChange::Merged { command, equivalent }),
+        );
+        Ok(changes)
+    }
+
+    /// Drops the imported commands equivalent to a stored one of the same category or to a previous imported one, once
+    /// normalized (or matching a stored labeled command, if enabled). The commands stored exactly as they are imported
+    /// are kept, to be updated as usual.
+    ///
+    /// Returns the kept commands along with the merged ones and the command they're equivalent to. The usage of the
+    /// commands merged into a previous imported one is added to it, as well as their alias and description if missing.
+    fn merge_equivalent_commands(&self, commands: Vec<Command>) -> Result<(Vec<Command>, MergedCommands)> {
+        let stored = {
+            let conn = self.conn.lock().expect("poisoned lock");
+            let mut stmt = conn.prepare(r#"SELECT cmd, category FROM command WHERE profile = ?"#)?;
+            let cmds = stmt
+                .query([&self.profile])?
+                .mapped(|row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+                .finish_vec()
+                .context("Error querying commands")?;
+            cmds
+        };
+        let exact: HashSet<&str> = stored.iter().map(|(cmd, _)| cmd.as_str()).collect();
+        let normalized: HashMap<(&str, String), &str> = stored
+            .iter()
+            .map(|(cmd, category)| ((category.as_str(), normalize_command(cmd)), cmd.as_str()))
+            .collect();
+        let templates = if self.import_template_match {
+            stored
+                .iter()
+                .filter_map(|(cmd, category)| Some((cmd.as_labeled_command()?, category.as_str(), cmd.as_str())))
+                .collect_vec()
+        } else {
+            Vec::new()
+        };
+
+        let mut kept: Vec<Command> = Vec::new();
+        let mut kept_normalized: HashMap<(String, String), usize> = HashMap::new();
+        let mut merged = Vec::new();
         for command in commands {
-            writeln!(w, "{} ## {}", command.cmd, command.description).context("Error writing file")?;
+            let key = (command.category.clone(), normalize_command(&command.cmd));
+            if exact.contains(command.cmd.as_str()) {
+                kept.push(command);
+            } else if let Some(equivalent) = normalized.get(&(key.0.as_str(), key.1.clone())) {
+                merged.push((command, equivalent.to_string()));
+            } else if let Some(ix) = kept_normalized.get(&key) {
+                let previous = &mut kept[*ix];
+                previous.usage += command.usage;
+                if previous.alias.is_none() {
+                    previous.alias = command.alias.clone();
+                }
+                if previous.description.is_empty() {
+                    previous.description = command.description.clone();
+                }
+                merged.push((command, previous.cmd.clone()));
+            } else if let Some((_, _, template)) = templates
+                .iter()
+                .find(|(t, category, _)| *category == command.category && t.matches(&command.cmd))
+            {
+                merged.push((command, template.to_string()));
+            } else {
+                kept_normalized.insert(key, kept.len());
+                kept.push(command);
+            }
         }
-        w.flush().context("Error writing file")?;
-        Ok(size)
+        Ok((kept, merged))
     }
 
-    /// Imports commands from the given file into a category.
-    ///
-    /// ## Returns
-    ///
-    /// The number of newly inserted commands
-    pub fn import(&self, category: impl AsRef<str>, file_path: String) -> Result<u64> {
-        let category = category.as_ref();
-        let file = fs::File::open(file_path).context("Error opening file")?;
-        let r = BufReader::new(file);
-        let mut commands = r
-            .lines()
-            .map_err(anyhow::Error::from)
-            .filter_ok(|line| !line.is_empty() && !line.starts_with('#'))
-            .and_then(|line| {
-                let (cmd, description) = line
-                    .split_once(" ## ")
-                    .ok_or_else(|| anyhow!("Unexpected file format"))?;
-                Ok::<_, anyhow::Error>(Command::new(category, cmd, description))
-            })
-            .finish_vec()?;
+    /// Compares the given commands against the stored ones
+    fn diff_commands(&self, commands: Vec<Command>, keep_description: bool) -> Result<Vec<ImportChange>> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        self.diff_commands_in(&conn, commands, keep_description)
+    }
 
-        let new = self.insert_commands(&mut commands)?;
+    /// Compares the given commands against the stored ones on the given connection, following the same rules as
+    /// [SqliteStorage::upsert_commands]: repeated commands are applied once (with the last description, unless kept) and
+    /// the description of the library commands is only updated by the library itself
+    fn diff_commands_in(
+        &self,
+        conn: &Connection,
+        commands: Vec<Command>,
+        keep_description: bool,
+    ) -> Result<Vec<ImportChange>> {
+        let mut unique: Vec<Command> = Vec::with_capacity(commands.len());
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for command in commands {
+            match positions.get(&command.cmd) {
+                Some(&ix) if !keep_description => unique[ix].description = command.description,
+                Some(_) => (),
+                None => {
+                    positions.insert(command.cmd.clone(), unique.len());
+                    unique.push(command);
+                }
+            }
+        }
 
-        Ok(new)
+        let mut stmt = conn.prepare(
+            r#"SELECT rowid, category, alias, cmd, description, usage, last_used
+            FROM command
+            WHERE profile = ? AND cmd = ?"#,
+        )?;
+
+        unique
+            .into_iter()
+            .map(|command| {
+                let current = stmt
+                    .query_row((&self.profile, &command.cmd), command_from_row)
+                    .optional()
+                    .context("Error querying command")?;
+                let Some(current) = current else {
+                    return Ok(ImportChange::New(command));
+                };
+                let protected = current.category == LIBRARY_CATEGORY && command.category != LIBRARY_CATEGORY;
+                let description = (!keep_description && !protected && current.description != command.description)
+                    .then_some(command.description);
+                Ok(match description {
+                    _ if current.category != command.category => ImportChange::Conflict {
+                        current,
+                        category: command.category,
+                        description,
+                    },
+                    Some(description) => ImportChange::Updated { current, description },
+                    None => ImportChange::Unchanged(current),
+                })
+            })
+            .collect()
     }
 
     /// Determines if the store is empty (no commands stored)
@@ -435,11 +2277,34 @@ impl SqliteStorage {
         Ok(self.len()? == 0)
     }
 
-    /// Returns the number of stored commands
+    /// Returns the number of stored commands on the current profile
     pub fn len(&self) -> Result<u64> {
         let conn = self.conn.lock().expect("poisoned lock");
-        let mut stmt = conn.prepare(r#"SELECT COUNT(*) FROM command"#)?;
-        Ok(stmt.query_row([], |r| r.get(0))?)
+        let mut stmt = conn.prepare(r#"SELECT COUNT(*) FROM command WHERE profile = ?"#)?;
+        Ok(stmt.query_row([&self.profile], |r| r.get(0))?)
+    }
+
+    /// Retrieves every label suggestion stored
+    pub fn get_label_suggestions(&self) -> Result<Vec<LabelSuggestion>> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        let mut stmt = conn.prepare(
+            r#"SELECT flat_root_cmd, flat_label, suggestion, usage FROM label_suggestion 
+            WHERE profile = ?
+            ORDER BY flat_root_cmd, flat_label, usage DESC"#,
+        )?;
+        let suggestions = stmt
+            .query_map([&self.profile], |row| {
+                Ok(LabelSuggestion {
+                    flat_root_cmd: row.get(0)?,
+                    flat_label: row.get(1)?,
+                    suggestion: row.get(2)?,
+                    usage: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Error querying label suggestions")?;
+
+        Ok(suggestions)
     }
 
     /// Inserts a label suggestion if it doesn't exists.
@@ -452,8 +2317,10 @@ impl SqliteStorage {
 
         let conn = self.conn.lock().expect("poisoned lock");
         let inserted = match conn.execute(
-            r#"INSERT INTO label_suggestion (flat_root_cmd, flat_label, suggestion, usage) VALUES (?, ?, ?, ?)"#,
+            r#"INSERT INTO label_suggestion (profile, flat_root_cmd, flat_label, suggestion, usage)
+            VALUES (?, ?, ?, ?, ?)"#,
             (
+                &self.profile,
                 &suggestion.flat_root_cmd,
                 &suggestion.flat_label,
                 &suggestion.suggestion,
@@ -489,9 +2356,11 @@ impl SqliteStorage {
         let new_suggestion = new_suggestion.into();
         let updated = conn
             .execute(
-                r#"UPDATE label_suggestion SET suggestion = ? WHERE flat_root_cmd = ? AND flat_label = ? AND suggestion = ?"#,
+                r#"UPDATE label_suggestion SET suggestion = ?
+                WHERE profile = ? AND flat_root_cmd = ? AND flat_label = ? AND suggestion = ?"#,
                 (
                     &new_suggestion,
+                    &self.profile,
                     &suggestion.flat_root_cmd,
                     &suggestion.flat_label,
                     &suggestion.suggestion,
@@ -515,9 +2384,11 @@ impl SqliteStorage {
         let conn = self.conn.lock().expect("poisoned lock");
         let updated = conn
             .execute(
-                r#"UPDATE label_suggestion SET usage = ? WHERE flat_root_cmd = ? AND flat_label = ? AND suggestion = ?"#,
+                r#"UPDATE label_suggestion SET usage = ?
+                WHERE profile = ? AND flat_root_cmd = ? AND flat_label = ? AND suggestion = ?"#,
                 (
                     suggestion.usage,
+                    &self.profile,
                     &suggestion.flat_root_cmd,
                     &suggestion.flat_label,
                     &suggestion.suggestion,
@@ -528,35 +2399,203 @@ impl SqliteStorage {
         Ok(updated == 1)
     }
 
-    /// Deletes an existing label suggestion
+    /// Deletes an existing label suggestion, along with the context it was used on
     ///
     /// Returns wether the suggestion exists and was deleted or not.
     pub fn delete_label_suggestion(&self, suggestion: &LabelSuggestion) -> Result<bool> {
+        Ok(self.take_label_suggestion(suggestion)?.is_some())
+    }
+
+    /// Deletes an existing label suggestion like [delete_label_suggestion](SqliteStorage::delete_label_suggestion),
+    /// but returning it along with its context, so it can be restored later
+    pub fn take_label_suggestion(&self, suggestion: &LabelSuggestion) -> Result<Option<DeletedLabelSuggestion>> {
         let conn = self.conn.lock().expect("poisoned lock");
-        let deleted = conn
+        let tx = write_transaction(&conn)?;
+        let key = (
+            &self.profile,
+            &suggestion.flat_root_cmd,
+            &suggestion.flat_label,
+            &suggestion.suggestion,
+        );
+
+        let context = tx
+            .prepare(
+                r#"SELECT context_label, context_value, usage FROM label_suggestion_context
+                WHERE profile = ? AND flat_root_cmd = ? AND flat_label = ? AND suggestion = ?"#,
+            )?
+            .query(key)?
+            .mapped(|r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+            .finish_vec()
+            .context("Error querying label suggestion context")?;
+        let deleted = tx
+            .execute(
+                r#"DELETE FROM label_suggestion
+                WHERE profile = ? AND flat_root_cmd = ? AND flat_label = ? AND suggestion = ?"#,
+                key,
+            )
+            .context("Error deleting label suggestion")?;
+        if deleted == 0 {
+            return Ok(None);
+        }
+        tx.execute(
+            r#"DELETE FROM label_suggestion_context
+            WHERE profile = ? AND flat_root_cmd = ? AND flat_label = ? AND suggestion = ?"#,
+            key,
+        )
+        .context("Error deleting label suggestion context")?;
+        tx.commit()?;
+
+        Ok(Some(DeletedLabelSuggestion {
+            suggestion: suggestion.clone(),
+            context,
+        }))
+    }
+
+    /// Restores a label suggestion previously taken, along with its context, so it ranks as before.
+    ///
+    /// Returns wether the suggestion was restored or not (an equal one was stored since).
+    pub fn restore_label_suggestion(&self, deleted: &DeletedLabelSuggestion) -> Result<bool> {
+        let suggestion = &deleted.suggestion;
+        let conn = self.conn.lock().expect("poisoned lock");
+        let tx = write_transaction(&conn)?;
+
+        let restored = tx
             .execute(
-                r#"DELETE FROM label_suggestion WHERE flat_root_cmd = ? AND flat_label = ? AND suggestion = ?"#,
+                r#"INSERT OR IGNORE INTO label_suggestion (profile, flat_root_cmd, flat_label, suggestion, usage)
+                VALUES (?, ?, ?, ?, ?)"#,
                 (
+                    &self.profile,
                     &suggestion.flat_root_cmd,
                     &suggestion.flat_label,
                     &suggestion.suggestion,
+                    suggestion.usage,
                 ),
             )
-            .context("Error deleting label suggestion")?;
+            .context("Error restoring label suggestion")?;
+        if restored == 0 {
+            return Ok(false);
+        }
+        for (context_label, context_value, usage) in &deleted.context {
+            tx.execute(
+                r#"INSERT INTO label_suggestion_context
+                    (profile, flat_root_cmd, flat_label, suggestion, context_label, context_value, usage)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT(profile, flat_root_cmd, flat_label, suggestion, context_label, context_value)
+                DO UPDATE SET usage = usage + excluded.usage"#,
+                (
+                    &self.profile,
+                    &suggestion.flat_root_cmd,
+                    &suggestion.flat_label,
+                    &suggestion.suggestion,
+                    context_label,
+                    context_value,
+                    usage,
+                ),
+            )
+            .context("Error restoring label suggestion context")?;
+        }
+        tx.commit()?;
 
-        Ok(deleted == 1)
+        Ok(true)
+    }
+
+    /// Retrieves the suggestions cached for a label provider, unless they're older than the given age
+    pub fn get_provider_cache(&self, provider: &str, max_age: Duration) -> Result<Option<Vec<String>>> {
+        let fetched_since = unix_timestamp().saturating_sub(max_age.as_secs());
+        let conn = self.conn.lock().expect("poisoned lock");
+        let suggestions: Option<String> = conn
+            .query_row(
+                r#"SELECT suggestions FROM provider_cache WHERE profile = ? AND provider = ? AND fetched_at >= ?"#,
+                (&self.profile, provider, fetched_since),
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Error querying provider cache")?;
+
+        Ok(suggestions.map(|s| s.lines().map(ToOwned::to_owned).collect()))
+    }
+
+    /// Caches the suggestions of a label provider, replacing the previous ones
+    pub fn set_provider_cache(&self, provider: &str, suggestions: &[String]) -> Result<()> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        conn.execute(
+            r#"INSERT OR REPLACE INTO provider_cache (profile, provider, suggestions, fetched_at) VALUES (?, ?, ?, ?)"#,
+            (&self.profile, provider, suggestions.join("\n"), unix_timestamp()),
+        )
+        .context("Error caching provider suggestions")?;
+
+        Ok(())
+    }
+
+    /// Discards the suggestions cached for a label provider
+    pub fn clear_provider_cache(&self, provider: &str) -> Result<()> {
+        let conn = self.conn.lock().expect("poisoned lock");
+        conn.execute(
+            r#"DELETE FROM provider_cache WHERE profile = ? AND provider = ?"#,
+            (&self.profile, provider),
+        )
+        .context("Error clearing provider cache")?;
+
+        Ok(())
+    }
+
+    /// Records that a label of a command was filled with the given suggestion along with the values of the labels
+    /// filled before it, so the suggestions used together are ranked first next time
+    pub fn record_label_context(
+        &self,
+        root_cmd: impl AsRef<str>,
+        label: impl AsRef<str>,
+        suggestion: impl AsRef<str>,
+        context: &[(String, String)],
+    ) -> Result<()> {
+        if context.is_empty() {
+            return Ok(());
+        }
+        let flat_root_cmd = flatten_str(root_cmd.as_ref());
+        let flat_label = flatten_str(label.as_ref());
+        let suggestion = suggestion.as_ref();
+
+        let conn = self.conn.lock().expect("poisoned lock");
+        let tx = write_transaction(&conn)?;
+        {
+            let mut stmt = tx.prepare(
+                r#"INSERT INTO label_suggestion_context 
+                    (profile, flat_root_cmd, flat_label, suggestion, context_label, context_value, usage) 
+                VALUES (?, ?, ?, ?, ?, ?, 1)
+                ON CONFLICT(profile, flat_root_cmd, flat_label, suggestion, context_label, context_value)
+                DO UPDATE SET usage = usage + 1"#,
+            )?;
+            for (context_label, context_value) in context {
+                stmt.execute((
+                    &self.profile,
+                    &flat_root_cmd,
+                    &flat_label,
+                    suggestion,
+                    flatten_str(context_label),
+                    context_value,
+                ))
+                .context("Error recording label context")?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(())
     }
 
-    /// Finds label suggestions for the given root command and label
+    /// Finds label suggestions for the given root command and label.
+    ///
+    /// The suggestions used along with the values of the labels already filled (the context) are ranked first.
     pub fn find_suggestions_for(
         &self,
         root_cmd: impl AsRef<str>,
         label: impl AsRef<str>,
+        context: &[(String, String)],
     ) -> Result<Vec<LabelSuggestion>> {
         let flat_root_cmd = flatten_str(root_cmd.as_ref());
         let label = label.as_ref();
         let mut parameters = label.split('|').map(str::trim).map(flatten_str).collect_vec();
         parameters.insert(0, flatten_str(label));
+        let labels_count = parameters.len();
 
         const QUERY: &str = r#"
             SELECT * FROM (
@@ -566,6 +2605,7 @@ impl SqliteStorage {
                     s.suggestion, 
                     s.usage, 
                     q.sum_usage,
+                    COALESCE(c.context_usage, 0) as context_usage,
                     RANK () OVER ( 
                         PARTITION BY s.suggestion
                         ORDER BY LENGTH(s.flat_label) DESC
@@ -574,29 +2614,48 @@ impl SqliteStorage {
                 JOIN (
                     SELECT flat_root_cmd, suggestion, SUM(usage) as sum_usage
                     FROM label_suggestion
-                    WHERE flat_root_cmd = ?1 AND flat_label IN (#LABELS#)
+                    WHERE profile = ?1 AND flat_root_cmd = ?2 AND flat_label IN (#LABELS#)
                     GROUP BY flat_root_cmd, suggestion
                 ) q ON s.flat_root_cmd = q.flat_root_cmd AND s.suggestion = q.suggestion
+                LEFT JOIN (
+                    SELECT suggestion, SUM(usage) as context_usage
+                    FROM label_suggestion_context
+                    WHERE profile = ?1 AND flat_root_cmd = ?2 AND flat_label IN (#LABELS#) AND (#CONTEXT#)
+                    GROUP BY suggestion
+                ) c ON s.suggestion = c.suggestion
+                WHERE s.profile = ?1
             )
             WHERE rank = 1
             ORDER BY 
+                context_usage DESC,
                 sum_usage DESC, 
-                (CASE WHEN flat_label = ?2 THEN 1 ELSE 0 END) DESC
+                (CASE WHEN flat_label = ?3 THEN 1 ELSE 0 END) DESC
         "#;
 
+        let context_filter = if context.is_empty() {
+            "0".to_owned()
+        } else {
+            (0..context.len())
+                .map(|i| {
+                    let ix = labels_count + 3 + i * 2;
+                    format!("(context_label = ?{ix} AND context_value = ?{})", ix + 1)
+                })
+                .join(" OR ")
+        };
+
         let conn = self.conn.lock().expect("poisoned lock");
         let mut stmt = conn.prepare(
-            &QUERY.replace(
-                "#LABELS#",
-                &parameters
-                    .iter()
-                    .enumerate()
-                    .map(|(i, _)| format!("?{}", i + 2))
-                    .join(","),
-            ),
+            &QUERY
+                .replace("#LABELS#", &(0..labels_count).map(|i| format!("?{}", i + 3)).join(","))
+                .replace("#CONTEXT#", &context_filter),
         )?;
 
         parameters.insert(0, flat_root_cmd);
+        parameters.insert(0, self.profile.clone());
+        for (context_label, context_value) in context {
+            parameters.push(flatten_str(context_label));
+            parameters.push(context_value.clone());
+        }
 
         let suggestions = stmt
             .query(params_from_iter(parameters.iter()))?
@@ -608,6 +2667,208 @@ impl SqliteStorage {
     }
 }
 
+/// Retrieves the system-wide library file, `/etc/.intellishell`, if it exists and is readable
+pub fn default_library_file() -> Option<PathBuf> {
+    let file = PathBuf::from("/etc/.intellishell");
+    // It's skipped when not readable by the current user, like a root-owned file without read permission
+    (cfg!(unix) && file.is_file() && fs::File::open(&file).is_ok()).then_some(file)
+}
+
+/// Computes the fingerprint of a file from its path, size and modification time, to detect its changes
+fn file_fingerprint(file: &Path) -> Result<String> {
+    let metadata = fs::metadata(file).with_context(|| format!("Error reading {}", file.display()))?;
+    let mut hasher = DefaultHasher::new();
+    file.hash(&mut hasher);
+    metadata.len().hash(&mut hasher);
+    metadata.modified().ok().hash(&mut hasher);
+    Ok(format!("{:x}", hasher.finish()))
+}
+
+/// Reads the commands of an import file, where each line is formatted as `cmd ## description`, optionally preceded by
+/// an alias header
+fn read_import_file(category: &str, file_path: String) -> Result<Vec<Command>> {
+    let content = fs::read_to_string(&file_path).context("Error reading file")?;
+    parse_import_file(category, &file_path, &content)
+}
+
+/// Parses the commands of the content of an import file, see [read_import_file]
+fn parse_import_file(category: &str, file_path: &str, content: &str) -> Result<Vec<Command>> {
+    // navi cheats and pet snippets are detected and converted as well
+    if let Some(format) = SnippetFormat::detect(file_path, content) {
+        return Ok(format
+            .parse(content)?
+            .into_iter()
+            .map(|(cmd, description)| Command::new(category, cmd, description))
+            .collect_vec());
+    }
+    let mut commands = Vec::new();
+    let mut alias = None;
+    let mut nested_category = None;
+    for line in content.lines() {
+        if let Some(header) = line.strip_prefix(ALIAS_HEADER) {
+            alias = Some(header.trim().to_owned()).filter(|a| !a.is_empty());
+            continue;
+        }
+        // Nested categories are kept, but commands can't be moved out of the one being imported into
+        if let Some(header) = line.strip_prefix(CATEGORY_HEADER) {
+            nested_category = Some(header.trim().to_owned()).filter(|c| is_within_category(c, category));
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (cmd, description) = line
+            .split_once(" ## ")
+            .ok_or_else(|| UserFacingError::parse("Unexpected file format"))?;
+        commands.push(Command {
+            alias: alias.take(),
+            ..Command::new(nested_category.take().as_deref().unwrap_or(category), cmd, description)
+        });
+    }
+    Ok(commands)
+}
+
+/// Reads the label values of a file on the text format
+fn read_import_values(file_path: impl AsRef<Path>) -> Result<Vec<LabelSuggestion>> {
+    let content = fs::read_to_string(file_path).context("Error reading file")?;
+    parse_import_values(&content)
+}
+
+/// Parses the label values of the content of a file on the text format
+fn parse_import_values(content: &str) -> Result<Vec<LabelSuggestion>> {
+    let mut values = Vec::new();
+    for line in content.lines() {
+        if line.starts_with(VALUE_HEADER) {
+            values.push(
+                LabelSuggestion::from_text(line)
+                    .ok_or_else(|| UserFacingError::parse("Unexpected label value format"))?,
+            );
+        }
+    }
+    Ok(values)
+}
+
+/// Reads the commands of a shell history file, with their usage
+fn read_history_file(category: &str, file_path: impl AsRef<Path>) -> Result<Vec<Command>> {
+    let content = fs::read(file_path).context("Error reading history file")?;
+    Ok(parse_history(&String::from_utf8_lossy(&content))
+        .into_iter()
+        .map(|(cmd, usage)| Command {
+            usage,
+            ..Command::new(category, cmd, "")
+        })
+        .collect_vec())
+}
+
+/// Reads the commands of the markdown files within a directory
+fn read_markdown_commands(category: &str, dir: impl AsRef<Path>) -> Result<Vec<Command>> {
+    Ok(read_markdown_dir(dir)?
+        .into_iter()
+        .map(|(cmd, description)| Command::new(category, cmd, description))
+        .collect_vec())
+}
+
+/// Reads the connection commands of the hosts found on a ssh config file
+fn read_ssh_commands(category: &str, config_file: impl AsRef<Path>) -> Result<Vec<Command>> {
+    Ok(read_ssh_hosts(config_file)?
+        .into_iter()
+        .map(|(alias, cmd, description)| Command {
+            alias,
+            ..Command::new(category, cmd, description)
+        })
+        .collect_vec())
+}
+
+/// Reads the commands found on the help of a tool
+fn read_tool_commands(category: &str, tool: &str) -> Result<Vec<Command>> {
+    Ok(read_help_commands(tool)?
+        .into_iter()
+        .map(|(cmd, description)| Command::new(category, cmd, description))
+        .collect_vec())
+}
+
+/// Reads the commands produced by the importer of a plugin
+fn read_plugin_commands(category: &str, plugin: &str, location: Option<&str>) -> Result<Vec<Command>> {
+    Ok(import_plugin_commands(plugin, location)?
+        .into_iter()
+        .map(|c| Command {
+            alias: c.alias.filter(|alias| !alias.trim().is_empty()),
+            ..Command::new(category, c.command, c.description)
+        })
+        .collect_vec())
+}
+
+/// Escapes the special characters of a GLOB pattern, so they're matched literally
+fn escape_glob(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '*' | '?' | '[' => format!("[{c}]"),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Deletes the rows of a command within the given transaction, along with everything related to it
+fn delete_command_rows(tx: &Transaction, command_id: i64) -> Result<bool> {
+    let deleted = tx
+        .execute(r#"DELETE FROM command WHERE rowid = ?"#, [command_id])
+        .context("Error deleting command")?;
+    if deleted == 0 {
+        return Ok(false);
+    }
+    tx.execute(r#"DELETE FROM command_fts WHERE rowid = ?"#, [command_id])
+        .context("Error deleting command fts")?;
+    tx.execute(r#"DELETE FROM command_path_usage WHERE command_id = ?"#, [command_id])
+        .context("Error deleting command path usage")?;
+    tx.execute(r#"DELETE FROM command_learning WHERE command_id = ?"#, [command_id])
+        .context("Error deleting command learning")?;
+    tx.execute(r#"DELETE FROM command_pin WHERE command_id = ?"#, [command_id])
+        .context("Error deleting command pins")?;
+    tx.execute(r#"DELETE FROM execution_log WHERE command_id = ?"#, [command_id])
+        .context("Error deleting command execution log")?;
+    Ok(true)
+}
+
+/// Starts a write transaction, locking the database upfront so concurrent writers from other shells wait for each
+/// other instead of failing halfway. SQLite already retries with backoff for up to the busy timeout, so it only fails
+/// if the database is still locked after it.
+fn write_transaction(conn: &Connection) -> Result<Transaction<'_>> {
+    // The connection is always behind the storage lock, so there can't be any other transaction on it
+    match Transaction::new_unchecked(conn, TransactionBehavior::Immediate) {
+        Err(Error::SqliteFailure(err, msg))
+            if matches!(err.code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) =>
+        {
+            Err(Error::SqliteFailure(err, msg))
+                .context("Error starting a write transaction, the database is locked by another process")
+        }
+        res => res.context("Error starting a write transaction"),
+    }
+}
+
+/// Criteria to select the commands to be pruned, every given one must be met
+#[derive(Default)]
+pub struct PruneCriteria {
+    /// Commands never used or not used for this long
+    pub unused_for: Option<Duration>,
+    /// Commands of this category
+    pub category: Option<String>,
+    /// Commands matching this regex
+    pub pattern: Option<Regex>,
+    /// Redundant copies of commands that only differ on their whitespace, keeping the most used one
+    pub duplicates: bool,
+}
+
+/// Determines the directory where the data is stored, which can be overridden with `INTELLI_HOME`
+pub fn data_dir() -> Result<PathBuf> {
+    match env::var_os("INTELLI_HOME") {
+        Some(path) => Ok(path.into()),
+        None => Ok(ProjectDirs::from("org", "IntelliShell", "Intelli-Shell")
+            .context("Error initializing project dir")?
+            .data_dir()
+            .to_path_buf()),
+    }
+}
+
 /// Maps a [Command] from a [Row]
 fn command_from_row(row: &Row<'_>) -> rusqlite::Result<Command> {
     Ok(Command {
@@ -617,6 +2878,7 @@ fn command_from_row(row: &Row<'_>) -> rusqlite::Result<Command> {
         cmd: row.get(3)?,
         description: row.get(4)?,
         usage: row.get(5)?,
+        last_used: row.get(6)?,
     })
 }
 
@@ -632,6 +2894,8 @@ fn label_suggestion_from_row(row: &Row<'_>) -> rusqlite::Result<LabelSuggestion>
 
 impl Drop for SqliteStorage {
     fn drop(&mut self) {
+        // Persist any pending usage, the journal will be recovered on the next run otherwise
+        let _ = self.flush_usage();
         let conn = self.conn.lock().expect("poisoned lock");
         // Make sure pragma optimize does not take too long
         conn.pragma_update(None, "analysis_limit", "400")
@@ -643,10 +2907,523 @@ impl Drop for SqliteStorage {
 
 #[cfg(test)]
 mod tests {
-    use super::MIGRATIONS;
+    use super::*;
+
+    /// Inserts a command with the given usage and last used timestamp
+    fn insert_used(storage: &SqliteStorage, category: &str, cmd: &str, usage: u64, last_used: Option<u64>) -> i64 {
+        let mut command = Command::new(category, cmd, "");
+        command.usage = usage;
+        storage.insert_commands_usage(slice::from_mut(&mut command)).unwrap();
+        storage
+            .conn
+            .lock()
+            .unwrap()
+            .execute(
+                "UPDATE command SET last_used = ? WHERE rowid = ?",
+                (last_used, command.id),
+            )
+            .unwrap();
+        command.id
+    }
+
+    /// Retrieves the sorted ids of the commands to be pruned
+    fn prunable_ids(storage: &SqliteStorage, criteria: &PruneCriteria) -> Vec<i64> {
+        let mut ids = storage
+            .find_prunable_commands(criteria)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.id)
+            .collect_vec();
+        ids.sort();
+        ids
+    }
 
     #[test]
     fn migrations_test() {
         assert!(MIGRATIONS.validate().is_ok());
     }
+
+    #[test]
+    fn test_pin_and_unpin_command() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        let id = insert_used(&storage, USER_CATEGORY, "cargo test", 0, None);
+        storage.pin_command(id, "/home/user/project").unwrap();
+
+        let nested = Path::new("/home/user/project/src");
+        assert_eq!(storage.get_pinned_commands(nested).unwrap()[0].id, id);
+        assert!(!storage.unpin_command(id, "/home/user/other").unwrap());
+        assert!(storage.unpin_command(id, nested).unwrap());
+        assert!(storage.get_pinned_commands(nested).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_take_and_restore_command() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        let project = Path::new("/home/user/project");
+        let mut command = Command::new(USER_CATEGORY, "git status", "Show the working tree status");
+        storage.insert_command(&mut command).unwrap();
+        storage.increment_command_usage_in(command.id, Some(project)).unwrap();
+        storage.flush_usage().unwrap();
+        storage.pin_command(command.id, project).unwrap();
+        storage.learn_command(&command, 10, false).unwrap();
+        storage
+            .record_execution(command.id, Some(1), Duration::from_secs(2))
+            .unwrap();
+        let command = storage.find_command("git status").unwrap().unwrap();
+
+        let deleted = storage.take_command(&command).unwrap().unwrap();
+        assert!(storage.find_commands("status").unwrap().is_empty());
+        assert!(storage.get_pinned_commands(project).unwrap().is_empty());
+        assert!(storage.get_execution_stats(command.id).unwrap().is_none());
+
+        // The id of the deleted command is reused by the next one
+        let mut other = Command::new(USER_CATEGORY, "ls", "List files");
+        storage.insert_command(&mut other).unwrap();
+        assert_eq!(other.id, command.id);
+
+        let restored = storage.restore_command(&deleted).unwrap().unwrap();
+        assert_ne!(restored.id, command.id);
+        assert_eq!(restored.usage, 1);
+        assert_eq!(restored.last_used, command.last_used);
+        let found = storage.find_commands("status").unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, restored.id);
+        let pinned = storage.get_pinned_commands(project).unwrap();
+        assert_eq!(pinned.iter().map(|c| c.id).collect_vec(), vec![restored.id]);
+        let used = storage.get_commands_used_in(project, Duration::from_secs(60)).unwrap();
+        assert_eq!(used.iter().map(|c| c.id).collect_vec(), vec![restored.id]);
+        let learning = storage.get_learning_commands().unwrap();
+        assert_eq!(learning.iter().map(|(c, _)| c.id).collect_vec(), vec![restored.id]);
+        let stats = storage.get_execution_stats(restored.id).unwrap().unwrap();
+        assert_eq!((stats.runs, stats.failures), (1, 1));
+        // The rows of the command reusing the id are left untouched
+        assert!(storage.get_execution_stats(other.id).unwrap().is_none());
+
+        // It can't be restored twice
+        assert!(storage.restore_command(&deleted).unwrap().is_none());
+    }
+
+    /// Retrieves the categories stored, along with their number of commands
+    fn categories(storage: &SqliteStorage) -> Vec<(String, u64)> {
+        storage.get_categories().unwrap()
+    }
+
+    #[test]
+    fn test_rename_category() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        insert_used(&storage, "cloud", "kubectl get pods", 0, None);
+        insert_used(&storage, "cloud/aws", "aws s3 ls", 0, None);
+        insert_used(&storage, "cloud/aws/ec2", "aws ec2 describe-instances", 0, None);
+        insert_used(&storage, "cloudflare", "wrangler dev", 0, None);
+
+        assert_eq!(storage.rename_category(" cloud ", " infra ").unwrap(), 3);
+        assert_eq!(
+            categories(&storage),
+            vec![
+                ("cloudflare".into(), 1),
+                ("infra".into(), 1),
+                ("infra/aws".into(), 1),
+                ("infra/aws/ec2".into(), 1),
+            ]
+        );
+
+        // Renaming a nested category keeps its parent
+        assert_eq!(storage.rename_category("infra/aws", "infra/amazon").unwrap(), 2);
+        assert_eq!(
+            categories(&storage),
+            vec![
+                ("cloudflare".into(), 1),
+                ("infra".into(), 1),
+                ("infra/amazon".into(), 1),
+                ("infra/amazon/ec2".into(), 1),
+            ]
+        );
+
+        assert!(storage.rename_category("cloudflare", "infra").is_err());
+        assert!(storage.rename_category("infra/amazon", "cloudflare/amazon").is_ok());
+        assert!(storage.rename_category("cloudflare", "infra").is_err());
+        assert!(storage.rename_category("infra", "infra/old").is_err());
+        assert!(storage.rename_category(USER_CATEGORY, "mine").is_err());
+        assert!(storage.rename_category("missing", "other").is_err());
+    }
+
+    #[test]
+    fn test_merge_categories() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        insert_used(&storage, "aws", "aws s3 ls", 0, None);
+        insert_used(&storage, "aws/ec2", "aws ec2 describe-instances", 0, None);
+        insert_used(&storage, "gcp", "gsutil ls", 0, None);
+        insert_used(&storage, "cloud", "kubectl get pods", 0, None);
+        insert_used(&storage, "cloud/ec2", "aws ec2 start-instances", 0, None);
+
+        assert_eq!(storage.merge_categories(&["aws", " gcp"], "cloud ").unwrap(), 3);
+        assert_eq!(categories(&storage), vec![("cloud".into(), 3), ("cloud/ec2".into(), 2)]);
+
+        // Merging a category into itself is a no-op
+        assert_eq!(storage.merge_categories(&["cloud"], "cloud").unwrap(), 0);
+        assert!(storage.merge_categories(&["cloud"], "cloud/ec2").is_err());
+        assert!(storage.merge_categories(&["cloud"], LIBRARY_CATEGORY).is_err());
+        assert!(storage.merge_categories(&["missing"], "cloud").is_err());
+        assert!(storage.merge_categories(&["cloud"], " ").is_err());
+    }
+
+    /// Counts the rows of the given table
+    fn count_rows(storage: &SqliteStorage, table: &str) -> i64 {
+        storage
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(&format!("SELECT count(*) FROM {table}"), [], |r| r.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_merge_equivalent_commands() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        let project = Path::new("/home/user/project");
+        let stored = insert_used(&storage, USER_CATEGORY, "git commit -m 'fix'", 1, None);
+        storage.increment_command_usage_in(stored, Some(project)).unwrap();
+        storage.flush_usage().unwrap();
+        storage.pin_command(stored, project).unwrap();
+
+        let imported = |cmd: &str, description: &str, alias: Option<&str>, usage: u64| Command {
+            alias: alias.map(ToOwned::to_owned),
+            usage,
+            ..Command::new(USER_CATEGORY, cmd, description)
+        };
+        let stats = storage
+            .insert_imported_commands(
+                vec![
+                    imported("git  commit -m \"fix\"", "Commit a fix", Some("gcf"), 5),
+                    imported("ls  -la", "", None, 2),
+                    imported("ls -la", "List every file", Some("ll"), 3),
+                ],
+                true,
+            )
+            .unwrap();
+        assert_eq!((stats.new, stats.merged), (1, 2));
+
+        // The duplicates are never stored, so there are no rows of their own
+        assert_eq!(storage.len().unwrap(), 2);
+        assert_eq!(count_rows(&storage, "command_fts"), 2);
+        assert_eq!(count_rows(&storage, "command_pin"), 1);
+        assert_eq!(count_rows(&storage, "command_path_usage"), 1);
+
+        // The merged data is kept on the stored command, along with its pins and usage per path
+        let commit = storage.find_command("gcf").unwrap().unwrap();
+        assert_eq!(commit.id, stored);
+        assert_eq!(commit.cmd, "git commit -m 'fix'");
+        assert_eq!(commit.usage, 5);
+        assert_eq!(commit.description, "Commit a fix");
+        assert_eq!(storage.find_commands("commit a fix").unwrap()[0].id, stored);
+        assert_eq!(storage.get_pinned_commands(project).unwrap()[0].id, stored);
+        let used = storage.get_commands_used_in(project, Duration::from_secs(60)).unwrap();
+        assert_eq!(used[0].id, stored);
+
+        // And on the first imported one, when equivalent to a previous imported command
+        let ls = storage.find_command("ll").unwrap().unwrap();
+        assert_eq!(ls.cmd, "ls  -la");
+        assert_eq!(ls.usage, 5);
+        assert_eq!(ls.description, "List every file");
+        assert_eq!(storage.find_commands("every file").unwrap()[0].id, ls.id);
+    }
+
+    #[test]
+    fn test_sync_library() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        let file = env::temp_dir().join(format!("intelli-shell-library-{}.txt", uuid::Uuid::new_v4()));
+        insert_used(&storage, USER_CATEGORY, "docker ps", 0, None);
+        fs::write(
+            &file,
+            "ls -la ## List files\ngit status ## Show status\ndocker ps ## Shared\n",
+        )
+        .unwrap();
+
+        assert!(storage.sync_library(&file).unwrap());
+        assert!(!storage.sync_library(&file).unwrap());
+        let library = storage.get_commands(LIBRARY_CATEGORY).unwrap();
+        assert_eq!(
+            library.iter().map(|c| c.cmd.as_str()).sorted().collect_vec(),
+            vec!["git status", "ls -la"]
+        );
+        // The commands on other categories are left untouched
+        assert_eq!(
+            storage.find_command("docker ps").unwrap().unwrap().category,
+            USER_CATEGORY
+        );
+
+        // The library commands are read-only
+        let ls = storage.find_command("ls -la").unwrap().unwrap();
+        assert!(storage.delete_command(ls.id).is_err());
+        assert!(storage.update_command(&ls).is_err());
+        assert!(storage.set_command_category(ls.id, USER_CATEGORY).is_err());
+
+        fs::write(&file, "ls -la ## List every file\n").unwrap();
+        assert!(storage.sync_library(&file).unwrap());
+        let library = storage.get_commands(LIBRARY_CATEGORY).unwrap();
+        assert_eq!(library.len(), 1);
+        assert_eq!(library[0].id, ls.id);
+        assert_eq!(library[0].description, "List every file");
+        assert_eq!(count_rows(&storage, "command_fts"), 2);
+
+        let _ = fs::remove_file(&file);
+        assert!(storage.sync_library(&file).is_err());
+    }
+
+    #[test]
+    fn test_sync_vault() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        let dir = env::temp_dir().join(format!("intelli-shell-vault-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("git.md"),
+            "# Status\n```sh\ngit status\n```\n# Log\n```sh\ngit log\n```\n",
+        )
+        .unwrap();
+
+        assert!(storage.sync_vault(&dir).unwrap());
+        assert!(!storage.sync_vault(&dir).unwrap());
+        let vault = storage.get_commands(VAULT_CATEGORY).unwrap();
+        assert_eq!(
+            vault.iter().map(|c| c.cmd.as_str()).sorted().collect_vec(),
+            vec!["git log", "git status"]
+        );
+        let status = storage.find_command("git status").unwrap().unwrap();
+        assert_eq!(status.description, "Status #git");
+        storage.pin_command(status.id, &dir).unwrap();
+
+        fs::write(dir.join("git.md"), "# Log\n```sh\ngit log\n```\n").unwrap();
+        assert!(storage.sync_vault(&dir).unwrap());
+        let vault = storage.get_commands(VAULT_CATEGORY).unwrap();
+        assert_eq!(vault.iter().map(|c| c.cmd.as_str()).collect_vec(), vec!["git log"]);
+        assert_eq!(count_rows(&storage, "command_fts"), 1);
+        assert_eq!(count_rows(&storage, "command_pin"), 0);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_label_suggestions_per_profile() {
+        let suggestion = |value: &str, usage: u64| LabelSuggestion {
+            flat_root_cmd: "ssh".into(),
+            flat_label: "host".into(),
+            suggestion: value.into(),
+            usage,
+        };
+        let suggested = |storage: &SqliteStorage, context: &[(String, String)]| {
+            storage
+                .find_suggestions_for("ssh", "host", context)
+                .unwrap()
+                .into_iter()
+                .map(|s| s.suggestion)
+                .collect_vec()
+        };
+        let context = [("user".to_owned(), "admin".to_owned())];
+
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        storage
+            .insert_label_suggestion(&suggestion("personal-server", 1))
+            .unwrap();
+        storage
+            .set_provider_cache("hosts", &["personal-server".into()])
+            .unwrap();
+
+        let storage = storage.with_profile("work");
+        assert!(storage.get_label_suggestions().unwrap().is_empty());
+        assert!(suggested(&storage, &[]).is_empty());
+        assert!(
+            storage
+                .get_provider_cache("hosts", Duration::from_secs(60))
+                .unwrap()
+                .is_none()
+        );
+
+        storage.insert_label_suggestion(&suggestion("work-server", 0)).unwrap();
+        storage.insert_label_suggestion(&suggestion("work-bastion", 2)).unwrap();
+        storage
+            .increment_label_suggestion_usage(&suggestion("work-server", 0))
+            .unwrap();
+        storage.flush_usage().unwrap();
+        storage
+            .record_label_context("ssh", "host", "work-server", &context)
+            .unwrap();
+        assert_eq!(suggested(&storage, &[]), vec!["work-bastion", "work-server"]);
+        assert_eq!(suggested(&storage, &context), vec!["work-server", "work-bastion"]);
+        let work_server = storage.get_label_suggestions().unwrap().remove(1);
+        assert_eq!((work_server.suggestion.as_str(), work_server.usage), ("work-server", 1));
+
+        let storage = storage.with_profile(DEFAULT_PROFILE);
+        assert_eq!(suggested(&storage, &context), vec!["personal-server"]);
+        assert!(!storage.delete_label_suggestion(&suggestion("work-server", 1)).unwrap());
+        assert_eq!(
+            storage.get_provider_cache("hosts", Duration::from_secs(60)).unwrap(),
+            Some(vec!["personal-server".to_owned()])
+        );
+    }
+
+    #[test]
+    fn test_take_and_restore_label_suggestion() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        let suggestion = |value: &str| LabelSuggestion {
+            flat_root_cmd: "ssh".into(),
+            flat_label: "host".into(),
+            suggestion: value.into(),
+            usage: 1,
+        };
+        let suggested = |context: &[(String, String)]| {
+            storage
+                .find_suggestions_for("ssh", "host", context)
+                .unwrap()
+                .into_iter()
+                .map(|s| s.suggestion)
+                .collect_vec()
+        };
+        let context = [("user".to_owned(), "admin".to_owned())];
+        storage.insert_label_suggestion(&suggestion("bastion")).unwrap();
+        storage.insert_label_suggestion(&suggestion("server")).unwrap();
+        storage.record_label_context("ssh", "host", "server", &context).unwrap();
+        storage
+            .record_label_context("ssh", "host", "bastion", &context)
+            .unwrap();
+        storage.record_label_context("ssh", "host", "server", &context).unwrap();
+        assert_eq!(suggested(&context), vec!["server", "bastion"]);
+
+        let deleted = storage.take_label_suggestion(&suggestion("server")).unwrap().unwrap();
+        assert_eq!(suggested(&context), vec!["bastion"]);
+        assert_eq!(count_rows(&storage, "label_suggestion_context"), 1);
+        assert!(storage.take_label_suggestion(&suggestion("server")).unwrap().is_none());
+
+        assert!(storage.restore_label_suggestion(&deleted).unwrap());
+        assert_eq!(suggested(&context), vec!["server", "bastion"]);
+        assert_eq!(count_rows(&storage, "label_suggestion_context"), 2);
+        assert!(!storage.restore_label_suggestion(&deleted).unwrap());
+
+        assert!(storage.delete_label_suggestion(&suggestion("bastion")).unwrap());
+        assert_eq!(suggested(&context), vec!["server"]);
+        assert_eq!(count_rows(&storage, "label_suggestion_context"), 1);
+    }
+
+    #[test]
+    fn test_prune_unused_for() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        let now = unix_timestamp();
+        let day = 24 * 60 * 60;
+        let never_used = insert_used(&storage, USER_CATEGORY, "ls", 0, None);
+        let used_long_ago = insert_used(&storage, USER_CATEGORY, "cd", 3, Some(now - 30 * day));
+        insert_used(&storage, USER_CATEGORY, "pwd", 3, Some(now - day));
+        // Used before the last usage was tracked
+        insert_used(&storage, USER_CATEGORY, "cat", 5, None);
+
+        let criteria = PruneCriteria {
+            unused_for: Some(Duration::from_secs(7 * day)),
+            ..Default::default()
+        };
+        assert_eq!(prunable_ids(&storage, &criteria), vec![never_used, used_long_ago]);
+    }
+
+    #[test]
+    fn test_prune_category() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        let cloud = insert_used(&storage, "cloud", "kubectl get pods", 0, None);
+        let aws = insert_used(&storage, "cloud/aws", "aws s3 ls", 0, None);
+        insert_used(&storage, "cloudflare", "wrangler dev", 0, None);
+        insert_used(&storage, USER_CATEGORY, "ls", 0, None);
+
+        let criteria = PruneCriteria {
+            category: Some("cloud".into()),
+            ..Default::default()
+        };
+        assert_eq!(prunable_ids(&storage, &criteria), vec![cloud, aws]);
+    }
+
+    #[test]
+    fn test_prune_pattern() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        let git_status = insert_used(&storage, USER_CATEGORY, "git status", 0, None);
+        insert_used(&storage, USER_CATEGORY, "ls -la", 0, None);
+
+        let criteria = PruneCriteria {
+            pattern: Some(Regex::new("^git ").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(prunable_ids(&storage, &criteria), vec![git_status]);
+    }
+
+    #[test]
+    fn test_prune_duplicates() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        let less_used = insert_used(&storage, USER_CATEGORY, "git  status", 1, None);
+        let most_used = insert_used(&storage, USER_CATEGORY, "git status", 5, None);
+        let unused = insert_used(&storage, USER_CATEGORY, " git status ", 0, None);
+        insert_used(&storage, USER_CATEGORY, "ls", 0, None);
+
+        let criteria = PruneCriteria {
+            duplicates: true,
+            ..Default::default()
+        };
+        assert_eq!(prunable_ids(&storage, &criteria), vec![less_used, unused]);
+
+        assert_eq!(storage.prune_commands(&criteria).unwrap(), 2);
+        let remaining = storage.get_commands(USER_CATEGORY).unwrap();
+        assert_eq!(remaining.len(), 2);
+        assert!(remaining.iter().any(|c| c.id == most_used));
+        assert!(prunable_ids(&storage, &criteria).is_empty());
+    }
+
+    #[test]
+    fn test_prune_unused_tldr_commands() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        let unused = insert_used(&storage, "common", "tar -xf {{file}}", 0, None);
+        let used = insert_used(&storage, "common", "tar -czf {{file}}", 2, Some(unix_timestamp()));
+        insert_used(&storage, USER_CATEGORY, "ls", 0, None);
+        storage.pin_command(unused, "/tmp").unwrap();
+        storage
+            .record_execution(unused, Some(0), Duration::from_secs(1))
+            .unwrap();
+
+        assert_eq!(storage.prune_unused_commands(None).unwrap(), 1);
+        assert_eq!(storage.len().unwrap(), 2);
+        assert_eq!(storage.get_commands("common").unwrap()[0].id, used);
+        let related: i64 = storage
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT (SELECT count(*) FROM command_pin) + (SELECT count(*) FROM execution_log)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(related, 0);
+    }
+
+    #[test]
+    fn test_prune_every_criteria() {
+        let storage = SqliteStorage::new_in_memory().unwrap();
+        let day = 24 * 60 * 60;
+        let pruned = insert_used(&storage, "cloud/aws", "aws s3 ls", 0, None);
+        let now = unix_timestamp();
+        insert_used(&storage, "cloud/aws", "aws s3 ls --recursive", 5, Some(now));
+        insert_used(&storage, "cloud/gcp", "gsutil ls", 0, None);
+        insert_used(&storage, "cloud/aws", "kubectl get pods", 0, None);
+
+        let criteria = PruneCriteria {
+            unused_for: Some(Duration::from_secs(day)),
+            category: Some("cloud/aws".into()),
+            pattern: Some(Regex::new("^aws ").unwrap()),
+            duplicates: false,
+        };
+        assert_eq!(prunable_ids(&storage, &criteria), vec![pruned]);
+        assert_eq!(storage.prune_commands(&criteria).unwrap(), 1);
+        assert_eq!(storage.len().unwrap(), 3);
+        let fts: i64 = storage
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT count(*) FROM command_fts WHERE rowid = ?", [pruned], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(fts, 0);
+    }
 }
@@ -1,10 +1,20 @@
-use ratatui::style::Color;
+use std::{env, fmt::Display, str::FromStr};
+
+use anyhow::bail;
+use itertools::Itertools;
+use ratatui::{
+    style::{Color, Style},
+    widgets::{Block, BorderType, Borders},
+};
 
 pub const LIGHT: Theme = Theme {
     secondary: Color::Gray,
     selected_background: Color::Gray,
     alias: Color::Yellow,
     description: Color::Rgb(0, 128, 0),
+    highlight: Color::Blue,
+    border: Color::Reset,
+    border_type: BorderType::Plain,
 };
 
 pub const DARK: Theme = Theme {
@@ -12,6 +22,29 @@ pub const DARK: Theme = Theme {
     selected_background: Color::Rgb(154, 154, 154),
     alias: Color::Yellow,
     description: Color::Rgb(71, 105, 56),
+    highlight: Color::Cyan,
+    border: Color::Reset,
+    border_type: BorderType::Plain,
+};
+
+pub const HIGH_CONTRAST_LIGHT: Theme = Theme {
+    secondary: Color::Rgb(80, 80, 80),
+    selected_background: Color::Rgb(200, 200, 200),
+    alias: Color::Rgb(0, 90, 160),
+    description: Color::Rgb(0, 110, 80),
+    highlight: Color::Rgb(180, 60, 0),
+    border: Color::Black,
+    border_type: BorderType::Thick,
+};
+
+pub const HIGH_CONTRAST_DARK: Theme = Theme {
+    secondary: Color::Rgb(200, 200, 200),
+    selected_background: Color::Rgb(0, 70, 140),
+    alias: Color::Rgb(240, 228, 66),
+    description: Color::Rgb(86, 180, 233),
+    highlight: Color::Rgb(230, 159, 0),
+    border: Color::White,
+    border_type: BorderType::Thick,
 };
 
 #[derive(Clone, Copy)]
@@ -20,4 +53,385 @@ pub struct Theme {
     pub selected_background: Color,
     pub alias: Color,
     pub description: Color,
+    pub highlight: Color,
+    pub border: Color,
+    pub border_type: BorderType,
+}
+
+impl Theme {
+    /// Overrides the type of the borders of this theme
+    pub fn with_border(mut self, border: Option<ThemeBorder>) -> Self {
+        if let Some(border) = border {
+            self.border_type = border.into();
+        }
+        self
+    }
+
+    /// Builds a block with the given borders, styled after this theme
+    pub fn block(self, borders: Borders) -> Block<'static> {
+        Block::default()
+            .borders(borders)
+            .border_type(self.border_type)
+            .border_style(Style::default().fg(self.border))
+    }
+}
+
+/// Type of the borders drawn around the widgets when the interface isn't inline
+#[derive(Clone, Copy)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum ThemeBorder {
+    Plain,
+    Rounded,
+    Double,
+    Thick,
+}
+
+impl FromStr for ThemeBorder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(ThemeBorder::Plain),
+            "rounded" => Ok(ThemeBorder::Rounded),
+            "double" => Ok(ThemeBorder::Double),
+            "thick" => Ok(ThemeBorder::Thick),
+            _ => bail!("Expected one of: plain, rounded, double, thick"),
+        }
+    }
+}
+
+impl From<ThemeBorder> for BorderType {
+    fn from(border: ThemeBorder) -> Self {
+        match border {
+            ThemeBorder::Plain => BorderType::Plain,
+            ThemeBorder::Rounded => BorderType::Rounded,
+            ThemeBorder::Double => BorderType::Double,
+            ThemeBorder::Thick => BorderType::Thick,
+        }
+    }
+}
+
+/// Theme variant to be used
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum ThemeVariant {
+    /// Detects the terminal background to pick the light or dark variant
+    #[default]
+    Auto,
+    Light,
+    Dark,
+    HighContrastLight,
+    HighContrastDark,
+}
+
+impl FromStr for ThemeVariant {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ThemeVariant::Auto),
+            "light" => Ok(ThemeVariant::Light),
+            "dark" => Ok(ThemeVariant::Dark),
+            "high-contrast-light" => Ok(ThemeVariant::HighContrastLight),
+            "high-contrast-dark" => Ok(ThemeVariant::HighContrastDark),
+            _ => bail!("Expected one of: auto, light, dark, high-contrast-light, high-contrast-dark"),
+        }
+    }
+}
+
+impl ThemeVariant {
+    /// Retrieves the theme of this variant, detecting the terminal background when [Auto](ThemeVariant::Auto)
+    pub fn theme(self) -> Theme {
+        match self.resolve() {
+            ThemeVariant::Light => LIGHT,
+            ThemeVariant::HighContrastLight => HIGH_CONTRAST_LIGHT,
+            ThemeVariant::HighContrastDark => HIGH_CONTRAST_DARK,
+            _ => DARK,
+        }
+    }
+
+    /// Checks the theme of this variant against the color vision deficiencies, warning about the pairs of colors
+    /// that are hard to tell apart
+    pub fn check(self) -> Vec<String> {
+        let variant = self.resolve();
+        let light = matches!(variant, ThemeVariant::Light | ThemeVariant::HighContrastLight);
+        let mut warnings = check_theme(variant.theme(), light);
+        if !warnings.is_empty() {
+            let alternative = if light {
+                "high-contrast-light"
+            } else {
+                "high-contrast-dark"
+            };
+            if !matches!(
+                variant,
+                ThemeVariant::HighContrastLight | ThemeVariant::HighContrastDark
+            ) {
+                warnings.push(format!(
+                    "Consider the accessible '{alternative}' theme, by setting INTELLI_THEME={alternative}"
+                ));
+            }
+        }
+        warnings
+    }
+
+    /// Resolves the [Auto](ThemeVariant::Auto) variant by detecting the terminal background
+    fn resolve(self) -> ThemeVariant {
+        match self {
+            ThemeVariant::Auto => match env::var("COLORFGBG").ok().and_then(|v| is_light_background(&v)) {
+                Some(true) => ThemeVariant::Light,
+                _ => ThemeVariant::Dark,
+            },
+            variant => variant,
+        }
+    }
+}
+
+impl Display for ThemeVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThemeVariant::Auto => write!(f, "auto"),
+            ThemeVariant::Light => write!(f, "light"),
+            ThemeVariant::Dark => write!(f, "dark"),
+            ThemeVariant::HighContrastLight => write!(f, "high-contrast-light"),
+            ThemeVariant::HighContrastDark => write!(f, "high-contrast-dark"),
+        }
+    }
+}
+
+/// Minimum contrast ratio of the text over its background, as recommended by WCAG
+const MIN_CONTRAST: f64 = 4.5;
+
+/// Minimum perceptual difference (CIE76) between two colors of text to tell them apart
+const MIN_DIFFERENCE: f64 = 20.0;
+
+/// Color vision simulated when checking a theme
+#[derive(Clone, Copy)]
+enum ColorVision {
+    Normal,
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+impl ColorVision {
+    const ALL: [ColorVision; 4] = [
+        ColorVision::Normal,
+        ColorVision::Protanopia,
+        ColorVision::Deuteranopia,
+        ColorVision::Tritanopia,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            ColorVision::Normal => "normal vision",
+            ColorVision::Protanopia => "protanopia",
+            ColorVision::Deuteranopia => "deuteranopia",
+            ColorVision::Tritanopia => "tritanopia",
+        }
+    }
+
+    /// Simulates how a linear RGB color is perceived, with the matrices of Machado et al. (2009) at full severity
+    fn simulate(self, [r, g, b]: [f64; 3]) -> [f64; 3] {
+        let m = match self {
+            ColorVision::Normal => return [r, g, b],
+            ColorVision::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            ColorVision::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            ColorVision::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        };
+        m.map(|row| (row[0] * r + row[1] * g + row[2] * b).clamp(0.0, 1.0))
+    }
+}
+
+/// Checks the colors of a theme, to be rendered on a light or dark terminal, returning the warnings found
+fn check_theme(theme: Theme, light: bool) -> Vec<String> {
+    let (text, background) = if light {
+        (Color::Black, Color::White)
+    } else {
+        (Color::White, Color::Black)
+    };
+    let over_background = [
+        ("secondary text", theme.secondary, "the background", background),
+        ("aliases", theme.alias, "the background", background),
+        ("descriptions", theme.description, "the background", background),
+        ("highlighted matches", theme.highlight, "the background", background),
+        ("selected commands", text, "their background", theme.selected_background),
+    ];
+    let side_by_side = [
+        ("highlighted matches", theme.highlight, "commands", text),
+        (
+            "highlighted matches",
+            theme.highlight,
+            "descriptions",
+            theme.description,
+        ),
+    ];
+
+    let mut warnings = Vec::new();
+    for (name, fg, other, bg) in over_background {
+        let failing = ColorVision::ALL
+            .into_iter()
+            .map(|vision| {
+                (
+                    vision,
+                    contrast(vision.simulate(linear_rgb(fg)), vision.simulate(linear_rgb(bg))),
+                )
+            })
+            .filter(|(_, contrast)| *contrast < MIN_CONTRAST)
+            .collect_vec();
+        if let Some(min) = failing.iter().map(|(_, c)| *c).reduce(f64::min) {
+            warnings.push(format!(
+                "Hard to read {name} over {other} with {} (contrast of {min:.1}:1, {MIN_CONTRAST}:1 recommended)",
+                failing.iter().map(|(v, _)| v.name()).join(", ")
+            ));
+        }
+    }
+    for (name, a, other, b) in side_by_side {
+        let failing = ColorVision::ALL
+            .into_iter()
+            .map(|vision| {
+                (
+                    vision,
+                    difference(vision.simulate(linear_rgb(a)), vision.simulate(linear_rgb(b))),
+                )
+            })
+            .filter(|(_, difference)| *difference < MIN_DIFFERENCE)
+            .collect_vec();
+        if let Some(min) = failing.iter().map(|(_, d)| *d).reduce(f64::min) {
+            warnings.push(format!(
+                "Hard to tell {name} apart from {other} with {} (difference of {min:.0}, {MIN_DIFFERENCE} recommended)",
+                failing.iter().map(|(v, _)| v.name()).join(", ")
+            ));
+        }
+    }
+    warnings
+}
+
+/// Converts a color into linear RGB, assuming the default xterm palette for the indexed ones
+fn linear_rgb(color: Color) -> [f64; 3] {
+    const ANSI: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (205, 0, 0),
+        (0, 205, 0),
+        (205, 205, 0),
+        (0, 0, 238),
+        (205, 0, 205),
+        (0, 205, 205),
+        (229, 229, 229),
+        (127, 127, 127),
+        (255, 0, 0),
+        (0, 255, 0),
+        (255, 255, 0),
+        (92, 92, 255),
+        (255, 0, 255),
+        (0, 255, 255),
+        (255, 255, 255),
+    ];
+    let (r, g, b) = match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(i @ 0..=15) => ANSI[i as usize],
+        Color::Indexed(i @ 16..=231) => {
+            let level = |l: u8| if l == 0 { 0 } else { 55 + l * 40 };
+            let i = i - 16;
+            (level(i / 36), level((i / 6) % 6), level(i % 6))
+        }
+        Color::Indexed(i) => {
+            let gray = 8 + (i - 232) * 10;
+            (gray, gray, gray)
+        }
+        Color::Black | Color::Reset => ANSI[0],
+        Color::Red => ANSI[1],
+        Color::Green => ANSI[2],
+        Color::Yellow => ANSI[3],
+        Color::Blue => ANSI[4],
+        Color::Magenta => ANSI[5],
+        Color::Cyan => ANSI[6],
+        Color::Gray => ANSI[7],
+        Color::DarkGray => ANSI[8],
+        Color::LightRed => ANSI[9],
+        Color::LightGreen => ANSI[10],
+        Color::LightYellow => ANSI[11],
+        Color::LightBlue => ANSI[12],
+        Color::LightMagenta => ANSI[13],
+        Color::LightCyan => ANSI[14],
+        Color::White => ANSI[15],
+    };
+    [r, g, b].map(|c| {
+        let c = c as f64 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    })
+}
+
+/// Computes the WCAG contrast ratio between two linear RGB colors
+fn contrast(a: [f64; 3], b: [f64; 3]) -> f64 {
+    let luminance = |[r, g, b]: [f64; 3]| 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Computes the perceptual difference (CIE76) between two linear RGB colors
+fn difference(a: [f64; 3], b: [f64; 3]) -> f64 {
+    let lab = |[r, g, b]: [f64; 3]| {
+        // Linear RGB into XYZ, relative to the D65 white point
+        let xyz = [
+            (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047,
+            0.2126 * r + 0.7152 * g + 0.0722 * b,
+            (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883,
+        ];
+        let [x, y, z] = xyz.map(|t| {
+            if t > 0.008856 {
+                t.cbrt()
+            } else {
+                7.787 * t + 16.0 / 116.0
+            }
+        });
+        [116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z)]
+    };
+    let (a, b) = (lab(a), lab(b));
+    a.iter().zip(b).map(|(a, b)| (a - b).powi(2)).sum::<f64>().sqrt()
+}
+
+/// Determines if the background is light from a `COLORFGBG` value (like `15;0` or `0;default;15`), whose last
+/// component is the ANSI color of the background
+fn is_light_background(colorfgbg: &str) -> Option<bool> {
+    let bg: u8 = colorfgbg.rsplit(';').next()?.trim().parse().ok()?;
+    Some(matches!(bg, 7 | 9..=15))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_light_background() {
+        assert_eq!(is_light_background("15;0"), Some(false));
+        assert_eq!(is_light_background("0;default;15"), Some(true));
+        assert_eq!(is_light_background("0;7"), Some(true));
+        assert_eq!(is_light_background("default"), None);
+    }
+
+    #[test]
+    fn test_check_theme() {
+        assert!(check_theme(HIGH_CONTRAST_LIGHT, true).is_empty());
+        assert!(check_theme(HIGH_CONTRAST_DARK, false).is_empty());
+        assert!(check_theme(LIGHT, true)
+            .iter()
+            .any(|w| w.starts_with("Hard to read aliases over the background")));
+        assert!(check_theme(HIGH_CONTRAST_DARK, true).len() > 1);
+    }
 }
@@ -1,12 +1,13 @@
 use std::{fs, path::Path};
 
-use anyhow::{bail, Context, Error, Result};
+use anyhow::{Context, Error, Result};
 use git2::build::{CheckoutBuilder, RepoBuilder};
 use once_cell::sync::Lazy;
 use regex::Regex;
 
 use crate::{
     cfg::{cfg_android, cfg_macos, cfg_unix, cfg_windows},
+    error::UserFacingError,
     model::Command,
 };
 
@@ -35,7 +36,7 @@ pub fn scrape_tldr_repo(url: impl AsRef<str>, category: Option<&str>) -> Result<
     match category {
         Some(category) => {
             if !repo_path.join("pages").join(category).exists() {
-                bail!("Category {category} doesn't exist")
+                return Err(UserFacingError::not_found(format!("Category {category} doesn't exist")).into());
             }
             result.append(&mut parse_tldr_folder(
                 category,